@@ -0,0 +1,112 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use intel_8080_kit::emu::{bus::Bus, Cpu, Emulator, FlatMemory, Memory};
+use std::hint::black_box;
+
+/// Instructions run per iteration, throughput is reported in millions of
+/// instructions per second.
+const STEPS: u64 = 100_000;
+
+/// Endless loop of loads, stores, arithmetic, calls and jumps.
+#[rustfmt::skip]
+const PROGRAM: &[u8] = &[
+    0x31, 0x00, 0x10,
+    0x21, 0x00, 0x20,
+    0x06, 0x00,
+    0x7e, 0x80, 0xa9, 0x04, 0x77, 0x23, 0xcd, 0x18, 0x00,
+    0x0d, 0xc2, 0x08, 0x00, 0xc3, 0x03, 0x00,
+    0xe6, 0x7f, 0x17, 0x27, 0xc9,
+];
+
+/// Plain 64K of memory behind the `Memory` trait.
+struct Plain(Box<[u8; 0x10000]>);
+
+impl Memory for Plain {
+    fn read_byte(&self, addr: u16) -> u8 {
+        self.0[addr as usize]
+    }
+
+    fn read_word(&self, addr: u16) -> u16 {
+        u16::from_le_bytes([self.read_byte(addr), self.read_byte(addr.wrapping_add(1))])
+    }
+
+    fn write_byte(&mut self, addr: u16, byte: u8) {
+        self.0[addr as usize] = byte;
+    }
+
+    fn write_word(&mut self, addr: u16, word: u16) {
+        let [lo, hi] = word.to_le_bytes();
+        self.write_byte(addr, lo);
+        self.write_byte(addr.wrapping_add(1), hi);
+    }
+
+    fn in_port(&mut self, _port: u8) -> u8 {
+        0
+    }
+
+    fn out_port(&mut self, _port: u8, _byte: u8) {}
+}
+
+fn plain(cpu: Cpu) -> Emulator {
+    let mut ram = Box::new([0; 0x10000]);
+    ram[..PROGRAM.len()].copy_from_slice(PROGRAM);
+    Emulator::with_cpu(Box::new(Plain(ram)), cpu)
+}
+
+/// Ports of the flat RAM.
+struct NoPorts;
+
+impl Memory for NoPorts {
+    fn read_byte(&self, _addr: u16) -> u8 {
+        0
+    }
+
+    fn read_word(&self, _addr: u16) -> u16 {
+        0
+    }
+
+    fn write_byte(&mut self, _addr: u16, _byte: u8) {}
+
+    fn write_word(&mut self, _addr: u16, _word: u16) {}
+
+    fn in_port(&mut self, _port: u8) -> u8 {
+        0
+    }
+
+    fn out_port(&mut self, _port: u8, _byte: u8) {}
+}
+
+fn flat(cpu: Cpu) -> Emulator {
+    Emulator::with_flat(FlatMemory::new(PROGRAM, Box::new(NoPorts)), cpu)
+}
+
+fn bus(cpu: Cpu) -> Emulator {
+    let mut bus = Bus::new();
+    bus.map_ram(0, 0x10000);
+    bus.load(0, PROGRAM);
+    Emulator::with_cpu(Box::new(bus), cpu)
+}
+
+type Board = fn(Cpu) -> Emulator;
+
+fn steps(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mips");
+    group.throughput(Throughput::Elements(STEPS));
+
+    let boards: [(&str, Board); 3] = [("flat", flat), ("plain", plain), ("bus", bus)];
+    for (name, board) in boards {
+        for cpu in [Cpu::I8080, Cpu::I8085, Cpu::Z80] {
+            let mut emu = board(cpu);
+            group.bench_function(format!("{}/{:?}", name, cpu), |b| {
+                b.iter(|| {
+                    for _ in 0..STEPS {
+                        black_box(emu.step());
+                    }
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, steps);
+criterion_main!(benches);
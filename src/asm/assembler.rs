@@ -0,0 +1,1174 @@
+use super::diag::{Diagnostic, Severity, Span};
+use super::expr::{eval, fit, symbols, EvalError};
+use super::macros::{block_end, directive, substitute, Macro, BLOCKS};
+use super::parser::{parse, parse_at, Expr, ExprKind, Ident, Line, Statement, StatementKind};
+use super::source::SourceMap;
+use super::{opcode, Opcode, INSTRUCTIONS};
+use std::{
+    collections::HashMap,
+    fs, io, iter,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Label,
+    /// Constant from `equ`
+    Equ,
+    /// Variable from `set`, it can be redefined with `set`
+    Set,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    /// `None` until an `equ` referring to later symbols is resolved
+    pub value: Option<i64>,
+    /// First definition
+    pub span: Span,
+    pub kind: SymbolKind,
+    /// Spans of the uses, in the order of the sources
+    pub uses: Vec<Span>,
+}
+
+/// Line of the sources as it was assembled, expansions included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedLine {
+    pub span: Span,
+    /// Address of the line, `None` without a label or statement, or when
+    /// skipped by a condition
+    pub address: Option<u16>,
+    /// Value of `equ` and `set`
+    pub value: Option<i64>,
+    /// Opcodes of the line in `Program::ops`
+    pub ops: Range<usize>,
+    /// Nesting of the macro and repeat expansions, 0 for lines as written
+    pub depth: usize,
+}
+
+/// Operand of `out[index]` referring to a label defined later, evaluated
+/// at the end with `here` as `$` and the values `sets` had at its use.
+/// Without `op` it is a `db` or `dw` value.
+struct Fixup {
+    index: usize,
+    op: Option<u8>,
+    bits: u32,
+    expr: Expr,
+    here: u16,
+    sets: HashMap<String, i64>,
+}
+
+/// Nesting limit of the macro and repeat expansions.
+const MAX_DEPTH: usize = 64;
+
+/// Expansion notes after an error, the outer expansions are summed up in the
+/// last one.
+const MAX_NOTES: usize = 8;
+
+/// Register names in the operands, they can't be symbols.
+const REGISTERS: &[&str] = &["a", "b", "c", "d", "e", "h", "l", "m", "sp", "psw"];
+
+/// Key of the symbol, macro or parameter `name`, they are case insensitive.
+fn key(name: &str) -> String {
+    name.to_ascii_lowercase()
+}
+
+/// State of an `if` until its `endif`.
+struct Cond {
+    /// The lines are assembled, ignoring the enclosing conditions
+    active: bool,
+    /// A branch was taken, or the condition is inside a false one
+    done: bool,
+    seen_else: bool,
+    span: Span,
+}
+
+struct Assembler<'a> {
+    sources: SourceMap,
+    /// Directories searched for the included files
+    include: &'a [PathBuf],
+    /// Canonical path and name of the files being assembled, innermost last
+    including: Vec<(PathBuf, String)>,
+    diags: &'a mut Vec<Diagnostic>,
+    out: Vec<Opcode>,
+    pc: u16,
+    /// `$`, the address of the start of the current statement
+    here: u16,
+    symbols: HashMap<String, Symbol>,
+    fixups: Vec<Fixup>,
+    /// `equ` referring to symbols defined later, with `$`
+    pending: Vec<(Ident, Expr, u16)>,
+    /// Symbols used before their definition where the value was needed
+    early: Vec<(String, Span)>,
+    macros: HashMap<String, Macro>,
+    /// Name and call of the macros being expanded, innermost last
+    expansions: Vec<(String, Span)>,
+    /// Nesting of the macro and repeat expansions
+    depth: usize,
+    /// Number of `local` symbols so far, to make them unique
+    locals: usize,
+    listing: Vec<ListedLine>,
+    /// Symbols in the operands, with their span
+    uses: Vec<(String, Span)>,
+}
+
+impl Assembler<'_> {
+    fn error(&mut self, span: Span, message: String) {
+        self.diags
+            .push(self.sources.diagnostic(Severity::Error, span, message));
+
+        // Repeated calls, as in a recursive macro, get a single note
+        let mut frames: Vec<(&str, Span, usize)> = Vec::new();
+        for (name, call) in self.expansions.iter().rev() {
+            match frames.last_mut() {
+                Some((last, span, count)) if last == name && span == call => *count += 1,
+                _ => frames.push((name, *call, 1)),
+            }
+        }
+
+        let mut notes = Vec::new();
+        for (name, call, count) in &frames {
+            notes.push((*call, format!("in expansion of macro `{}`", name)));
+            if *count > 1 {
+                let msg = format!("... and {} more expansions of `{}`", count - 1, name);
+                notes.push((*call, msg));
+            }
+        }
+        if notes.len() > MAX_NOTES {
+            let more = notes.len() - MAX_NOTES + 1;
+            let last = notes[notes.len() - 1].0;
+            notes.truncate(MAX_NOTES - 1);
+            notes.push((last, format!("... and {} more notes", more)));
+        }
+
+        for (call, msg) in notes {
+            self.diags
+                .push(self.sources.diagnostic(Severity::Note, call, msg));
+        }
+    }
+
+    fn note(&mut self, span: Span, message: &str) {
+        self.diags
+            .push(self.sources.diagnostic(Severity::Note, span, message));
+    }
+
+    /// Assembles the file at `path`, with the name `name` in the diagnostics.
+    fn file(&mut self, path: &Path, name: String, src: String) {
+        let index = self.sources.add(Some(name.clone()), Some(path.into()), src);
+        let file = &self.sources.files[index];
+
+        let count = self.diags.len();
+        let lines = parse_at(&file.src, file.start, self.diags);
+        for diag in &mut self.diags[count..] {
+            diag.file = file.name.clone();
+        }
+
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.into());
+        self.including.push((canonical, name));
+        self.lines(&lines);
+        self.including.pop();
+    }
+
+    /// Path of the file `name` used at `span`, next to the file of `span`,
+    /// in the include directories, or relative to the working directory.
+    fn find(&self, name: &str, span: Span) -> Option<PathBuf> {
+        let dir = self
+            .sources
+            .file(span.start)
+            .path
+            .as_ref()
+            .and_then(|path| path.parent());
+
+        dir.into_iter()
+            .chain(self.include.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(name))
+            .chain(iter::once(PathBuf::from(name)))
+            .find(|path| path.is_file())
+    }
+
+    /// File named by the string `expr`, reporting the errors.
+    fn path(&mut self, expr: &Expr) -> Option<(PathBuf, String)> {
+        let name = match &expr.kind {
+            ExprKind::Str(bytes) => String::from_utf8(bytes.clone()).ok()?,
+            _ => {
+                self.error(expr.span, "expected a file name string".into());
+                return None;
+            }
+        };
+
+        match self.find(&name, expr.span) {
+            Some(path) => {
+                let name = path.display().to_string();
+                Some((path, name))
+            }
+            None => {
+                self.error(expr.span, format!("file `{}` not found", name));
+                None
+            }
+        }
+    }
+
+    fn include(&mut self, stmt: &Statement) {
+        let [expr] = &stmt.operands[..] else {
+            let msg = format!("`include` takes 1 operand, found {}", stmt.operands.len());
+            return self.error(stmt.span, msg);
+        };
+        let Some((path, name)) = self.path(expr) else {
+            return;
+        };
+
+        let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if let Some(i) = self.including.iter().position(|(p, _)| *p == canonical) {
+            let mut cycle = self.including[i..]
+                .iter()
+                .map(|(_, name)| name.as_str())
+                .collect::<Vec<_>>();
+            cycle.push(&name);
+            let msg = format!("include cycle: {}", cycle.join(" -> "));
+            return self.error(expr.span, msg);
+        }
+
+        match fs::read_to_string(&path) {
+            Ok(src) => self.file(&path, name, src),
+            Err(err) => self.error(expr.span, format!("can't read `{}`: {}", name, err)),
+        }
+    }
+
+    /// `incbin "file"[, offset[, length]]`, the bytes of the file.
+    fn incbin(&mut self, stmt: &Statement) {
+        let (file, offset, length) = match &stmt.operands[..] {
+            [file] => (file, None, None),
+            [file, offset] => (file, Some(offset), None),
+            [file, offset, length] => (file, Some(offset), Some(length)),
+            operands => {
+                let msg = format!("`incbin` takes 1 to 3 operands, found {}", operands.len());
+                return self.error(stmt.span, msg);
+            }
+        };
+
+        let mut range = [None; 2];
+        for (value, expr) in range.iter_mut().zip([offset, length]) {
+            let Some(expr) = expr else {
+                continue;
+            };
+            match self.known(expr) {
+                Some(v @ 0..=0xffff_ffff) => *value = Some(v as usize),
+                Some(v) => return self.error(expr.span, format!("invalid size `{}`", v)),
+                None => return,
+            }
+        }
+
+        let Some((path, name)) = self.path(file) else {
+            return;
+        };
+        let bin = match fs::read(&path) {
+            Ok(bin) => bin,
+            Err(err) => {
+                return self.error(file.span, format!("can't read `{}`: {}", name, err));
+            }
+        };
+
+        let offset = range[0].unwrap_or(0);
+        let length = range[1];
+        let length = length.unwrap_or(bin.len().saturating_sub(offset));
+        let Some(bytes) = bin.get(offset..offset.saturating_add(length)) else {
+            let msg = format!(
+                "`{}` has {} bytes, not {} from offset {}",
+                name,
+                bin.len(),
+                length,
+                offset
+            );
+            return self.error(stmt.span, msg);
+        };
+
+        if bytes.len() > 0x10000 - self.pc as usize {
+            let msg = format!("`incbin` overflows pc ({:#06x})", self.pc);
+            return self.error(stmt.span, msg);
+        }
+        self.out.extend(bytes.iter().map(|&b| Opcode::Db(b)));
+        self.pc = self.pc.wrapping_add(bytes.len() as u16);
+    }
+
+    /// Assembles `lines`, expanding the macros and skipping the lines of the
+    /// false conditions. Returns `true` after an `exitm`.
+    fn lines(&mut self, lines: &[Line]) -> bool {
+        let mut conds: Vec<Cond> = Vec::new();
+        let mut i = 0;
+
+        while i < lines.len() {
+            let line = &lines[i];
+            i += 1;
+            let record = self.list(line);
+            let active = conds.iter().all(|cond| cond.active);
+            let (Some(name), Some(stmt)) = (directive(line), &line.statement) else {
+                if active {
+                    self.line(line, record);
+                }
+                continue;
+            };
+
+            match name.as_str() {
+                "if" | "ifdef" | "ifndef" => {
+                    let value = active && self.condition(&name, stmt);
+                    conds.push(Cond {
+                        active: value,
+                        done: value || !active,
+                        seen_else: false,
+                        span: stmt.name.span,
+                    });
+                }
+                "else" => match conds.last_mut() {
+                    Some(cond) if !cond.seen_else => {
+                        cond.active = !cond.done;
+                        cond.done = true;
+                        cond.seen_else = true;
+                    }
+                    Some(_) => self.error(stmt.name.span, "`else` after `else`".into()),
+                    None => self.error(stmt.name.span, "`else` without `if`".into()),
+                },
+                "endif" => {
+                    if conds.pop().is_none() {
+                        self.error(stmt.name.span, "`endif` without `if`".into());
+                    }
+                }
+                _ if BLOCKS.contains(&name.as_str()) => {
+                    let end = match block_end(&lines[i - 1..]) {
+                        Some(end) => i - 1 + end,
+                        None => {
+                            let msg = format!("`{}` without `endm`", name);
+                            self.error(stmt.name.span, msg);
+                            lines.len()
+                        }
+                    };
+                    let body = &lines[i..end];
+                    for line in &lines[i..lines.len().min(end + 1)] {
+                        self.list(line);
+                    }
+                    i = end + 1;
+
+                    if active {
+                        self.block(&name, line, stmt, body);
+                    }
+                }
+                _ if !active => {}
+                "exitm" if self.depth > 0 => return true,
+                "exitm" => {
+                    let msg = "`exitm` outside of a macro or repeat block".into();
+                    self.error(stmt.name.span, msg);
+                }
+                "endm" => self.error(stmt.name.span, "`endm` without `macro`".into()),
+                "local" => self.error(stmt.name.span, "`local` outside of a macro".into()),
+                _ => self.line(line, record),
+            }
+        }
+
+        for cond in conds {
+            self.error(cond.span, "`if` without `endif`".into());
+        }
+        false
+    }
+
+    /// Adds `line` to the listing and returns its index.
+    fn list(&mut self, line: &Line) -> usize {
+        self.listing.push(ListedLine {
+            span: line.span,
+            address: None,
+            value: None,
+            ops: self.out.len()..self.out.len(),
+            depth: self.depth,
+        });
+        self.listing.len() - 1
+    }
+
+    fn use_symbols(&mut self, stmt: &Statement) {
+        for expr in &stmt.operands {
+            symbols(expr, &mut self.uses);
+        }
+    }
+
+    /// Assembles `line`, listed at `record`.
+    fn line(&mut self, line: &Line, record: usize) {
+        self.here = self.pc;
+        if line.label.is_some() || line.statement.is_some() {
+            self.listing[record].address = Some(self.pc);
+        }
+        let name = line
+            .statement
+            .as_ref()
+            .map(|stmt| stmt.name.name.to_ascii_lowercase());
+
+        match (&line.statement, name.as_deref()) {
+            (Some(stmt), Some("equ" | "set")) if stmt.kind == StatementKind::Directive => {
+                self.use_symbols(stmt);
+                self.assign(line.label.as_ref(), stmt);
+                self.listing[record].value = line
+                    .label
+                    .as_ref()
+                    .and_then(|label| self.symbols.get(&key(&label.name)))
+                    .and_then(|sym| sym.value);
+                return;
+            }
+            _ => {}
+        }
+        if let Some(label) = &line.label {
+            self.define(label, Some(self.pc as i64), SymbolKind::Label);
+        }
+
+        let (Some(stmt), Some(name)) = (&line.statement, name) else {
+            return;
+        };
+        let start = self.out.len();
+        match stmt.kind {
+            StatementKind::Directive => {
+                self.use_symbols(stmt);
+                self.directive(stmt);
+            }
+            StatementKind::Instruction => match self.macros.get(&name) {
+                // Listed with the lines of the expansion
+                Some(mac) => return self.expand(mac.clone(), stmt),
+                None => {
+                    self.use_symbols(stmt);
+                    self.instruction(stmt);
+                }
+            },
+        }
+
+        match name.as_str() {
+            // The padding is not part of the line
+            "org" => self.listing[record].address = Some(self.pc),
+            // Listed with the lines of the file
+            "include" => {}
+            _ => self.listing[record].ops = start..self.out.len(),
+        }
+    }
+
+    /// Value of the condition of `if`, `ifdef` or `ifndef`.
+    fn condition(&mut self, directive: &str, stmt: &Statement) -> bool {
+        let [expr] = &stmt.operands[..] else {
+            let msg = format!(
+                "`{}` takes 1 operand, found {}",
+                directive,
+                stmt.operands.len()
+            );
+            self.error(stmt.span, msg);
+            return false;
+        };
+
+        symbols(expr, &mut self.uses);
+        match (directive, &expr.kind) {
+            ("if", _) => self.known(expr).is_some_and(|value| value != 0),
+            (_, ExprKind::Symbol(name)) => {
+                self.symbols.contains_key(&key(name)) == (directive == "ifdef")
+            }
+            _ => {
+                let msg = format!("`{}` takes a symbol name", directive);
+                self.error(expr.span, msg);
+                false
+            }
+        }
+    }
+
+    /// Defines the macro, or expands the repeat block, of `body`.
+    fn block(&mut self, directive: &str, line: &Line, stmt: &Statement, body: &[Line]) {
+        if directive == "macro" {
+            return self.define_macro(line.label.as_ref(), stmt, body);
+        }
+        if let Some(label) = &line.label {
+            self.here = self.pc;
+            self.define(label, Some(self.pc as i64), SymbolKind::Label);
+        }
+        if self.depth == MAX_DEPTH {
+            return self.error(stmt.name.span, "macro expansion too deep".into());
+        }
+
+        // Arguments of each repetition
+        let mut repetitions = Vec::new();
+        match (directive, &stmt.operands[..]) {
+            ("rept", [count]) => {
+                self.here = self.pc;
+                let count = self
+                    .known(count)
+                    .and_then(|value| self.fit(count, Ok(value), 16));
+                repetitions.resize(count.unwrap_or(0) as usize, HashMap::new());
+            }
+            ("irp", [param, items @ ..]) | ("irpc", [param, items @ ..]) => {
+                let ExprKind::Symbol(name) = &param.kind else {
+                    let msg = format!("`{}` takes a parameter name", directive);
+                    return self.error(param.span, msg);
+                };
+
+                let items = match (directive, items) {
+                    ("irp", _) => items.to_vec(),
+                    (
+                        _,
+                        [Expr {
+                            kind: ExprKind::Str(bytes),
+                            span,
+                        }],
+                    ) => bytes
+                        .iter()
+                        .map(|&b| Expr {
+                            kind: ExprKind::Str(vec![b]),
+                            span: *span,
+                        })
+                        .collect(),
+                    _ => {
+                        let msg = "`irpc` takes a parameter name and a string".into();
+                        return self.error(stmt.span, msg);
+                    }
+                };
+                for item in items {
+                    repetitions.push(HashMap::from([(name.clone(), item)]));
+                }
+            }
+            _ => {
+                let msg = match directive {
+                    "rept" => format!("`rept` takes 1 operand, found {}", stmt.operands.len()),
+                    _ => format!("`{}` takes a parameter name and a list", directive),
+                };
+                return self.error(stmt.span, msg);
+            }
+        }
+
+        self.depth += 1;
+        for args in repetitions {
+            let body = body
+                .iter()
+                .map(|line| substitute(line, &args))
+                .collect::<Vec<_>>();
+            if self.lines(&body) {
+                break;
+            }
+        }
+        self.depth -= 1;
+    }
+
+    fn define_macro(&mut self, name: Option<&Ident>, stmt: &Statement, body: &[Line]) {
+        let Some(name) = name else {
+            return self.error(stmt.name.span, "`macro` needs a name".into());
+        };
+
+        let mut params = Vec::new();
+        for expr in &stmt.operands {
+            match &expr.kind {
+                ExprKind::Symbol(param) => params.push(Ident {
+                    name: param.clone(),
+                    span: expr.span,
+                }),
+                _ => return self.error(expr.span, "expected parameter name".into()),
+            }
+        }
+
+        let mac = Macro {
+            name: name.clone(),
+            params,
+            body: body.to_vec(),
+        };
+        if let Some(first) = self.macros.insert(key(&name.name), mac) {
+            self.error(name.span, format!("macro `{}` redefined", name.name));
+            self.note(first.name.span, "first defined here");
+        }
+    }
+
+    fn expand(&mut self, mac: Macro, stmt: &Statement) {
+        if stmt.operands.len() != mac.params.len() {
+            let msg = format!(
+                "macro `{}` takes {} argument{}, found {}",
+                mac.name.name,
+                mac.params.len(),
+                if mac.params.len() == 1 { "" } else { "s" },
+                stmt.operands.len()
+            );
+            return self.error(stmt.span, msg);
+        }
+        if self.depth == MAX_DEPTH {
+            return self.error(stmt.name.span, "macro expansion too deep".into());
+        }
+
+        let mut args = mac
+            .params
+            .iter()
+            .map(|param| key(&param.name))
+            .zip(stmt.operands.iter().cloned())
+            .collect::<HashMap<_, _>>();
+
+        // `local` symbols get a name unique to this expansion
+        let mut body = Vec::new();
+        for line in &mac.body {
+            if directive(line).as_deref() != Some("local") {
+                body.push(line);
+                continue;
+            }
+            for expr in &line.statement.as_ref().unwrap().operands {
+                match &expr.kind {
+                    ExprKind::Symbol(name) => {
+                        self.locals += 1;
+                        let kind = ExprKind::Symbol(format!("??{:04}", self.locals));
+                        args.insert(
+                            key(name),
+                            Expr {
+                                kind,
+                                span: expr.span,
+                            },
+                        );
+                    }
+                    _ => self.error(expr.span, "expected symbol name".into()),
+                }
+            }
+        }
+        let body = body
+            .iter()
+            .map(|line| substitute(line, &args))
+            .collect::<Vec<_>>();
+
+        self.depth += 1;
+        self.expansions.push((mac.name.name.clone(), stmt.span));
+        self.lines(&body);
+        self.expansions.pop();
+        self.depth -= 1;
+    }
+
+    fn eval(&self, expr: &Expr) -> Result<i64, EvalError> {
+        self.eval_at(expr, self.here)
+    }
+
+    /// Value of `expr` with `here` as `$`, for the operands evaluated later.
+    fn eval_at(&self, expr: &Expr, here: u16) -> Result<i64, EvalError> {
+        eval(expr, here, &|name| {
+            self.symbols.get(&key(name)).and_then(|sym| sym.value)
+        })
+    }
+
+    /// Value of `expr` truncated to `bits`, reporting the errors.
+    fn value(&mut self, expr: &Expr, bits: u32) -> Option<u16> {
+        let value = self.eval(expr);
+        self.fit(expr, value, bits)
+    }
+
+    /// Value of `expr` where it is needed right away, in `org`, `ds` and
+    /// `set`. The symbols used before their definition are reported at the end.
+    fn known(&mut self, expr: &Expr) -> Option<i64> {
+        match self.eval(expr) {
+            Ok(value) => Some(value),
+            Err(EvalError::Undefined(name, span)) => {
+                self.early.push((name, span));
+                None
+            }
+            Err(err) => {
+                self.error(err.span(), err.to_string());
+                None
+            }
+        }
+    }
+
+    /// `value` of `expr` truncated to `bits`, reporting the errors.
+    fn fit(&mut self, expr: &Expr, value: Result<i64, EvalError>, bits: u32) -> Option<u16> {
+        match value {
+            Ok(value) => match fit(value, bits) {
+                Some(value) => Some(value),
+                None => {
+                    let msg = format!("`{}` doesn't fit in {} bits", value, bits);
+                    self.error(expr.span, msg);
+                    None
+                }
+            },
+            Err(err) => {
+                self.error(err.span(), err.to_string());
+                None
+            }
+        }
+    }
+
+    /// Value of the data operand `expr` of `out[index]`, 0 and a fixup if it
+    /// refers to a label defined later.
+    fn data(&mut self, index: usize, op: Option<u8>, bits: u32, expr: &Expr) -> u16 {
+        match self.eval(expr) {
+            Err(EvalError::Undefined(..)) => {
+                self.fixups.push(Fixup {
+                    index,
+                    op,
+                    bits,
+                    expr: expr.clone(),
+                    here: self.here,
+                    sets: self
+                        .symbols
+                        .iter()
+                        .filter(|(_, sym)| sym.kind == SymbolKind::Set)
+                        .filter_map(|(name, sym)| Some((name.clone(), sym.value?)))
+                        .collect(),
+                });
+                0
+            }
+            _ => self.value(expr, bits).unwrap_or(0),
+        }
+    }
+
+    /// Reports `name` if it is a register, which can't be a symbol.
+    fn register(&mut self, name: &Ident) -> bool {
+        let register = REGISTERS.contains(&key(&name.name).as_str());
+        if register {
+            let msg = format!("`{}` is a register and can't be a symbol", name.name);
+            self.error(name.span, msg);
+        }
+        register
+    }
+
+    fn define(&mut self, name: &Ident, value: Option<i64>, kind: SymbolKind) {
+        if self.register(name) {
+            return;
+        }
+        let first = match self.symbols.get_mut(&key(&name.name)) {
+            Some(sym) if sym.kind == SymbolKind::Set && kind == SymbolKind::Set => {
+                sym.value = value;
+                return;
+            }
+            Some(sym) => sym.span,
+            None => {
+                let sym = Symbol {
+                    name: name.name.clone(),
+                    value,
+                    span: name.span,
+                    kind,
+                    uses: Vec::new(),
+                };
+                self.symbols.insert(key(&name.name), sym);
+                return;
+            }
+        };
+
+        let what = if kind == SymbolKind::Label {
+            "label"
+        } else {
+            "symbol"
+        };
+        self.error(name.span, format!("{} `{}` redefined", what, name.name));
+        self.note(first, "first defined here");
+    }
+
+    /// `name equ value` and `name set value`, the value of `set` must be known
+    /// here while `equ` may refer to symbols defined later.
+    fn assign(&mut self, name: Option<&Ident>, stmt: &Statement) {
+        let directive = stmt.name.name.to_ascii_lowercase();
+        let Some(name) = name else {
+            let msg = format!("`{}` needs a symbol name", directive);
+            return self.error(stmt.name.span, msg);
+        };
+        if stmt.operands.len() != 1 {
+            let msg = format!(
+                "`{}` takes 1 operand, found {}",
+                directive,
+                stmt.operands.len()
+            );
+            return self.error(stmt.span, msg);
+        }
+        if self.register(name) {
+            return;
+        }
+
+        let expr = &stmt.operands[0];
+        if directive == "set" {
+            if let Some(value) = self.known(expr) {
+                self.define(name, Some(value), SymbolKind::Set);
+            }
+            return;
+        }
+
+        match self.eval(expr) {
+            Ok(value) => self.define(name, Some(value), SymbolKind::Equ),
+            Err(EvalError::Undefined(..)) => {
+                self.define(name, None, SymbolKind::Equ);
+                self.pending.push((name.clone(), expr.clone(), self.here));
+            }
+            Err(err) => self.error(err.span(), err.to_string()),
+        }
+    }
+
+    fn directive(&mut self, stmt: &Statement) {
+        match stmt.name.name.to_ascii_lowercase().as_str() {
+            "org" => self.org(stmt),
+            "db" => self.db(stmt),
+            "dw" => self.dw(stmt),
+            "ds" => self.ds(stmt),
+            "include" => self.include(stmt),
+            "incbin" => self.incbin(stmt),
+            // Handled with the label
+            "equ" | "set" => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn org(&mut self, stmt: &Statement) {
+        if stmt.operands.len() != 1 {
+            let msg = format!("`org` takes 1 operand, found {}", stmt.operands.len());
+            return self.error(stmt.span, msg);
+        }
+
+        let expr = &stmt.operands[0];
+        let Some(new_pc) = self
+            .known(expr)
+            .and_then(|value| self.fit(expr, Ok(value), 16))
+        else {
+            return;
+        };
+
+        if let Some(diff) = new_pc.checked_sub(self.pc) {
+            for _ in 0..diff {
+                self.out.push(Opcode::Nop);
+            }
+            self.pc = new_pc;
+        } else {
+            let msg = format!("org operand overflows pc ({:#06x})", self.pc);
+            self.error(expr.span, msg);
+        }
+    }
+
+    /// Resolves the pending `equ` until no more can be, and reports the rest.
+    fn resolve(&mut self) {
+        let mut pending = std::mem::take(&mut self.pending);
+        loop {
+            let count = pending.len();
+            pending.retain(|(name, expr, here)| {
+                let value = match self.eval_at(expr, *here) {
+                    Err(EvalError::Undefined(..)) => return true,
+                    Ok(value) => value,
+                    Err(err) => {
+                        self.error(err.span(), err.to_string());
+                        0
+                    }
+                };
+                if let Some(sym) = self.symbols.get_mut(&key(&name.name)) {
+                    sym.value = Some(value);
+                }
+                false
+            });
+            if pending.len() == count {
+                break;
+            }
+        }
+
+        // Symbol each unresolved `equ` waits for, by key
+        let mut deps = HashMap::new();
+        for (name, expr, here) in &pending {
+            if let Err(EvalError::Undefined(dep, span)) = self.eval_at(expr, *here) {
+                deps.insert(key(&name.name), (key(&dep), dep, span));
+            }
+        }
+
+        for (name, _, _) in &pending {
+            let (dep_key, dep, span) = &deps[&key(&name.name)];
+            let mut next = dep_key;
+            for _ in 0..deps.len() {
+                if *next == key(&name.name) {
+                    let msg = format!("circular definition of `{}`", name.name);
+                    self.error(name.span, msg);
+                    break;
+                }
+                match deps.get(next) {
+                    Some((dep, _, _)) => next = dep,
+                    None => break,
+                }
+            }
+
+            // Otherwise reported with the symbol it waits for
+            if !deps.contains_key(dep_key) {
+                self.error(*span, format!("use of undefined symbol `{}`", dep));
+            }
+        }
+    }
+
+    fn db(&mut self, stmt: &Statement) {
+        if stmt.operands.is_empty() {
+            return self.error(stmt.span, "`db` takes at least 1 operand".into());
+        }
+
+        let start = self.out.len();
+        for expr in &stmt.operands {
+            match &expr.kind {
+                ExprKind::Str(bytes) => self.out.extend(bytes.iter().map(|&b| Opcode::Db(b))),
+                _ => {
+                    let value = self.data(self.out.len(), None, 8, expr);
+                    self.out.push(Opcode::Db(value as u8));
+                }
+            }
+        }
+        self.pc = self.here.wrapping_add((self.out.len() - start) as u16);
+    }
+
+    fn dw(&mut self, stmt: &Statement) {
+        if stmt.operands.is_empty() {
+            return self.error(stmt.span, "`dw` takes at least 1 operand".into());
+        }
+
+        let start = self.out.len();
+        for expr in &stmt.operands {
+            let [lo, hi] = self.data(self.out.len(), None, 16, expr).to_le_bytes();
+            self.out.push(Opcode::Db(lo));
+            self.out.push(Opcode::Db(hi));
+        }
+        self.pc = self.here.wrapping_add((self.out.len() - start) as u16);
+    }
+
+    /// `ds count[, fill]`, `count` bytes of `fill` or 0.
+    fn ds(&mut self, stmt: &Statement) {
+        let (count, fill) = match &stmt.operands[..] {
+            [count] => (count, None),
+            [count, fill] => (count, Some(fill)),
+            operands => {
+                let msg = format!("`ds` takes 1 or 2 operands, found {}", operands.len());
+                return self.error(stmt.span, msg);
+            }
+        };
+
+        let Some(count) = self
+            .known(count)
+            .and_then(|value| self.fit(count, Ok(value), 16))
+        else {
+            return;
+        };
+        let fill = match fill {
+            Some(fill) => match self
+                .known(fill)
+                .and_then(|value| self.fit(fill, Ok(value), 8))
+            {
+                Some(fill) => fill as u8,
+                None => return,
+            },
+            None => 0,
+        };
+
+        if self.pc.checked_add(count).is_none() {
+            let msg = format!("`ds` overflows pc ({:#06x})", self.pc);
+            return self.error(stmt.span, msg);
+        }
+        for _ in 0..count {
+            self.out.push(Opcode::Db(fill));
+        }
+        self.pc += count;
+    }
+
+    fn instruction(&mut self, stmt: &Statement) {
+        let mnemonic = stmt.name.name.to_ascii_lowercase();
+        let mut candidates = INSTRUCTIONS
+            .iter()
+            .filter(|(name, _, _)| *name == mnemonic)
+            .collect::<Vec<_>>();
+
+        if candidates.is_empty() {
+            let msg = format!("unknown opcode `{}`", stmt.name.name);
+            return self.error(stmt.name.span, msg);
+        }
+
+        let arity = candidates[0].1.len();
+        if stmt.operands.len() != arity {
+            let msg = format!(
+                "`{}` takes {} operand{}, found {}",
+                mnemonic,
+                arity,
+                if arity == 1 { "" } else { "s" },
+                stmt.operands.len()
+            );
+            return self.error(stmt.span, msg);
+        }
+
+        let mut data = None;
+        for (i, expr) in stmt.operands.iter().enumerate() {
+            if matches!(candidates[0].1[i], "d8" | "d16" | "adr") {
+                data = Some(expr);
+                continue;
+            }
+
+            // Registers, and the number of `rst`
+            let name = match &expr.kind {
+                ExprKind::Symbol(name) if REGISTERS.contains(&key(name).as_str()) => key(name),
+                _ => self
+                    .eval(expr)
+                    .map_or(String::new(), |value| value.to_string()),
+            };
+            candidates.retain(|(_, operands, _)| operands[i] == name);
+
+            if candidates.is_empty() {
+                let msg = format!(
+                    "unknown operand `{}` for {}",
+                    self.sources.text(expr.span),
+                    mnemonic
+                );
+                return self.error(expr.span, msg);
+            }
+        }
+
+        let op = candidates[0].2;
+        let value = match data {
+            Some(expr) => self.data(self.out.len(), Some(op), bits(op), expr),
+            None => 0,
+        };
+
+        let ins = opcode(op, value);
+        self.pc = self.pc.wrapping_add(ins.size() as u16);
+        self.out.push(ins);
+    }
+}
+
+/// Width of the data operand of `op`.
+fn bits(op: u8) -> u32 {
+    if opcode(op, 0).size() == 2 {
+        8
+    } else {
+        16
+    }
+}
+
+impl<'a> Assembler<'a> {
+    fn new(sources: SourceMap, include: &'a [PathBuf], diags: &'a mut Vec<Diagnostic>) -> Self {
+        Assembler {
+            sources,
+            include,
+            including: Vec::new(),
+            diags,
+            out: Vec::new(),
+            pc: 0,
+            here: 0,
+            symbols: HashMap::new(),
+            fixups: Vec::new(),
+            pending: Vec::new(),
+            early: Vec::new(),
+            macros: HashMap::new(),
+            expansions: Vec::new(),
+            depth: 0,
+            locals: 0,
+            listing: Vec::new(),
+            uses: Vec::new(),
+        }
+    }
+
+    /// Resolves what was left for the end of the source.
+    fn finish(mut self) -> Program {
+        self.resolve();
+
+        for (name, span) in std::mem::take(&mut self.early) {
+            let msg = if self.symbols.contains_key(&key(&name)) {
+                format!("`{}` must be defined before this use", name)
+            } else {
+                format!("use of undefined symbol `{}`", name)
+            };
+            self.error(span, msg);
+        }
+
+        for fixup in std::mem::take(&mut self.fixups) {
+            // A `set` symbol defined only after the use has no value here
+            let value = eval(&fixup.expr, fixup.here, &|name| match (
+                fixup.sets.get(&key(name)),
+                self.symbols.get(&key(name)),
+            ) {
+                (Some(&value), _) => Some(value),
+                (None, Some(sym)) if sym.kind != SymbolKind::Set => sym.value,
+                _ => None,
+            });
+            let value = match value {
+                Err(EvalError::Undefined(name, span))
+                    if self.symbols.get(&key(&name)).map(|sym| sym.kind)
+                        == Some(SymbolKind::Set) =>
+                {
+                    self.error(span, format!("`{}` is used before its `set`", name));
+                    continue;
+                }
+                value => value,
+            };
+            let Some(value) = self.fit(&fixup.expr, value, fixup.bits) else {
+                continue;
+            };
+            match fixup.op {
+                Some(op) => self.out[fixup.index] = opcode(op, value),
+                None => {
+                    let [lo, hi] = value.to_le_bytes();
+                    self.out[fixup.index] = Opcode::Db(lo);
+                    if fixup.bits == 16 {
+                        self.out[fixup.index + 1] = Opcode::Db(hi);
+                    }
+                }
+            }
+        }
+
+        for (name, span) in std::mem::take(&mut self.uses) {
+            if let Some(sym) = self.symbols.get_mut(&key(&name)) {
+                if sym.span != span {
+                    sym.uses.push(span);
+                }
+            }
+        }
+        let mut symbols = self.symbols.into_values().collect::<Vec<_>>();
+        symbols.sort_by_key(|sym| (sym.name.to_ascii_lowercase(), sym.name.clone()));
+        for sym in &mut symbols {
+            sym.uses.sort_by_key(|span| span.start);
+            sym.uses.dedup();
+        }
+
+        Program {
+            ops: self.out,
+            diags: Vec::new(),
+            sources: self.sources,
+            listing: self.listing,
+            symbols,
+        }
+    }
+}
+
+/// Assembles the parsed `lines` of `src` into opcodes, the errors are added to `diags`.
+/// Included files are looked up relative to the working directory.
+pub fn assemble(src: &str, lines: &[Line], diags: &mut Vec<Diagnostic>) -> Vec<Opcode> {
+    let mut sources = SourceMap::default();
+    sources.add(None, None, src.into());
+
+    let mut asm = Assembler::new(sources, &[], diags);
+    asm.lines(lines);
+    asm.finish().ops
+}
+
+/// Opcodes assembled from sources, with the diagnostics and the sources they
+/// refer to, the listing and the symbols sorted by name.
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub ops: Vec<Opcode>,
+    pub diags: Vec<Diagnostic>,
+    pub sources: SourceMap,
+    pub listing: Vec<ListedLine>,
+    pub symbols: Vec<Symbol>,
+}
+
+impl Program {
+    pub fn has_errors(&self) -> bool {
+        self.diags.iter().any(Diagnostic::is_error)
+    }
+}
+
+/// Assembles the files at `paths` one after the other into a single program.
+/// Included files are looked up next to the file including them, then in
+/// the `include` directories.
+pub fn assemble_files(paths: &[PathBuf], include: &[PathBuf]) -> io::Result<Program> {
+    let mut diags = Vec::new();
+    let mut asm = Assembler::new(SourceMap::default(), include, &mut diags);
+    for path in paths {
+        let src = fs::read_to_string(path)?;
+        asm.file(path, path.display().to_string(), src);
+    }
+
+    let mut program = asm.finish();
+    program.diags = diags;
+    Ok(program)
+}
+
+/// Assembles `src` into a program, included files are looked up relative to
+/// the working directory.
+pub fn assemble_source(src: &str) -> Program {
+    let mut diags = Vec::new();
+    let lines = parse(src, &mut diags);
+    let mut sources = SourceMap::default();
+    sources.add(None, None, src.into());
+
+    let mut asm = Assembler::new(sources, &[], &mut diags);
+    asm.lines(&lines);
+    let mut program = asm.finish();
+    program.diags = diags;
+    program
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// Byte range in the source.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Source file, `None` for a source given as a string
+    pub file: Option<String>,
+    /// Line, starting from 1
+    pub line: usize,
+    /// Column in characters, starting from 1
+    pub column: usize,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Diagnostic at `span` of `src`.
+    pub fn new(severity: Severity, src: &str, span: Span, message: impl Into<String>) -> Self {
+        let before = &src[..span.start.min(src.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        Diagnostic {
+            severity,
+            file: None,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            span,
+            message: message.into(),
+        }
+    }
+
+    pub fn error(src: &str, span: Span, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, src, span, message)
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Renders the diagnostic with the line of `src` it points to, the span
+    /// underlined with carets.
+    pub fn render(&self, src: &str) -> String {
+        let line = src.lines().nth(self.line - 1).unwrap_or("");
+        let number = self.line.to_string();
+        let pad = " ".repeat(number.len());
+
+        let start = self.column - 1;
+        let len = src
+            .get(self.span.start..self.span.end)
+            .map_or(0, |s| s.lines().next().unwrap_or("").chars().count());
+        let indent = line
+            .chars()
+            .take(start)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+
+        format!(
+            "{}: {}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}\n",
+            self.severity,
+            self.message,
+            pad,
+            self.file.as_deref().unwrap_or("<source>"),
+            self.line,
+            self.column,
+            pad,
+            number,
+            line,
+            pad,
+            indent,
+            "^".repeat(len.max(1)),
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, self.severity, self.message
+        )
+    }
+}
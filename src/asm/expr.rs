@@ -0,0 +1,125 @@
+use super::diag::Span;
+use super::parser::{BinaryOp, Expr, ExprKind, UnaryOp};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    /// Symbol not defined (yet)
+    Undefined(String, Span),
+    Overflow(Span),
+    DivisionByZero(Span),
+    /// String longer than 2 characters used as a number
+    String(Span),
+}
+
+impl EvalError {
+    pub fn span(&self) -> Span {
+        match self {
+            EvalError::Undefined(_, span)
+            | EvalError::Overflow(span)
+            | EvalError::DivisionByZero(span)
+            | EvalError::String(span) => *span,
+        }
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalError::Undefined(name, _) => write!(f, "use of undefined symbol `{}`", name),
+            EvalError::Overflow(_) => write!(f, "arithmetic overflow"),
+            EvalError::DivisionByZero(_) => write!(f, "division by zero"),
+            EvalError::String(_) => write!(f, "string used as a number"),
+        }
+    }
+}
+
+/// Evaluates `expr` with `here` as `$` and `symbol` giving the value of the
+/// defined symbols.
+pub fn eval(
+    expr: &Expr,
+    here: u16,
+    symbol: &dyn Fn(&str) -> Option<i64>,
+) -> Result<i64, EvalError> {
+    let overflow = || EvalError::Overflow(expr.span);
+
+    Ok(match &expr.kind {
+        ExprKind::Number(value) => *value as i64,
+        ExprKind::Here => here as i64,
+        ExprKind::Symbol(name) => {
+            symbol(name).ok_or_else(|| EvalError::Undefined(name.clone(), expr.span))?
+        }
+        ExprKind::Str(bytes) => match bytes[..] {
+            [c] => c as i64,
+            [hi, lo] => (hi as i64) << 8 | lo as i64,
+            _ => return Err(EvalError::String(expr.span)),
+        },
+        ExprKind::Unary(op, e) => {
+            let value = eval(e, here, symbol)?;
+            match op {
+                UnaryOp::Neg => value.checked_neg().ok_or_else(overflow)?,
+                UnaryOp::Not => !value,
+                UnaryOp::High => (value >> 8) & 0xff,
+                UnaryOp::Low => value & 0xff,
+            }
+        }
+        ExprKind::Binary(op, lhs, rhs) => {
+            let lhs = eval(lhs, here, symbol)?;
+            let rhs = eval(rhs, here, symbol)?;
+            match op {
+                BinaryOp::Add => lhs.checked_add(rhs).ok_or_else(overflow)?,
+                BinaryOp::Sub => lhs.checked_sub(rhs).ok_or_else(overflow)?,
+                BinaryOp::Mul => lhs.checked_mul(rhs).ok_or_else(overflow)?,
+                BinaryOp::Div | BinaryOp::Mod if rhs == 0 => {
+                    return Err(EvalError::DivisionByZero(expr.span))
+                }
+                BinaryOp::Div => lhs.checked_div(rhs).ok_or_else(overflow)?,
+                BinaryOp::Mod => lhs.checked_rem(rhs).ok_or_else(overflow)?,
+                BinaryOp::Shl | BinaryOp::Shr if rhs < 0 => return Err(overflow()),
+                BinaryOp::Shl => match rhs {
+                    0..=31 => lhs.checked_mul(1 << rhs).ok_or_else(overflow)?,
+                    _ if lhs == 0 => 0,
+                    _ => return Err(overflow()),
+                },
+                BinaryOp::Shr => match rhs {
+                    0..=63 => lhs >> rhs,
+                    _ => 0,
+                },
+                BinaryOp::And => lhs & rhs,
+                BinaryOp::Or => lhs | rhs,
+                BinaryOp::Xor => lhs ^ rhs,
+                BinaryOp::Eq => -((lhs == rhs) as i64),
+                BinaryOp::Ne => -((lhs != rhs) as i64),
+                BinaryOp::Lt => -((lhs < rhs) as i64),
+                BinaryOp::Le => -((lhs <= rhs) as i64),
+                BinaryOp::Gt => -((lhs > rhs) as i64),
+                BinaryOp::Ge => -((lhs >= rhs) as i64),
+            }
+        }
+    })
+}
+
+/// Adds the symbols used in `expr` to `out`, with their span.
+pub fn symbols(expr: &Expr, out: &mut Vec<(String, Span)>) {
+    match &expr.kind {
+        ExprKind::Symbol(name) => out.push((name.clone(), expr.span)),
+        ExprKind::Unary(_, e) => symbols(e, out),
+        ExprKind::Binary(_, a, b) => {
+            symbols(a, out);
+            symbols(b, out);
+        }
+        _ => {}
+    }
+}
+
+/// `value` truncated to `bits`, `None` if it doesn't fit as either a signed
+/// or an unsigned number.
+pub fn fit(value: i64, bits: u32) -> Option<u16> {
+    let min = -(1 << (bits - 1));
+    let max = (1 << bits) - 1;
+    if (min..=max).contains(&value) {
+        Some((value & max) as u16)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,141 @@
+use super::assembler::{Program, SymbolKind};
+use super::codegen;
+use super::source::SourceMap;
+use std::fmt::Write;
+
+/// Bytes on a row of the listing.
+const ROW_BYTES: usize = 4;
+/// Rows shown for a line, the bytes of a longer `ds` or `incbin` are elided.
+const MAX_ROWS: usize = 4;
+
+/// Offsets of the line starts of every file of `sources`.
+struct Lines<'a> {
+    sources: &'a SourceMap,
+    starts: Vec<Vec<usize>>,
+}
+
+impl<'a> Lines<'a> {
+    fn new(sources: &'a SourceMap) -> Self {
+        let starts = sources
+            .files
+            .iter()
+            .map(|file| {
+                let newlines = file.src.match_indices('\n').map(|(i, _)| i + 1);
+                std::iter::once(0).chain(newlines).collect()
+            })
+            .collect();
+        Lines { sources, starts }
+    }
+
+    /// Line of `offset` in its file, starting from 1.
+    fn number(&self, offset: usize) -> usize {
+        let index = self.sources.index(offset);
+        let local = offset - self.sources.files[index].start;
+        self.starts[index].partition_point(|&start| start <= local)
+    }
+
+    /// `file:line` of `offset`, or only the line for a single file.
+    fn location(&self, offset: usize) -> String {
+        match &self.sources.file(offset).name {
+            Some(name) if self.sources.files.len() > 1 => {
+                format!("{}:{}", name, self.number(offset))
+            }
+            _ => self.number(offset).to_string(),
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let bytes = bytes.iter().map(|b| format!("{:02X}", b));
+    bytes.collect::<Vec<_>>().join(" ")
+}
+
+/// Renders the listing of `program`: every line with its address, its bytes
+/// and its text, the lines of expansions marked with `+`, then the symbols
+/// with their value, where they are defined and where they are used.
+pub fn listing(program: &Program) -> String {
+    let sources = &program.sources;
+    let lines = Lines::new(sources);
+    let mut out = String::new();
+    let mut file = None;
+
+    for listed in &program.listing {
+        let index = sources.index(listed.span.start);
+        if file != Some(index) {
+            file = Some(index);
+            if let Some(name) = &sources.files[index].name {
+                writeln!(out, "{}:", name).unwrap();
+            }
+        }
+
+        let bin = codegen(&program.ops[listed.ops.clone()]);
+        let mut rows = bin.chunks(ROW_BYTES);
+        let address = match (listed.value, listed.address) {
+            (None, Some(address)) => format!("{:04X}", address),
+            _ => String::new(),
+        };
+        let bytes = match listed.value {
+            Some(value) => format!("= {:04X}", value & 0xffff),
+            None => rows.next().map_or(String::new(), hex),
+        };
+        let marker = if listed.depth > 0 { '+' } else { ' ' };
+        let text = sources.text(listed.span).trim_end();
+        let row = format!(
+            "{:<4}  {:<11}  {}{:>5}  {}",
+            address,
+            bytes,
+            marker,
+            lines.number(listed.span.start),
+            text
+        );
+        writeln!(out, "{}", row.trim_end()).unwrap();
+
+        for (i, chunk) in rows.take(MAX_ROWS - 1).enumerate() {
+            let address = listed.address.unwrap_or(0) as usize + (i + 1) * ROW_BYTES;
+            writeln!(out, "{:04X}  {}", address & 0xffff, hex(chunk)).unwrap();
+        }
+    }
+
+    if program.symbols.is_empty() {
+        return out;
+    }
+    let defined = program
+        .symbols
+        .iter()
+        .map(|sym| lines.location(sym.span.start));
+    let defined = defined.collect::<Vec<_>>();
+    let width = program.symbols.iter().map(|sym| sym.name.len()).max();
+    let width = width.unwrap_or(0).max("Symbol".len());
+    let at = defined
+        .iter()
+        .map(String::len)
+        .max()
+        .unwrap_or(0)
+        .max("Defined".len());
+    let header = format!(
+        "{:<width$}  Value  Kind   {:<at$}  Used",
+        "Symbol", "Defined"
+    );
+    writeln!(out, "\n{}", header).unwrap();
+    for (sym, defined) in program.symbols.iter().zip(&defined) {
+        let value = sym
+            .value
+            .map_or("????".into(), |v| format!("{:04X}", v & 0xffff));
+        let kind = match sym.kind {
+            SymbolKind::Label => "label",
+            SymbolKind::Equ => "equ",
+            SymbolKind::Set => "set",
+        };
+        let uses = sym.uses.iter().map(|span| lines.location(span.start));
+        let row = format!(
+            "{:<width$}  {:<5}  {:<5}  {:<at$}  {}",
+            sym.name,
+            value,
+            kind,
+            defined,
+            uses.collect::<Vec<_>>().join(", ")
+        );
+        writeln!(out, "{}", row.trim_end()).unwrap();
+    }
+    out
+}
@@ -0,0 +1,91 @@
+use super::parser::{Expr, ExprKind, Ident, Line, StatementKind, DIRECTIVES};
+use std::collections::HashMap;
+
+/// Directives opening a block closed by `endm`.
+pub const BLOCKS: &[&str] = &["macro", "rept", "irp", "irpc"];
+
+#[derive(Debug, Clone)]
+pub struct Macro {
+    pub name: Ident,
+    pub params: Vec<Ident>,
+    pub body: Vec<Line>,
+}
+
+/// Lowercase name of the directive of `line`, if it has one.
+pub fn directive(line: &Line) -> Option<String> {
+    line.statement
+        .as_ref()
+        .filter(|stmt| stmt.kind == StatementKind::Directive)
+        .map(|stmt| stmt.name.name.to_ascii_lowercase())
+}
+
+/// Index of the `endm` closing the block opened by `lines[0]`.
+pub fn block_end(lines: &[Line]) -> Option<usize> {
+    let mut depth = 0;
+    for (i, line) in lines.iter().enumerate() {
+        match directive(line).as_deref() {
+            Some(name) if BLOCKS.contains(&name) => depth += 1,
+            Some("endm") => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn expr(e: &Expr, args: &HashMap<String, Expr>) -> Expr {
+    let kind = match &e.kind {
+        ExprKind::Symbol(name) => return args.get(&name.to_ascii_lowercase()).unwrap_or(e).clone(),
+        ExprKind::Unary(op, a) => ExprKind::Unary(*op, Box::new(expr(a, args))),
+        ExprKind::Binary(op, a, b) => {
+            ExprKind::Binary(*op, Box::new(expr(a, args)), Box::new(expr(b, args)))
+        }
+        kind => kind.clone(),
+    };
+    Expr { kind, span: e.span }
+}
+
+/// `ident` renamed if its argument is a symbol.
+fn ident(ident: &Ident, args: &HashMap<String, Expr>) -> Ident {
+    match args
+        .get(&ident.name.to_ascii_lowercase())
+        .map(|arg| &arg.kind)
+    {
+        Some(ExprKind::Symbol(name)) => Ident {
+            name: name.clone(),
+            span: ident.span,
+        },
+        _ => ident.clone(),
+    }
+}
+
+/// `line` with the symbols in `args`, keyed by their lowercase name, replaced
+/// by their argument, in the operands, and in the label and the statement
+/// name when it is a symbol.
+pub fn substitute(line: &Line, args: &HashMap<String, Expr>) -> Line {
+    let statement = line.statement.as_ref().map(|stmt| {
+        let name = ident(&stmt.name, args);
+        let kind = if DIRECTIVES.contains(&name.name.to_ascii_lowercase().as_str()) {
+            StatementKind::Directive
+        } else {
+            StatementKind::Instruction
+        };
+
+        let mut stmt = stmt.clone();
+        stmt.operands = stmt.operands.iter().map(|e| expr(e, args)).collect();
+        stmt.kind = kind;
+        stmt.name = name;
+        stmt
+    });
+
+    Line {
+        label: line.label.as_ref().map(|label| ident(label, args)),
+        statement,
+        comment: line.comment.clone(),
+        span: line.span,
+    }
+}
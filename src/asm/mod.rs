@@ -2,7 +2,14 @@
 //! don't modify this file directly, instead run `python3 ./utils/gen.py`.
 
 use super::op::*;
+pub mod assembler;
+pub mod diag;
+pub mod expr;
 pub mod lexer;
+pub mod listing;
+pub mod macros;
+pub mod parser;
+pub mod source;
 
 pub fn codegen(ops: &[Opcode]) -> Vec<u8> {
     let mut bin = Vec::new();
@@ -834,9 +841,512 @@ pub fn codegen(ops: &[Opcode]) -> Vec<u8> {
             Opcode::Rst7 => {
                 bin.push(0xffu8);
             }
+            Opcode::Db(b) => {
+                bin.push(b);
+            }
         }
         i += 1;
     }
 
     bin
 }
+
+/// Mnemonic, operands and opcode of every instruction, `d8`, `d16` and
+/// `adr` are the data and address operands.
+pub const INSTRUCTIONS: &[(&str, &[&str], u8)] = &[
+    ("nop", &[], 0x00),
+    ("lxi", &["b", "d16"], 0x01),
+    ("stax", &["b"], 0x02),
+    ("inx", &["b"], 0x03),
+    ("inr", &["b"], 0x04),
+    ("dcr", &["b"], 0x05),
+    ("mvi", &["b", "d8"], 0x06),
+    ("rlc", &[], 0x07),
+    ("dad", &["b"], 0x09),
+    ("ldax", &["b"], 0x0a),
+    ("dcx", &["b"], 0x0b),
+    ("inr", &["c"], 0x0c),
+    ("dcr", &["c"], 0x0d),
+    ("mvi", &["c", "d8"], 0x0e),
+    ("rrc", &[], 0x0f),
+    ("lxi", &["d", "d16"], 0x11),
+    ("stax", &["d"], 0x12),
+    ("inx", &["d"], 0x13),
+    ("inr", &["d"], 0x14),
+    ("dcr", &["d"], 0x15),
+    ("mvi", &["d", "d8"], 0x16),
+    ("ral", &[], 0x17),
+    ("dad", &["d"], 0x19),
+    ("ldax", &["d"], 0x1a),
+    ("dcx", &["d"], 0x1b),
+    ("inr", &["e"], 0x1c),
+    ("dcr", &["e"], 0x1d),
+    ("mvi", &["e", "d8"], 0x1e),
+    ("rar", &[], 0x1f),
+    ("lxi", &["h", "d16"], 0x21),
+    ("shld", &["adr"], 0x22),
+    ("inx", &["h"], 0x23),
+    ("inr", &["h"], 0x24),
+    ("dcr", &["h"], 0x25),
+    ("mvi", &["h", "d8"], 0x26),
+    ("daa", &[], 0x27),
+    ("dad", &["h"], 0x29),
+    ("lhld", &["adr"], 0x2a),
+    ("dcx", &["h"], 0x2b),
+    ("inr", &["l"], 0x2c),
+    ("dcr", &["l"], 0x2d),
+    ("mvi", &["l", "d8"], 0x2e),
+    ("cma", &[], 0x2f),
+    ("lxi", &["sp", "d16"], 0x31),
+    ("sta", &["adr"], 0x32),
+    ("inx", &["sp"], 0x33),
+    ("inr", &["m"], 0x34),
+    ("dcr", &["m"], 0x35),
+    ("mvi", &["m", "d8"], 0x36),
+    ("stc", &[], 0x37),
+    ("dad", &["sp"], 0x39),
+    ("lda", &["adr"], 0x3a),
+    ("dcx", &["sp"], 0x3b),
+    ("inr", &["a"], 0x3c),
+    ("dcr", &["a"], 0x3d),
+    ("mvi", &["a", "d8"], 0x3e),
+    ("cmc", &[], 0x3f),
+    ("mov", &["b", "b"], 0x40),
+    ("mov", &["b", "c"], 0x41),
+    ("mov", &["b", "d"], 0x42),
+    ("mov", &["b", "e"], 0x43),
+    ("mov", &["b", "h"], 0x44),
+    ("mov", &["b", "l"], 0x45),
+    ("mov", &["b", "m"], 0x46),
+    ("mov", &["b", "a"], 0x47),
+    ("mov", &["c", "b"], 0x48),
+    ("mov", &["c", "c"], 0x49),
+    ("mov", &["c", "d"], 0x4a),
+    ("mov", &["c", "e"], 0x4b),
+    ("mov", &["c", "h"], 0x4c),
+    ("mov", &["c", "l"], 0x4d),
+    ("mov", &["c", "m"], 0x4e),
+    ("mov", &["c", "a"], 0x4f),
+    ("mov", &["d", "b"], 0x50),
+    ("mov", &["d", "c"], 0x51),
+    ("mov", &["d", "d"], 0x52),
+    ("mov", &["d", "e"], 0x53),
+    ("mov", &["d", "h"], 0x54),
+    ("mov", &["d", "l"], 0x55),
+    ("mov", &["d", "m"], 0x56),
+    ("mov", &["d", "a"], 0x57),
+    ("mov", &["e", "b"], 0x58),
+    ("mov", &["e", "c"], 0x59),
+    ("mov", &["e", "d"], 0x5a),
+    ("mov", &["e", "e"], 0x5b),
+    ("mov", &["e", "h"], 0x5c),
+    ("mov", &["e", "l"], 0x5d),
+    ("mov", &["e", "m"], 0x5e),
+    ("mov", &["e", "a"], 0x5f),
+    ("mov", &["h", "b"], 0x60),
+    ("mov", &["h", "c"], 0x61),
+    ("mov", &["h", "d"], 0x62),
+    ("mov", &["h", "e"], 0x63),
+    ("mov", &["h", "h"], 0x64),
+    ("mov", &["h", "l"], 0x65),
+    ("mov", &["h", "m"], 0x66),
+    ("mov", &["h", "a"], 0x67),
+    ("mov", &["l", "b"], 0x68),
+    ("mov", &["l", "c"], 0x69),
+    ("mov", &["l", "d"], 0x6a),
+    ("mov", &["l", "e"], 0x6b),
+    ("mov", &["l", "h"], 0x6c),
+    ("mov", &["l", "l"], 0x6d),
+    ("mov", &["l", "m"], 0x6e),
+    ("mov", &["l", "a"], 0x6f),
+    ("mov", &["m", "b"], 0x70),
+    ("mov", &["m", "c"], 0x71),
+    ("mov", &["m", "d"], 0x72),
+    ("mov", &["m", "e"], 0x73),
+    ("mov", &["m", "h"], 0x74),
+    ("mov", &["m", "l"], 0x75),
+    ("hlt", &[], 0x76),
+    ("mov", &["m", "a"], 0x77),
+    ("mov", &["a", "b"], 0x78),
+    ("mov", &["a", "c"], 0x79),
+    ("mov", &["a", "d"], 0x7a),
+    ("mov", &["a", "e"], 0x7b),
+    ("mov", &["a", "h"], 0x7c),
+    ("mov", &["a", "l"], 0x7d),
+    ("mov", &["a", "m"], 0x7e),
+    ("mov", &["a", "a"], 0x7f),
+    ("add", &["b"], 0x80),
+    ("add", &["c"], 0x81),
+    ("add", &["d"], 0x82),
+    ("add", &["e"], 0x83),
+    ("add", &["h"], 0x84),
+    ("add", &["l"], 0x85),
+    ("add", &["m"], 0x86),
+    ("add", &["a"], 0x87),
+    ("adc", &["b"], 0x88),
+    ("adc", &["c"], 0x89),
+    ("adc", &["d"], 0x8a),
+    ("adc", &["e"], 0x8b),
+    ("adc", &["h"], 0x8c),
+    ("adc", &["l"], 0x8d),
+    ("adc", &["m"], 0x8e),
+    ("adc", &["a"], 0x8f),
+    ("sub", &["b"], 0x90),
+    ("sub", &["c"], 0x91),
+    ("sub", &["d"], 0x92),
+    ("sub", &["e"], 0x93),
+    ("sub", &["h"], 0x94),
+    ("sub", &["l"], 0x95),
+    ("sub", &["m"], 0x96),
+    ("sub", &["a"], 0x97),
+    ("sbb", &["b"], 0x98),
+    ("sbb", &["c"], 0x99),
+    ("sbb", &["d"], 0x9a),
+    ("sbb", &["e"], 0x9b),
+    ("sbb", &["h"], 0x9c),
+    ("sbb", &["l"], 0x9d),
+    ("sbb", &["m"], 0x9e),
+    ("sbb", &["a"], 0x9f),
+    ("ana", &["b"], 0xa0),
+    ("ana", &["c"], 0xa1),
+    ("ana", &["d"], 0xa2),
+    ("ana", &["e"], 0xa3),
+    ("ana", &["h"], 0xa4),
+    ("ana", &["l"], 0xa5),
+    ("ana", &["m"], 0xa6),
+    ("ana", &["a"], 0xa7),
+    ("xra", &["b"], 0xa8),
+    ("xra", &["c"], 0xa9),
+    ("xra", &["d"], 0xaa),
+    ("xra", &["e"], 0xab),
+    ("xra", &["h"], 0xac),
+    ("xra", &["l"], 0xad),
+    ("xra", &["m"], 0xae),
+    ("xra", &["a"], 0xaf),
+    ("ora", &["b"], 0xb0),
+    ("ora", &["c"], 0xb1),
+    ("ora", &["d"], 0xb2),
+    ("ora", &["e"], 0xb3),
+    ("ora", &["h"], 0xb4),
+    ("ora", &["l"], 0xb5),
+    ("ora", &["m"], 0xb6),
+    ("ora", &["a"], 0xb7),
+    ("cmp", &["b"], 0xb8),
+    ("cmp", &["c"], 0xb9),
+    ("cmp", &["d"], 0xba),
+    ("cmp", &["e"], 0xbb),
+    ("cmp", &["h"], 0xbc),
+    ("cmp", &["l"], 0xbd),
+    ("cmp", &["m"], 0xbe),
+    ("cmp", &["a"], 0xbf),
+    ("rnz", &[], 0xc0),
+    ("pop", &["b"], 0xc1),
+    ("jnz", &["adr"], 0xc2),
+    ("jmp", &["adr"], 0xc3),
+    ("cnz", &["adr"], 0xc4),
+    ("push", &["b"], 0xc5),
+    ("adi", &["d8"], 0xc6),
+    ("rst", &["0"], 0xc7),
+    ("rz", &[], 0xc8),
+    ("ret", &[], 0xc9),
+    ("jz", &["adr"], 0xca),
+    ("cz", &["adr"], 0xcc),
+    ("call", &["adr"], 0xcd),
+    ("aci", &["d8"], 0xce),
+    ("rst", &["1"], 0xcf),
+    ("rnc", &[], 0xd0),
+    ("pop", &["d"], 0xd1),
+    ("jnc", &["adr"], 0xd2),
+    ("out", &["d8"], 0xd3),
+    ("cnc", &["adr"], 0xd4),
+    ("push", &["d"], 0xd5),
+    ("sui", &["d8"], 0xd6),
+    ("rst", &["2"], 0xd7),
+    ("rc", &[], 0xd8),
+    ("jc", &["adr"], 0xda),
+    ("in", &["d8"], 0xdb),
+    ("cc", &["adr"], 0xdc),
+    ("sbi", &["d8"], 0xde),
+    ("rst", &["3"], 0xdf),
+    ("rpo", &[], 0xe0),
+    ("pop", &["h"], 0xe1),
+    ("jpo", &["adr"], 0xe2),
+    ("xthl", &[], 0xe3),
+    ("cpo", &["adr"], 0xe4),
+    ("push", &["h"], 0xe5),
+    ("ani", &["d8"], 0xe6),
+    ("rst", &["4"], 0xe7),
+    ("rpe", &[], 0xe8),
+    ("pchl", &[], 0xe9),
+    ("jpe", &["adr"], 0xea),
+    ("xchg", &[], 0xeb),
+    ("cpe", &["adr"], 0xec),
+    ("xri", &["d8"], 0xee),
+    ("rst", &["5"], 0xef),
+    ("rp", &[], 0xf0),
+    ("pop", &["psw"], 0xf1),
+    ("jp", &["adr"], 0xf2),
+    ("di", &[], 0xf3),
+    ("cp", &["adr"], 0xf4),
+    ("push", &["psw"], 0xf5),
+    ("ori", &["d8"], 0xf6),
+    ("rst", &["6"], 0xf7),
+    ("rm", &[], 0xf8),
+    ("sphl", &[], 0xf9),
+    ("jm", &["adr"], 0xfa),
+    ("ei", &[], 0xfb),
+    ("cm", &["adr"], 0xfc),
+    ("cpi", &["d8"], 0xfe),
+    ("rst", &["7"], 0xff),
+];
+
+/// Instruction with opcode `op` and `data` as its data or address operand.
+pub fn opcode(op: u8, data: u16) -> Opcode {
+    match op {
+        0x00 => Opcode::Nop,
+        0x01 => Opcode::LxiB((data >> 8) as u8, data as u8),
+        0x02 => Opcode::StaxB,
+        0x03 => Opcode::InxB,
+        0x04 => Opcode::InrB,
+        0x05 => Opcode::DcrB,
+        0x06 => Opcode::MviB(data as u8),
+        0x07 => Opcode::Rlc,
+        0x09 => Opcode::DadB,
+        0x0a => Opcode::LdaxB,
+        0x0b => Opcode::DcxB,
+        0x0c => Opcode::InrC,
+        0x0d => Opcode::DcrC,
+        0x0e => Opcode::MviC(data as u8),
+        0x0f => Opcode::Rrc,
+        0x11 => Opcode::LxiD((data >> 8) as u8, data as u8),
+        0x12 => Opcode::StaxD,
+        0x13 => Opcode::InxD,
+        0x14 => Opcode::InrD,
+        0x15 => Opcode::DcrD,
+        0x16 => Opcode::MviD(data as u8),
+        0x17 => Opcode::Ral,
+        0x19 => Opcode::DadD,
+        0x1a => Opcode::LdaxD,
+        0x1b => Opcode::DcxD,
+        0x1c => Opcode::InrE,
+        0x1d => Opcode::DcrE,
+        0x1e => Opcode::MviE(data as u8),
+        0x1f => Opcode::Rar,
+        0x21 => Opcode::LxiH((data >> 8) as u8, data as u8),
+        0x22 => Opcode::Shld(data),
+        0x23 => Opcode::InxH,
+        0x24 => Opcode::InrH,
+        0x25 => Opcode::DcrH,
+        0x26 => Opcode::MviH(data as u8),
+        0x27 => Opcode::Daa,
+        0x29 => Opcode::DadH,
+        0x2a => Opcode::Lhld(data),
+        0x2b => Opcode::DcxH,
+        0x2c => Opcode::InrL,
+        0x2d => Opcode::DcrL,
+        0x2e => Opcode::MviL(data as u8),
+        0x2f => Opcode::Cma,
+        0x31 => Opcode::LxiSp((data >> 8) as u8, data as u8),
+        0x32 => Opcode::Sta(data),
+        0x33 => Opcode::InxSp,
+        0x34 => Opcode::InrM,
+        0x35 => Opcode::DcrM,
+        0x36 => Opcode::MviM(data as u8),
+        0x37 => Opcode::Stc,
+        0x39 => Opcode::DadSp,
+        0x3a => Opcode::Lda(data),
+        0x3b => Opcode::DcxSp,
+        0x3c => Opcode::InrA,
+        0x3d => Opcode::DcrA,
+        0x3e => Opcode::MviA(data as u8),
+        0x3f => Opcode::Cmc,
+        0x40 => Opcode::MovBB,
+        0x41 => Opcode::MovBC,
+        0x42 => Opcode::MovBD,
+        0x43 => Opcode::MovBE,
+        0x44 => Opcode::MovBH,
+        0x45 => Opcode::MovBL,
+        0x46 => Opcode::MovBM,
+        0x47 => Opcode::MovBA,
+        0x48 => Opcode::MovCB,
+        0x49 => Opcode::MovCC,
+        0x4a => Opcode::MovCD,
+        0x4b => Opcode::MovCE,
+        0x4c => Opcode::MovCH,
+        0x4d => Opcode::MovCL,
+        0x4e => Opcode::MovCM,
+        0x4f => Opcode::MovCA,
+        0x50 => Opcode::MovDB,
+        0x51 => Opcode::MovDC,
+        0x52 => Opcode::MovDD,
+        0x53 => Opcode::MovDE,
+        0x54 => Opcode::MovDH,
+        0x55 => Opcode::MovDL,
+        0x56 => Opcode::MovDM,
+        0x57 => Opcode::MovDA,
+        0x58 => Opcode::MovEB,
+        0x59 => Opcode::MovEC,
+        0x5a => Opcode::MovED,
+        0x5b => Opcode::MovEE,
+        0x5c => Opcode::MovEH,
+        0x5d => Opcode::MovEL,
+        0x5e => Opcode::MovEM,
+        0x5f => Opcode::MovEA,
+        0x60 => Opcode::MovHB,
+        0x61 => Opcode::MovHC,
+        0x62 => Opcode::MovHD,
+        0x63 => Opcode::MovHE,
+        0x64 => Opcode::MovHH,
+        0x65 => Opcode::MovHL,
+        0x66 => Opcode::MovHM,
+        0x67 => Opcode::MovHA,
+        0x68 => Opcode::MovLB,
+        0x69 => Opcode::MovLC,
+        0x6a => Opcode::MovLD,
+        0x6b => Opcode::MovLE,
+        0x6c => Opcode::MovLH,
+        0x6d => Opcode::MovLL,
+        0x6e => Opcode::MovLM,
+        0x6f => Opcode::MovLA,
+        0x70 => Opcode::MovMB,
+        0x71 => Opcode::MovMC,
+        0x72 => Opcode::MovMD,
+        0x73 => Opcode::MovME,
+        0x74 => Opcode::MovMH,
+        0x75 => Opcode::MovML,
+        0x76 => Opcode::Hlt,
+        0x77 => Opcode::MovMA,
+        0x78 => Opcode::MovAB,
+        0x79 => Opcode::MovAC,
+        0x7a => Opcode::MovAD,
+        0x7b => Opcode::MovAE,
+        0x7c => Opcode::MovAH,
+        0x7d => Opcode::MovAL,
+        0x7e => Opcode::MovAM,
+        0x7f => Opcode::MovAA,
+        0x80 => Opcode::AddB,
+        0x81 => Opcode::AddC,
+        0x82 => Opcode::AddD,
+        0x83 => Opcode::AddE,
+        0x84 => Opcode::AddH,
+        0x85 => Opcode::AddL,
+        0x86 => Opcode::AddM,
+        0x87 => Opcode::AddA,
+        0x88 => Opcode::AdcB,
+        0x89 => Opcode::AdcC,
+        0x8a => Opcode::AdcD,
+        0x8b => Opcode::AdcE,
+        0x8c => Opcode::AdcH,
+        0x8d => Opcode::AdcL,
+        0x8e => Opcode::AdcM,
+        0x8f => Opcode::AdcA,
+        0x90 => Opcode::SubB,
+        0x91 => Opcode::SubC,
+        0x92 => Opcode::SubD,
+        0x93 => Opcode::SubE,
+        0x94 => Opcode::SubH,
+        0x95 => Opcode::SubL,
+        0x96 => Opcode::SubM,
+        0x97 => Opcode::SubA,
+        0x98 => Opcode::SbbB,
+        0x99 => Opcode::SbbC,
+        0x9a => Opcode::SbbD,
+        0x9b => Opcode::SbbE,
+        0x9c => Opcode::SbbH,
+        0x9d => Opcode::SbbL,
+        0x9e => Opcode::SbbM,
+        0x9f => Opcode::SbbA,
+        0xa0 => Opcode::AnaB,
+        0xa1 => Opcode::AnaC,
+        0xa2 => Opcode::AnaD,
+        0xa3 => Opcode::AnaE,
+        0xa4 => Opcode::AnaH,
+        0xa5 => Opcode::AnaL,
+        0xa6 => Opcode::AnaM,
+        0xa7 => Opcode::AnaA,
+        0xa8 => Opcode::XraB,
+        0xa9 => Opcode::XraC,
+        0xaa => Opcode::XraD,
+        0xab => Opcode::XraE,
+        0xac => Opcode::XraH,
+        0xad => Opcode::XraL,
+        0xae => Opcode::XraM,
+        0xaf => Opcode::XraA,
+        0xb0 => Opcode::OraB,
+        0xb1 => Opcode::OraC,
+        0xb2 => Opcode::OraD,
+        0xb3 => Opcode::OraE,
+        0xb4 => Opcode::OraH,
+        0xb5 => Opcode::OraL,
+        0xb6 => Opcode::OraM,
+        0xb7 => Opcode::OraA,
+        0xb8 => Opcode::CmpB,
+        0xb9 => Opcode::CmpC,
+        0xba => Opcode::CmpD,
+        0xbb => Opcode::CmpE,
+        0xbc => Opcode::CmpH,
+        0xbd => Opcode::CmpL,
+        0xbe => Opcode::CmpM,
+        0xbf => Opcode::CmpA,
+        0xc0 => Opcode::Rnz,
+        0xc1 => Opcode::PopB,
+        0xc2 => Opcode::Jnz(data),
+        0xc3 => Opcode::Jmp(data),
+        0xc4 => Opcode::Cnz(data),
+        0xc5 => Opcode::PushB,
+        0xc6 => Opcode::Adi(data as u8),
+        0xc7 => Opcode::Rst0,
+        0xc8 => Opcode::Rz,
+        0xc9 => Opcode::Ret,
+        0xca => Opcode::Jz(data),
+        0xcc => Opcode::Cz(data),
+        0xcd => Opcode::Call(data),
+        0xce => Opcode::Aci(data as u8),
+        0xcf => Opcode::Rst1,
+        0xd0 => Opcode::Rnc,
+        0xd1 => Opcode::PopD,
+        0xd2 => Opcode::Jnc(data),
+        0xd3 => Opcode::Out(data as u8),
+        0xd4 => Opcode::Cnc(data),
+        0xd5 => Opcode::PushD,
+        0xd6 => Opcode::Sui(data as u8),
+        0xd7 => Opcode::Rst2,
+        0xd8 => Opcode::Rc,
+        0xda => Opcode::Jc(data),
+        0xdb => Opcode::In(data as u8),
+        0xdc => Opcode::Cc(data),
+        0xde => Opcode::Sbi(data as u8),
+        0xdf => Opcode::Rst3,
+        0xe0 => Opcode::Rpo,
+        0xe1 => Opcode::PopH,
+        0xe2 => Opcode::Jpo(data),
+        0xe3 => Opcode::Xthl,
+        0xe4 => Opcode::Cpo(data),
+        0xe5 => Opcode::PushH,
+        0xe6 => Opcode::Ani(data as u8),
+        0xe7 => Opcode::Rst4,
+        0xe8 => Opcode::Rpe,
+        0xe9 => Opcode::Pchl,
+        0xea => Opcode::Jpe(data),
+        0xeb => Opcode::Xchg,
+        0xec => Opcode::Cpe(data),
+        0xee => Opcode::Xri(data as u8),
+        0xef => Opcode::Rst5,
+        0xf0 => Opcode::Rp,
+        0xf1 => Opcode::PopPsw,
+        0xf2 => Opcode::Jp(data),
+        0xf3 => Opcode::Di,
+        0xf4 => Opcode::Cp(data),
+        0xf5 => Opcode::PushPsw,
+        0xf6 => Opcode::Ori(data as u8),
+        0xf7 => Opcode::Rst6,
+        0xf8 => Opcode::Rm,
+        0xf9 => Opcode::Sphl,
+        0xfa => Opcode::Jm(data),
+        0xfb => Opcode::Ei,
+        0xfc => Opcode::Cm(data),
+        0xfe => Opcode::Cpi(data as u8),
+        0xff => Opcode::Rst7,
+        _ => Opcode::Nop,
+    }
+}
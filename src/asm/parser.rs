@@ -0,0 +1,539 @@
+use super::diag::{Diagnostic, Span};
+use super::lexer::{lex_line, Token, TokenKind};
+
+/// Names of the directives, every other statement is an instruction.
+pub const DIRECTIVES: &[&str] = &[
+    "org", "db", "dw", "ds", "equ", "set", "macro", "endm", "local", "rept", "irp", "irpc",
+    "exitm", "if", "else", "endif", "ifdef", "ifndef", "include", "incbin",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    High,
+    Low,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Shl,
+    Shr,
+    And,
+    Or,
+    Xor,
+    /// Comparisons, -1 (0FFFFH) when true and 0 otherwise
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExprKind {
+    Number(u32),
+    Symbol(String),
+    /// `$`, the address of the start of the statement
+    Here,
+    /// String, or character constant
+    Str(Vec<u8>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementKind {
+    Instruction,
+    Directive,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    pub kind: StatementKind,
+    /// Mnemonic or directive
+    pub name: Ident,
+    pub operands: Vec<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+}
+
+/// Line of source, `label: statement ; comment` with every part optional.
+/// The label of `equ`, `set` and `macro` is the name of the symbol or of the
+/// macro, the colon is optional before them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub label: Option<Ident>,
+    pub statement: Option<Statement>,
+    pub comment: Option<Comment>,
+    /// Line without the line break
+    pub span: Span,
+}
+
+/// Operators written as words, they can't be used as symbols.
+pub const KEYWORDS: &[&str] = &[
+    "mod", "shl", "shr", "and", "or", "xor", "not", "high", "low", "eq", "ne", "lt", "le", "gt",
+    "ge",
+];
+
+/// Parses numbers with a `0x`, `0o` or `0b` prefix, an `h`, `o`, `q`, `b` or
+/// `d` suffix, or decimal.
+pub fn parse_number(s: &str) -> Option<u32> {
+    let s = s.to_ascii_lowercase();
+    let (digits, radix) = if let Some(s) = s.strip_suffix('h') {
+        (s, 16)
+    } else if let Some(s) = s.strip_prefix("0x") {
+        (s, 16)
+    } else if let Some(s) = s.strip_prefix("0o") {
+        (s, 8)
+    } else if let Some(s) = s.strip_prefix("0b") {
+        (s, 2)
+    } else if let Some(s) = s.strip_suffix(['o', 'q']) {
+        (s, 8)
+    } else if let Some(s) = s.strip_suffix('b') {
+        (s, 2)
+    } else if let Some(s) = s.strip_suffix('d') {
+        (s, 10)
+    } else {
+        (s.as_str(), 10)
+    };
+
+    if digits.is_empty() {
+        return None;
+    }
+    u32::from_str_radix(digits, radix).ok()
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+    diags: &'a mut Vec<Diagnostic>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&TokenKind> {
+        match self.tokens.get(self.pos) {
+            Some(Token {
+                kind: TokenKind::Comment(_),
+                ..
+            })
+            | None => None,
+            Some(tok) => Some(&tok.kind),
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        self.peek()?;
+        self.pos += 1;
+        Some(self.tokens[self.pos - 1].clone())
+    }
+
+    fn error(&mut self, span: Span, message: String) {
+        self.diags.push(Diagnostic::error(self.src, span, message));
+    }
+
+    /// Span of the next token, or the end of the line.
+    fn here(&self, line: Span) -> Span {
+        self.tokens
+            .get(self.pos)
+            .map_or(Span::new(line.end, line.end), |tok| tok.span)
+    }
+
+    fn text(&self, span: Span) -> &str {
+        &self.src[span.start..span.end]
+    }
+
+    fn ident(&mut self) -> Option<Ident> {
+        match self.peek() {
+            Some(TokenKind::Ident(name)) => {
+                let name = name.clone();
+                let span = self.next()?.span;
+                Some(Ident { name, span })
+            }
+            _ => None,
+        }
+    }
+
+    /// Lowercase word at the next token, if it is an identifier.
+    fn word(&self) -> Option<String> {
+        match self.peek() {
+            Some(TokenKind::Ident(name)) => Some(name.to_ascii_lowercase()),
+            _ => None,
+        }
+    }
+
+    fn binary(&mut self, op: BinaryOp, lhs: Expr, rhs: Expr) -> Expr {
+        Expr {
+            span: Span::new(lhs.span.start, rhs.span.end),
+            kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)),
+        }
+    }
+
+    /// `OR` and `XOR`, the lowest precedence.
+    fn expr(&mut self, line: Span) -> Option<Expr> {
+        let mut lhs = self.and_expr(line)?;
+        loop {
+            let op = match self.word().as_deref() {
+                Some("or") => BinaryOp::Or,
+                Some("xor") => BinaryOp::Xor,
+                _ => return Some(lhs),
+            };
+            self.next();
+            let rhs = self.and_expr(line)?;
+            lhs = self.binary(op, lhs, rhs);
+        }
+    }
+
+    fn and_expr(&mut self, line: Span) -> Option<Expr> {
+        let mut lhs = self.not_expr(line)?;
+        while self.word().as_deref() == Some("and") {
+            self.next();
+            let rhs = self.not_expr(line)?;
+            lhs = self.binary(BinaryOp::And, lhs, rhs);
+        }
+        Some(lhs)
+    }
+
+    fn not_expr(&mut self, line: Span) -> Option<Expr> {
+        if self.word().as_deref() == Some("not") {
+            let start = self.next()?.span.start;
+            let expr = self.not_expr(line)?;
+            return Some(Expr {
+                span: Span::new(start, expr.span.end),
+                kind: ExprKind::Unary(UnaryOp::Not, Box::new(expr)),
+            });
+        }
+        self.cmp_expr(line)
+    }
+
+    /// `EQ`, `NE`, `LT`, `LE`, `GT` and `GE`.
+    fn cmp_expr(&mut self, line: Span) -> Option<Expr> {
+        let mut lhs = self.add_expr(line)?;
+        loop {
+            let op = match self.word().as_deref() {
+                Some("eq") => BinaryOp::Eq,
+                Some("ne") => BinaryOp::Ne,
+                Some("lt") => BinaryOp::Lt,
+                Some("le") => BinaryOp::Le,
+                Some("gt") => BinaryOp::Gt,
+                Some("ge") => BinaryOp::Ge,
+                _ => return Some(lhs),
+            };
+            self.next();
+            let rhs = self.add_expr(line)?;
+            lhs = self.binary(op, lhs, rhs);
+        }
+    }
+
+    fn add_expr(&mut self, line: Span) -> Option<Expr> {
+        let mut lhs = self.mul_expr(line)?;
+        loop {
+            let op = match self.peek() {
+                Some(TokenKind::Plus) => BinaryOp::Add,
+                Some(TokenKind::Minus) => BinaryOp::Sub,
+                _ => return Some(lhs),
+            };
+            self.next();
+            let rhs = self.mul_expr(line)?;
+            lhs = self.binary(op, lhs, rhs);
+        }
+    }
+
+    fn mul_expr(&mut self, line: Span) -> Option<Expr> {
+        let mut lhs = self.unary(line)?;
+        loop {
+            let op = match (self.peek(), self.word().as_deref()) {
+                (Some(TokenKind::Star), _) => BinaryOp::Mul,
+                (Some(TokenKind::Slash), _) => BinaryOp::Div,
+                (_, Some("mod")) => BinaryOp::Mod,
+                (_, Some("shl")) => BinaryOp::Shl,
+                (_, Some("shr")) => BinaryOp::Shr,
+                _ => return Some(lhs),
+            };
+            self.next();
+            let rhs = self.unary(line)?;
+            lhs = self.binary(op, lhs, rhs);
+        }
+    }
+
+    /// Unary `+`, `-`, `HIGH` and `LOW`.
+    fn unary(&mut self, line: Span) -> Option<Expr> {
+        let op = match (self.peek(), self.word().as_deref()) {
+            (Some(TokenKind::Plus), _) => None,
+            (Some(TokenKind::Minus), _) => Some(UnaryOp::Neg),
+            (_, Some("high")) => Some(UnaryOp::High),
+            (_, Some("low")) => Some(UnaryOp::Low),
+            _ => return self.primary(line),
+        };
+
+        let start = self.next()?.span.start;
+        let expr = self.unary(line)?;
+        Some(match op {
+            Some(op) => Expr {
+                span: Span::new(start, expr.span.end),
+                kind: ExprKind::Unary(op, Box::new(expr)),
+            },
+            None => expr,
+        })
+    }
+
+    fn primary(&mut self, line: Span) -> Option<Expr> {
+        let span = self.here(line);
+        let kind = match self.next().map(|tok| tok.kind) {
+            Some(TokenKind::Number(text)) => match parse_number(&text) {
+                Some(value) => ExprKind::Number(value),
+                None => {
+                    self.error(span, format!("invalid number `{}`", text));
+                    ExprKind::Number(0)
+                }
+            },
+            Some(TokenKind::Ident(name))
+                if KEYWORDS.contains(&name.to_ascii_lowercase().as_str()) =>
+            {
+                self.error(span, format!("expected operand, found `{}`", name));
+                return None;
+            }
+            Some(TokenKind::Ident(name)) => ExprKind::Symbol(name),
+            Some(TokenKind::Dollar) => ExprKind::Here,
+            Some(TokenKind::Str(bytes)) => ExprKind::Str(bytes),
+            Some(TokenKind::LParen) => {
+                let expr = self.expr(line)?;
+                if self.peek() != Some(&TokenKind::RParen) {
+                    let span = self.here(line);
+                    self.error(span, "expected `)`".into());
+                    return None;
+                }
+                let end = self.next()?.span.end;
+                return Some(Expr {
+                    kind: expr.kind,
+                    span: Span::new(span.start, end),
+                });
+            }
+            Some(_) => {
+                let msg = format!("expected operand, found `{}`", self.text(span));
+                self.error(span, msg);
+                return None;
+            }
+            None => {
+                self.error(span, "expected operand".into());
+                return None;
+            }
+        };
+        Some(Expr { kind, span })
+    }
+
+    /// Items of `<a, b, ...>`, the list of `irp`, false after an error.
+    fn list(&mut self, line: Span, items: &mut Vec<Expr>) -> bool {
+        self.next();
+        if self.peek() == Some(&TokenKind::Greater) {
+            self.next();
+            return true;
+        }
+
+        loop {
+            let Some(expr) = self.expr(line) else {
+                return false;
+            };
+            items.push(expr);
+
+            match self.peek() {
+                Some(TokenKind::Comma) => {
+                    self.next();
+                }
+                Some(TokenKind::Greater) => {
+                    self.next();
+                    return true;
+                }
+                _ => {
+                    let span = self.here(line);
+                    self.error(span, "expected `,` or `>`".into());
+                    return false;
+                }
+            }
+        }
+    }
+
+    fn statement(&mut self, line: Span) -> Option<Statement> {
+        let name = self.ident()?;
+        let kind = if DIRECTIVES.contains(&name.name.to_ascii_lowercase().as_str()) {
+            StatementKind::Directive
+        } else {
+            StatementKind::Instruction
+        };
+
+        let irp = name.name.eq_ignore_ascii_case("irp");
+        let mut operands = Vec::new();
+        if self.peek().is_some() {
+            loop {
+                if irp && self.peek() == Some(&TokenKind::Less) {
+                    if !self.list(line, &mut operands) {
+                        break;
+                    }
+                } else {
+                    match self.expr(line) {
+                        Some(expr) => operands.push(expr),
+                        None => break,
+                    }
+                }
+
+                match self.peek() {
+                    Some(TokenKind::Comma) => {
+                        self.next();
+                    }
+                    Some(_) => {
+                        let span = self.here(line);
+                        let msg = format!("expected `,`, found `{}`", self.text(span));
+                        self.error(span, msg);
+                        break;
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        let end = operands.last().map_or(name.span.end, |expr| expr.span.end);
+        Some(Statement {
+            kind,
+            span: Span::new(name.span.start, end),
+            name,
+            operands,
+        })
+    }
+
+    fn line(&mut self, span: Span) -> Line {
+        let label = match self.tokens.get(self.pos + 1).map(|tok| &tok.kind) {
+            Some(TokenKind::Colon) => {
+                let label = self.ident();
+                if label.is_some() {
+                    self.next();
+                }
+                label
+            }
+            Some(TokenKind::Ident(name))
+                if matches!(name.to_ascii_lowercase().as_str(), "equ" | "set" | "macro") =>
+            {
+                self.ident()
+            }
+            _ => None,
+        };
+
+        let statement = match self.peek() {
+            Some(TokenKind::Ident(_)) => self.statement(span),
+            Some(_) => {
+                let span = self.here(span);
+                let msg = format!("expected instruction, found `{}`", self.text(span));
+                self.error(span, msg);
+                None
+            }
+            None => None,
+        };
+
+        // Skip what is left after an error
+        while self.next().is_some() {}
+
+        let comment = match self.tokens.get(self.pos) {
+            Some(Token {
+                kind: TokenKind::Comment(text),
+                span,
+            }) => Some(Comment {
+                text: text.clone(),
+                span: *span,
+            }),
+            _ => None,
+        };
+
+        Line {
+            label,
+            statement,
+            comment,
+            span,
+        }
+    }
+}
+
+fn shift_span(span: &mut Span, offset: usize) {
+    span.start += offset;
+    span.end += offset;
+}
+
+fn shift_expr(expr: &mut Expr, offset: usize) {
+    shift_span(&mut expr.span, offset);
+    match &mut expr.kind {
+        ExprKind::Unary(_, e) => shift_expr(e, offset),
+        ExprKind::Binary(_, a, b) => {
+            shift_expr(a, offset);
+            shift_expr(b, offset);
+        }
+        _ => {}
+    }
+}
+
+/// Parses `src` with the spans of its lines starting at `start`, the errors
+/// are added to `diags` with spans relative to `src`.
+pub fn parse_at(src: &str, start: usize, diags: &mut Vec<Diagnostic>) -> Vec<Line> {
+    let mut lines = parse(src, diags);
+    for line in &mut lines {
+        shift_span(&mut line.span, start);
+        if let Some(label) = &mut line.label {
+            shift_span(&mut label.span, start);
+        }
+        if let Some(stmt) = &mut line.statement {
+            shift_span(&mut stmt.span, start);
+            shift_span(&mut stmt.name.span, start);
+            for expr in &mut stmt.operands {
+                shift_expr(expr, start);
+            }
+        }
+        if let Some(comment) = &mut line.comment {
+            shift_span(&mut comment.span, start);
+        }
+    }
+    lines
+}
+
+/// Parses `src` into lines, the errors are added to `diags`.
+pub fn parse(src: &str, diags: &mut Vec<Diagnostic>) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+
+    for raw in src.split_inclusive('\n') {
+        let text = raw.trim_end_matches(['\n', '\r']);
+        let span = Span::new(offset, offset + text.len());
+        let tokens = lex_line(src, text, offset, diags);
+
+        let mut parser = Parser {
+            src,
+            tokens,
+            pos: 0,
+            diags,
+        };
+        lines.push(parser.line(span));
+        offset += raw.len();
+    }
+
+    lines
+}
@@ -0,0 +1,79 @@
+use super::diag::{Diagnostic, Severity, Span};
+use std::path::PathBuf;
+
+/// Source text, with the spans of its lines starting at `start`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    /// Name in the diagnostics, `None` for a source given as a string
+    pub name: Option<String>,
+    /// Path it was read from, included files are looked up next to it
+    pub path: Option<PathBuf>,
+    pub src: String,
+    pub start: usize,
+}
+
+/// Files of a program, with the spans of all of them in a single range of
+/// offsets so a span tells the file it is in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    pub files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    /// Adds a file and returns its index, its spans start after the ones of
+    /// the previous file.
+    pub fn add(&mut self, name: Option<String>, path: Option<PathBuf>, src: String) -> usize {
+        let start = self.files.last().map_or(0, |f| f.start + f.src.len() + 1);
+        self.files.push(SourceFile {
+            name,
+            path,
+            src,
+            start,
+        });
+        self.files.len() - 1
+    }
+
+    /// Index of the file of `offset`.
+    pub fn index(&self, offset: usize) -> usize {
+        self.files
+            .partition_point(|f| f.start <= offset)
+            .saturating_sub(1)
+    }
+
+    pub fn file(&self, offset: usize) -> &SourceFile {
+        &self.files[self.index(offset)]
+    }
+
+    /// `span` relative to the start of its file.
+    pub fn local(&self, span: Span) -> Span {
+        let start = self.file(span.start).start;
+        Span::new(span.start - start, span.end - start)
+    }
+
+    /// Text at `span`.
+    pub fn text(&self, span: Span) -> &str {
+        let local = self.local(span);
+        &self.file(span.start).src[local.start..local.end]
+    }
+
+    /// Diagnostic at `span`, with the file name and the span relative to it.
+    pub fn diagnostic(
+        &self,
+        severity: Severity,
+        span: Span,
+        message: impl Into<String>,
+    ) -> Diagnostic {
+        let file = self.file(span.start);
+        let mut diag = Diagnostic::new(severity, &file.src, self.local(span), message);
+        diag.file = file.name.clone();
+        diag
+    }
+
+    /// Source of the file named `name`, to render its diagnostics.
+    pub fn source(&self, name: &str) -> Option<&str> {
+        self.files
+            .iter()
+            .find(|f| f.name.as_deref() == Some(name))
+            .map(|f| f.src.as_str())
+    }
+}
@@ -1,63 +1,103 @@
-use intel_8080_kit::asm::{codegen, lexer::tokenize};
-use std::{
-    env,
-    fs::{self, File},
-    io::Write,
-    path::Path,
-    process::Command,
-    str,
+use intel_8080_kit::asm::{
+    assembler::assemble_files, codegen, diag::Diagnostic, listing::listing, source::SourceMap,
 };
+use std::{env, fs, path::PathBuf, process::ExitCode};
 
 const OUT_FILE: &str = "out.bin";
 
-fn main() {
-    let args = env::args().collect::<Vec<_>>();
-    let mut start = 1;
+fn report(sources: &SourceMap, diags: &[Diagnostic]) {
+    for diag in diags {
+        let src = diag
+            .file
+            .as_deref()
+            .and_then(|file| sources.source(file))
+            .unwrap_or("");
+        eprintln!("{}", diag.render(src));
+    }
+}
 
-    let nopp = if args.len() > 1 && args[1] == "--no-pp" {
-        start += 1;
-        true
-    } else {
-        false
-    };
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    let mut include = Vec::new();
+    let mut out = PathBuf::from(OUT_FILE);
+    let mut list = None;
+    let mut files = Vec::new();
 
-    for arg in &args[start..] {
-        let path = Path::new(&arg);
-
-        if path.exists() {
-            if nopp {
-                let src = fs::read_to_string(arg).unwrap();
-                let ops = tokenize(&src).unwrap();
-                let bin = codegen(&ops);
-
-                let mut file = File::create(OUT_FILE).unwrap();
-                file.write(&bin).unwrap();
-                println!("Emitted {} bytes to {} from {}.", bin.len(), OUT_FILE, arg);
-            } else {
-                if let Ok(sub) = Command::new("cpp").arg("-nostdinc").arg(path).output() {
-                    if sub.status.success() {
-                        let src = str::from_utf8(&sub.stdout).unwrap();
-
-                        if let Ok(ops) = tokenize(&src) {
-                            let bin = codegen(&ops);
-
-                            let mut file = File::create(OUT_FILE).unwrap();
-                            file.write(&bin).unwrap();
-                            println!("Emitted {} bytes to {} from {}.", bin.len(), OUT_FILE, arg);
-                        }
-                    } else {
-                        if let Ok(stderr) = str::from_utf8(&sub.stderr) {
-                            println!("{}", stderr);
-                        } else {
-                            println!("Preprocessor error.");
-                        }
-                    }
-                } else {
-                    println!("Preprocessor error.");
-                }
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--no-pp" => {
+                eprintln!(
+                    "--no-pp is deprecated and ignored, macros are expanded by the assembler."
+                )
             }
-        } else {
-            eprintln!("{} doesn't exist.", arg);
+            "-I" => match args.next() {
+                Some(dir) => include.push(PathBuf::from(dir)),
+                None => {
+                    eprintln!("Expected a directory after -I.");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-o" => match args.next() {
+                Some(path) => out = PathBuf::from(path),
+                None => {
+                    eprintln!("Expected an output path after -o.");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-l" => match args.next() {
+                Some(path) => list = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("Expected a listing path after -l.");
+                    return ExitCode::FAILURE;
+                }
+            },
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+
+    if files.is_empty() {
+        eprintln!("Usage: asm8080 [-I dir]... [-o out.bin] [-l out.lst] file...");
+        return ExitCode::FAILURE;
+    }
+    if let Some(path) = files.iter().find(|path| !path.exists()) {
+        eprintln!("{} doesn't exist.", path.display());
+        return ExitCode::FAILURE;
+    }
+
+    let program = match assemble_files(&files, &include) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("Can't read the sources: {}.", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    report(&program.sources, &program.diags);
+    if let Some(path) = &list {
+        if let Err(err) = fs::write(path, listing(&program)) {
+            eprintln!("Can't write {}: {}.", path.display(), err);
+            return ExitCode::FAILURE;
         }
     }
+    if program.has_errors() {
+        return ExitCode::FAILURE;
+    }
+
+    let bin = codegen(&program.ops);
+    if let Err(err) = fs::write(&out, &bin) {
+        eprintln!("Can't write {}: {}.", out.display(), err);
+        return ExitCode::FAILURE;
+    }
+
+    let names = files
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>();
+    println!(
+        "Emitted {} bytes to {} from {}.",
+        bin.len(),
+        out.display(),
+        names.join(", ")
+    );
+    ExitCode::SUCCESS
 }
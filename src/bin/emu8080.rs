@@ -1,71 +1,454 @@
-use intel_8080_kit::emu::{Emulator, Memory};
+use intel_8080_kit::emu::{
+    audio,
+    dev::{
+        console::{ConsolePorts, RawMode},
+        shared,
+        tape::{TapeMode, TapePorts},
+        Console, PortBus, Tape,
+    },
+    machine::Machine,
+    replay::{IoLog, Recorder, ReplayState, Replayer},
+    video::{Animation, FrameSink, ImageFormat, Sequence, Video},
+    Cpu, Emulator, FlatMemory, Memory,
+};
 use std::{
+    cell::RefCell,
     env, fs,
     path::Path,
-    time::{SystemTime, UNIX_EPOCH},
+    process::{self, ExitCode},
+    rc::Rc,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-struct MemoryBase([u8; u16::MAX as usize]);
+/// Base port of the tape, the status and data ports of the Altair 88-ACR.
+const TAPE_PORT: u8 = 6;
 
-impl MemoryBase {
-    pub fn new() -> Self {
-        Self([0; u16::MAX as usize])
-    }
+/// Ctrl-], stops the emulation when the console is in raw mode.
+const ESCAPE_KEY: u8 = 0x1d;
 
-    pub fn from_slice(base: &[u8]) -> Self {
-        let mut mem = Self::new();
-        for (i, v) in base.iter().enumerate() {
-            mem.0[i] = *v;
-        }
-        mem
-    }
-}
+/// Ports of the plain mode, the memory is the flat RAM in front of them.
+struct PortsBase;
 
-impl Memory for MemoryBase {
-    fn out_port(&self, port: u8, byte: u8) {
+impl Memory for PortsBase {
+    fn out_port(&mut self, port: u8, byte: u8) {
         println!("Output byte {} to port {}.", byte, port);
     }
 
-    fn in_port(&self, port: u8) -> u8 {
+    fn in_port(&mut self, port: u8) -> u8 {
         println!("Input byte from port {}.", port);
         0
     }
 
-    fn read_byte(&self, addr: u16) -> u8 {
-        self.0[addr as usize]
+    fn read_byte(&self, _addr: u16) -> u8 {
+        0
+    }
+
+    fn read_word(&self, _addr: u16) -> u16 {
+        0
+    }
+
+    fn write_byte(&mut self, _addr: u16, _byte: u8) {}
+
+    fn write_word(&mut self, _addr: u16, _word: u16) {}
+}
+
+fn parse_port(s: &str) -> Option<u8> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
     }
+}
+
+/// Frames written by `--video`, ends the emulation after `frames` frames.
+struct Capture {
+    sink: Box<dyn FrameSink>,
+    frames: Option<usize>,
+}
+
+impl Capture {
+    fn open(path: &str, frames: Option<usize>) -> Self {
+        let path = Path::new(path);
+        let prefix = path.with_extension("");
 
-    fn read_word(&self, addr: u16) -> u16 {
-        (self.read_byte(addr + 1) as u16) << 8 | self.read_byte(addr + 1) as u16
+        let sink: Box<dyn FrameSink> = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("apng") => Box::new(Animation::new(path, 60)),
+            Some("png") => Box::new(Sequence::new(prefix, ImageFormat::Png)),
+            _ => Box::new(Sequence::new(prefix, ImageFormat::Ppm)),
+        };
+        Self { sink, frames }
     }
+}
+
+/// Input log written by `--record` or fed back by `--replay`.
+enum IoTrace {
+    Record(String, Rc<RefCell<IoLog>>),
+    Replay(Rc<RefCell<ReplayState>>),
+}
+
+impl IoTrace {
+    fn attach(
+        emu: &mut Emulator,
+        record: &Option<String>,
+        replay: &Option<String>,
+    ) -> Option<Self> {
+        if let Some(path) = record {
+            let log = Rc::new(RefCell::new(IoLog::default()));
+            let handle = log.clone();
+            emu.wrap_memory(|mem| Box::new(Recorder::new(mem, handle)));
+            return Some(IoTrace::Record(path.clone(), log));
+        }
+
+        let path = replay.as_ref()?;
+        let log = fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|src| src.parse::<IoLog>().map_err(|err| err.to_string()));
 
-    fn write_byte(&mut self, addr: u16, byte: u8) {
-        self.0[addr as usize] = byte;
+        match log {
+            Ok(log) => {
+                let state = Rc::new(RefCell::new(ReplayState::new(log)));
+                let handle = state.clone();
+                emu.wrap_memory(|mem| Box::new(Replayer::new(mem, handle)));
+                Some(IoTrace::Replay(state))
+            }
+            Err(err) => {
+                eprintln!("Can't read {}: {}.", path, err);
+                process::exit(1);
+            }
+        }
     }
 
-    fn write_word(&mut self, addr: u16, word: u16) {
-        self.write_byte(addr, (word & 0xff) as u8);
-        self.write_byte(addr + 1, (word >> 8) as u8);
+    /// Writes the recorded log or checks the replay, false if the log
+    /// can't be written or the replay didn't match it to the end.
+    fn finish(self) -> bool {
+        match self {
+            IoTrace::Record(path, log) => {
+                if let Err(err) = fs::write(&path, log.borrow().to_string()) {
+                    eprintln!("Can't write {}: {}.", path, err);
+                    return false;
+                }
+            }
+            IoTrace::Replay(state) => {
+                let state = state.borrow();
+                if let Some((cycle, msg)) = state.diverged() {
+                    eprintln!("Replay diverged at cycle {}: {}.", cycle, msg);
+                    return false;
+                } else if !state.finished() {
+                    eprintln!("Replay stopped before the end of the log.");
+                    return false;
+                }
+            }
+        }
+        true
     }
 }
 
-fn main() {
-    let args = env::args().collect::<Vec<_>>();
-    for arg in &args[1..] {
+fn run(
+    emu: &mut Emulator,
+    clock: Option<u64>,
+    console: Option<&Rc<RefCell<Console>>>,
+    mut video: Option<&mut Video>,
+    mut capture: Option<Capture>,
+) -> bool {
+    let _raw = console.and_then(|console| match RawMode::enable() {
+        Ok(raw) => {
+            let raw = raw?;
+            console.borrow_mut().set_escape(Some(ESCAPE_KEY));
+            eprint!("Press Ctrl-] to quit.\r\n");
+            Some(raw)
+        }
+        Err(err) => {
+            eprintln!(
+                "Can't put the terminal in raw mode, input is line buffered: {}.",
+                err
+            );
+            None
+        }
+    });
+
+    let start = SystemTime::now();
+    let mut check = 0;
+
+    while !emu.halted() {
+        emu.step();
+
+        if let Some(video) = video.as_deref_mut() {
+            if video.update(emu) {
+                if let Some(capture) = capture.as_mut() {
+                    if let Err(err) = capture.sink.frame(video.frame()) {
+                        eprintln!("Can't write frame: {}.", err);
+                        return false;
+                    }
+                    if capture.frames.is_some_and(|frames| video.count() >= frames) {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let Some(console) = console {
+            let console = console.borrow();
+            if console.quit() || console.starved() {
+                break;
+            }
+        }
+
+        if let (Some(clock), None) = (clock, &capture) {
+            if emu.cycles() >= check {
+                check = emu.cycles() + (clock as usize / 100).max(1);
+
+                let target = Duration::from_nanos(emu.cycles() as u64 * 1_000_000_000 / clock);
+                let elapsed = start.elapsed().unwrap_or_default();
+                if target > elapsed {
+                    thread::sleep(target - elapsed);
+                }
+            }
+        }
+    }
+
+    if let Some(mut capture) = capture {
+        if let Err(err) = capture.sink.finish() {
+            eprintln!("Can't write frames: {}.", err);
+            return false;
+        }
+    }
+    true
+}
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    let mut machine = None;
+    let mut ports = None;
+    let mut vt52 = false;
+    let mut video = None;
+    let mut frames = None;
+    let mut wav = None;
+    let mut tape = None;
+    let mut punch = None;
+    let mut record = None;
+    let mut replay = None;
+    let mut cpu = Cpu::I8080;
+    let mut files = Vec::new();
+    let mut status = ExitCode::SUCCESS;
+
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--machine" => match args.next() {
+                Some(path) => machine = Some(path),
+                None => {
+                    eprintln!("Expected a machine description after --machine.");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--console" => {
+                let arg = args.next().unwrap_or_default();
+                let mut split = arg.split(',').map(parse_port);
+
+                match (split.next().flatten(), split.next().flatten()) {
+                    (Some(data), Some(status)) if data != status => ports = Some((data, status)),
+                    _ => {
+                        eprintln!("Expected data and status ports after --console.");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            "--vt52" => vt52 = true,
+            "--video" => match args.next() {
+                Some(path) => video = Some(path),
+                None => {
+                    eprintln!("Expected an output path after --video.");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--wav" => match args.next() {
+                Some(path) => wav = Some(path),
+                None => {
+                    eprintln!("Expected an output path after --wav.");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--tape" => match args.next() {
+                Some(path) => tape = Some(path),
+                None => {
+                    eprintln!("Expected a tape after --tape.");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--punch" => match args.next() {
+                Some(path) => punch = Some(path),
+                None => {
+                    eprintln!("Expected an output path after --punch.");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--record" => match args.next() {
+                Some(path) => record = Some(path),
+                None => {
+                    eprintln!("Expected an output path after --record.");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--replay" => match args.next() {
+                Some(path) => replay = Some(path),
+                None => {
+                    eprintln!("Expected a log after --replay.");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--cpu" => match args.next().as_deref() {
+                Some("8080") => cpu = Cpu::I8080,
+                Some("8085") => cpu = Cpu::I8085,
+                Some("z80") => cpu = Cpu::Z80,
+                _ => {
+                    eprintln!("Expected 8080, 8085 or z80 after --cpu.");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--frames" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => frames = Some(n),
+                None => {
+                    eprintln!("Expected a number of frames after --frames.");
+                    return ExitCode::FAILURE;
+                }
+            },
+            _ => files.push(arg),
+        }
+    }
+
+    if let Some(arg) = machine {
+        match Machine::open(Path::new(&arg)) {
+            Ok(mut machine) => {
+                let clock = machine.clock();
+                let console = machine.console();
+                if let Some(console) = &console {
+                    if vt52 {
+                        console.borrow_mut().set_vt52(true);
+                    }
+                }
+
+                let capture = video.map(|path| Capture::open(&path, frames));
+                let trace = IoTrace::attach(machine.emulator(), &record, &replay);
+                let (emu, video) = machine.parts();
+                if capture.is_some() && video.is_none() {
+                    eprintln!("{} has no video.", arg);
+                    return ExitCode::FAILURE;
+                }
+
+                let start = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+                if !run(emu, clock, console.as_ref(), video, capture) {
+                    status = ExitCode::FAILURE;
+                }
+                let end = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+                eprintln!("Execution of {} took {:?}.", arg, (end - start));
+                if trace.is_some_and(|trace| !trace.finish()) {
+                    status = ExitCode::FAILURE;
+                }
+
+                if let Some(path) = wav {
+                    let cycles = machine.emulator().cycles();
+                    match machine.mixer() {
+                        Some(mixer) => {
+                            let events = machine.sound_events();
+                            if let Err(err) =
+                                audio::capture(Path::new(&path), mixer, &events, cycles)
+                            {
+                                eprintln!("Can't write {}: {}.", path, err);
+                                status = ExitCode::FAILURE;
+                            }
+                        }
+                        None => {
+                            eprintln!("{} has no audio.", arg);
+                            status = ExitCode::FAILURE;
+                        }
+                    }
+                }
+
+                if let Err(err) = machine.save_tapes() {
+                    eprintln!("{}", err);
+                    status = ExitCode::FAILURE;
+                }
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                status = ExitCode::FAILURE;
+            }
+        }
+        return status;
+    }
+
+    if files.is_empty() {
+        eprintln!("Expected a binary or a machine description.");
+        return ExitCode::FAILURE;
+    }
+
+    for arg in &files {
         let path = Path::new(&arg);
 
         if path.exists() {
-            let bin = fs::read(arg).unwrap();
-            let mem = Box::new(MemoryBase::from_slice(&bin));
-            let mut emu = Emulator::new(mem);
+            let bin = match fs::read(arg) {
+                Ok(bin) => bin,
+                Err(err) => {
+                    eprintln!("Can't read {}: {}.", arg, err);
+                    status = ExitCode::FAILURE;
+                    continue;
+                }
+            };
+            let mut bus = PortBus::new(Box::new(PortsBase));
+
+            let console = ports.map(|(data, status)| {
+                let base = data.min(status);
+                let mut console = Console::stdio(ConsolePorts {
+                    data: data - base,
+                    status: status - base,
+                    ..ConsolePorts::default()
+                });
+                console.set_vt52(vt52);
+
+                let console = shared(console);
+                bus.map(base, console.clone());
+                console
+            });
+
+            let reader = if tape.is_some() || punch.is_some() {
+                let reader = shared(Tape::new(TapePorts::default(), TapeMode::Byte));
+                if let Some(tape) = &tape {
+                    if let Err(err) = reader.borrow_mut().load(Path::new(tape)) {
+                        eprintln!("Can't read {}: {}.", tape, err);
+                        return ExitCode::FAILURE;
+                    }
+                }
+                bus.map(TAPE_PORT, reader.clone());
+                Some(reader)
+            } else {
+                None
+            };
+
+            let mem = FlatMemory::new(&bin, Box::new(bus));
+            let mut emu = Emulator::with_flat(mem, cpu);
+            let trace = IoTrace::attach(&mut emu, &record, &replay);
 
             let start = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-            emu.run();
+            if !run(&mut emu, None, console.as_ref(), None, None) {
+                status = ExitCode::FAILURE;
+            }
             let end = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
 
-            println!("Execution of {} took {:?}.", arg, (end - start))
+            eprintln!("Execution of {} took {:?}.", arg, (end - start));
+            if trace.is_some_and(|trace| !trace.finish()) {
+                status = ExitCode::FAILURE;
+            }
+
+            if let (Some(reader), Some(punch)) = (reader, &punch) {
+                if let Err(err) = reader.borrow().save(Path::new(punch)) {
+                    eprintln!("Can't write {}: {}.", punch, err);
+                    status = ExitCode::FAILURE;
+                }
+            }
         } else {
             eprintln!("{} doesn't exist.", arg);
+            status = ExitCode::FAILURE;
         }
     }
+    status
 }
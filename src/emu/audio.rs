@@ -0,0 +1,272 @@
+use super::dev::Device;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+};
+
+/// Port write, at the cpu cycle it happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoundEvent {
+    pub cycle: usize,
+    /// Absolute port
+    pub port: u8,
+    pub value: u8,
+}
+
+/// Sound latches written with `OUT`, every write is logged with its cycle.
+pub struct Sound {
+    /// Base port
+    base: u8,
+    /// Latched values
+    latches: Vec<u8>,
+    /// Cycles elapsed
+    cycles: usize,
+    events: Vec<SoundEvent>,
+}
+
+impl Sound {
+    /// Latches at `ports` ports starting from `base`.
+    pub fn new(base: u8, ports: u8) -> Self {
+        Self {
+            base,
+            latches: vec![0; ports as usize],
+            cycles: 0,
+            events: Vec::new(),
+        }
+    }
+
+    /// Value latched at the absolute `port`, `None` outside the latches.
+    pub fn latch(&self, port: u8) -> Option<u8> {
+        let offset = port.wrapping_sub(self.base) as usize;
+        self.latches.get(offset).copied()
+    }
+
+    pub fn cycles(&self) -> usize {
+        self.cycles
+    }
+
+    pub fn events(&self) -> &[SoundEvent] {
+        &self.events
+    }
+
+    /// Drops the logged events, keeping the latches.
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+impl Device for Sound {
+    fn ports(&self) -> u16 {
+        self.latches.len() as u16
+    }
+
+    fn read(&mut self, port: u8) -> u8 {
+        self.latches[port as usize]
+    }
+
+    fn write(&mut self, port: u8, byte: u8) {
+        self.latches[port as usize] = byte;
+        self.events.push(SoundEvent {
+            cycle: self.cycles,
+            port: self.base.wrapping_add(port),
+            value: byte,
+        });
+    }
+
+    fn tick(&mut self, cycles: usize) {
+        self.cycles += cycles;
+    }
+}
+
+/// Sound source driven by a latch.
+#[derive(Debug, Clone)]
+pub enum Voice {
+    /// Level following the bits of `mask`, e.g. a beeper or a dac
+    Level { port: u8, mask: u8 },
+    /// `sample` played when `bit` rises, over again while it stays set if
+    /// `looped`, a full level gate while it's set if `sample` is empty
+    Trigger {
+        port: u8,
+        bit: u8,
+        sample: Vec<i16>,
+        looped: bool,
+    },
+}
+
+/// Loudest level of a single voice.
+const LEVEL: i32 = 0x2000;
+
+/// Renders logged port writes to samples.
+#[derive(Debug, Clone)]
+pub struct Mixer {
+    /// Cpu clock in Hz
+    clock: u64,
+    /// Sample rate in Hz
+    rate: u32,
+    voices: Vec<Voice>,
+}
+
+impl Mixer {
+    pub fn new(clock: u64, rate: u32) -> Self {
+        Self {
+            clock,
+            rate,
+            voices: Vec::new(),
+        }
+    }
+
+    /// Adds `voice`, panics if it is a trigger with a `bit` above 7.
+    pub fn add(&mut self, voice: Voice) {
+        if let Voice::Trigger { bit, .. } = voice {
+            assert!(bit < 8, "trigger bit {} is out of range", bit);
+        }
+        self.voices.push(voice);
+    }
+
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    /// Mono samples for the first `cycles` cycles, `events` sorted by cycle.
+    pub fn render(&self, events: &[SoundEvent], cycles: usize) -> Vec<i16> {
+        let len = (cycles as u128 * self.rate as u128 / self.clock.max(1) as u128) as usize;
+        let mut samples = Vec::with_capacity(len);
+
+        let mut latches = [0u8; 256];
+        let mut next = 0;
+        // Playing position of every voice
+        let mut pos = vec![None; self.voices.len()];
+
+        for n in 0..len {
+            let cycle = (n as u128 * self.clock as u128 / self.rate as u128) as usize;
+
+            while let Some(event) = events.get(next).filter(|e| e.cycle <= cycle) {
+                let old = latches[event.port as usize];
+                latches[event.port as usize] = event.value;
+
+                for (voice, pos) in self.voices.iter().zip(pos.iter_mut()) {
+                    if let Voice::Trigger { port, bit, .. } = voice {
+                        let mask = 1 << bit;
+                        if *port == event.port && event.value & mask != 0 && old & mask == 0 {
+                            *pos = Some(0);
+                        }
+                    }
+                }
+                next += 1;
+            }
+
+            let mut mix = 0;
+            for (voice, pos) in self.voices.iter().zip(pos.iter_mut()) {
+                mix += match voice {
+                    Voice::Level { port, mask } => match latches[*port as usize] & mask {
+                        0 => 0,
+                        level => level as i32 * LEVEL / *mask as i32,
+                    },
+                    Voice::Trigger {
+                        port,
+                        bit,
+                        sample,
+                        looped,
+                    } => {
+                        let set = latches[*port as usize] & (1 << bit) != 0;
+                        if sample.is_empty() {
+                            if set {
+                                LEVEL
+                            } else {
+                                0
+                            }
+                        } else if let Some(i) = *pos {
+                            *pos = match i + 1 {
+                                end if end < sample.len() => Some(end),
+                                _ if *looped && set => Some(0),
+                                _ => None,
+                            };
+                            sample[i] as i32
+                        } else {
+                            0
+                        }
+                    }
+                };
+            }
+
+            samples.push(mix.clamp(i16::MIN as i32, i16::MAX as i32) as i16);
+        }
+
+        samples
+    }
+}
+
+/// Writes mono 16 bit pcm samples as a wav file.
+pub fn write_wav<W: Write>(mut out: W, rate: u32, samples: &[i16]) -> io::Result<()> {
+    let size = samples.len() as u32 * 2;
+
+    out.write_all(b"RIFF")?;
+    out.write_all(&(36 + size).to_le_bytes())?;
+    out.write_all(b"WAVEfmt ")?;
+    out.write_all(&16u32.to_le_bytes())?;
+    // Pcm, mono
+    out.write_all(&1u16.to_le_bytes())?;
+    out.write_all(&1u16.to_le_bytes())?;
+    out.write_all(&rate.to_le_bytes())?;
+    out.write_all(&(rate * 2).to_le_bytes())?;
+    out.write_all(&2u16.to_le_bytes())?;
+    out.write_all(&16u16.to_le_bytes())?;
+    out.write_all(b"data")?;
+    out.write_all(&size.to_le_bytes())?;
+
+    for sample in samples {
+        out.write_all(&sample.to_le_bytes())?;
+    }
+    out.flush()
+}
+
+/// Reads the first channel of a 16 bit pcm wav file.
+pub fn read_wav<R: Read>(mut src: R) -> io::Result<(u32, Vec<i16>)> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    let mut data = Vec::new();
+    src.read_to_end(&mut data)?;
+    if data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err(invalid("not a wav file"));
+    }
+
+    let mut format = None;
+    let mut i = 12;
+    while i + 8 <= data.len() {
+        let id = &data[i..i + 4];
+        let len = u32::from_le_bytes([data[i + 4], data[i + 5], data[i + 6], data[i + 7]]) as usize;
+        let body = data
+            .get(i + 8..i + 8 + len)
+            .ok_or_else(|| invalid("truncated chunk"))?;
+
+        match id {
+            b"fmt " if len >= 16 => {
+                let word = |n: usize| u16::from_le_bytes([body[n], body[n + 1]]);
+                let rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                if word(0) != 1 || word(14) != 16 {
+                    return Err(invalid("only 16 bit pcm is supported"));
+                }
+                format = Some((rate, word(2).max(1) as usize));
+            }
+            b"data" => {
+                let (rate, channels) = format.ok_or_else(|| invalid("data before format"))?;
+                let samples = body
+                    .chunks_exact(2 * channels)
+                    .map(|frame| i16::from_le_bytes([frame[0], frame[1]]))
+                    .collect();
+                return Ok((rate, samples));
+            }
+            _ => {}
+        }
+        i += 8 + len + (len & 1);
+    }
+
+    Err(invalid("missing data"))
+}
+
+/// Renders `events` with `mixer` into the wav file at `path`.
+pub fn capture(path: &Path, mixer: &Mixer, events: &[SoundEvent], cycles: usize) -> io::Result<()> {
+    let samples = mixer.render(events, cycles);
+    write_wav(BufWriter::new(File::create(path)?), mixer.rate, &samples)
+}
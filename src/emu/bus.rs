@@ -0,0 +1,266 @@
+use super::Memory;
+use alloc::{rc::Rc, vec, vec::Vec};
+use core::cell::RefCell;
+
+/// Memory mapped device, `offset` is relative to the start of its region.
+pub trait Mmio {
+    fn read(&mut self, offset: u16) -> u8;
+    fn write(&mut self, offset: u16, byte: u8);
+}
+
+impl<R: FnMut(u16) -> u8, W: FnMut(u16, u8)> Mmio for (R, W) {
+    fn read(&mut self, offset: u16) -> u8 {
+        (self.0)(offset)
+    }
+
+    fn write(&mut self, offset: u16, byte: u8) {
+        (self.1)(offset, byte)
+    }
+}
+
+#[derive(Clone)]
+pub enum Region {
+    Ram(Vec<u8>),
+    Rom(Vec<u8>),
+    /// `len` bytes repeating the `period` bytes found at `target`
+    Mirror {
+        len: usize,
+        target: u16,
+        period: usize,
+    },
+    /// `len` bytes reading as `0xff` and ignoring writes
+    Unmapped(usize),
+    /// `len` bytes handled by a device
+    Mmio(usize, Rc<RefCell<dyn Mmio>>),
+    /// Banks of the same size, selected by the bank register `register`
+    Banked {
+        register: usize,
+        banks: Vec<Vec<u8>>,
+        writable: bool,
+    },
+}
+
+impl Region {
+    fn len(&self) -> usize {
+        match self {
+            Region::Ram(data) | Region::Rom(data) => data.len(),
+            Region::Mirror { len, .. } | Region::Unmapped(len) | Region::Mmio(len, _) => *len,
+            Region::Banked { banks, .. } => banks.first().map_or(0, |bank| bank.len()),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Mapping {
+    start: u16,
+    region: Region,
+}
+
+/// Address decoding memory made of regions, unmapped addresses read as `0xff`.
+///
+/// Bank registers are written and read back through the ports bound with
+/// `bank_port`, every other port reads as `0xff`.
+#[derive(Clone, Default)]
+pub struct Bus {
+    /// Mapped regions, the first match wins
+    regions: Vec<Mapping>,
+    /// Bank registers
+    banks: Vec<u8>,
+    /// Ports bound to the bank registers
+    bank_ports: Vec<(u8, usize)>,
+}
+
+/// Mirrors followed before giving up on a mirror loop.
+const MAX_MIRRORS: usize = 8;
+
+impl Bus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn map(&mut self, start: u16, region: Region) {
+        if let Region::Banked { register, .. } = region {
+            if register >= self.banks.len() {
+                self.banks.resize(register + 1, 0);
+            }
+        }
+        self.regions.push(Mapping { start, region });
+    }
+
+    pub fn map_ram(&mut self, start: u16, size: usize) {
+        self.map(start, Region::Ram(vec![0; size]));
+    }
+
+    pub fn map_rom(&mut self, start: u16, data: Vec<u8>) {
+        self.map(start, Region::Rom(data));
+    }
+
+    /// Maps `len` bytes at `start` repeating the `period` bytes at `target`.
+    pub fn map_mirror(&mut self, start: u16, len: usize, target: u16, period: usize) {
+        self.map(
+            start,
+            Region::Mirror {
+                len,
+                target,
+                period: period.max(1),
+            },
+        );
+    }
+
+    pub fn map_unmapped(&mut self, start: u16, len: usize) {
+        self.map(start, Region::Unmapped(len));
+    }
+
+    pub fn map_mmio(&mut self, start: u16, len: usize, dev: Rc<RefCell<dyn Mmio>>) {
+        self.map(start, Region::Mmio(len, dev));
+    }
+
+    pub fn map_banked(&mut self, start: u16, register: usize, banks: Vec<Vec<u8>>, writable: bool) {
+        self.map(
+            start,
+            Region::Banked {
+                register,
+                banks,
+                writable,
+            },
+        );
+    }
+
+    /// Binds `port` to the bank register `register`.
+    pub fn bank_port(&mut self, port: u8, register: usize) {
+        if register >= self.banks.len() {
+            self.banks.resize(register + 1, 0);
+        }
+        self.bank_ports.push((port, register));
+    }
+
+    pub fn bank(&self, register: usize) -> u8 {
+        self.banks.get(register).copied().unwrap_or(0)
+    }
+
+    pub fn set_bank(&mut self, register: usize, bank: u8) {
+        if register >= self.banks.len() {
+            self.banks.resize(register + 1, 0);
+        }
+        self.banks[register] = bank;
+    }
+
+    /// Finds the region holding `addr` and the offset inside it, following mirrors.
+    fn find(&self, addr: u16) -> Option<(usize, usize)> {
+        let mut addr = addr;
+
+        for _ in 0..MAX_MIRRORS {
+            let (n, offset) = self.regions.iter().enumerate().find_map(|(i, map)| {
+                let offset = addr.wrapping_sub(map.start) as usize;
+                if addr >= map.start && offset < map.region.len() {
+                    Some((i, offset))
+                } else {
+                    None
+                }
+            })?;
+
+            match self.regions[n].region {
+                Region::Mirror { target, period, .. } => {
+                    addr = target.wrapping_add((offset % period) as u16);
+                }
+                _ => return Some((n, offset)),
+            }
+        }
+
+        None
+    }
+
+    fn bank_index(&self, register: usize, count: usize) -> usize {
+        self.bank(register) as usize % count
+    }
+
+    /// Copies `data` at `addr`, writing through ROM regions and into the
+    /// selected banks.
+    pub fn load(&mut self, addr: u16, data: &[u8]) {
+        for (i, byte) in data.iter().enumerate() {
+            let addr = addr.wrapping_add(i as u16);
+            if let Some((n, offset)) = self.find(addr) {
+                let bank = match &self.regions[n].region {
+                    Region::Banked {
+                        register, banks, ..
+                    } => self.bank_index(*register, banks.len()),
+                    _ => 0,
+                };
+
+                match &mut self.regions[n].region {
+                    Region::Ram(data) | Region::Rom(data) => data[offset] = *byte,
+                    Region::Banked { banks, .. } => banks[bank][offset] = *byte,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+impl Memory for Bus {
+    fn read_byte(&self, addr: u16) -> u8 {
+        let (n, offset) = match self.find(addr) {
+            Some(found) => found,
+            None => return 0xff,
+        };
+
+        match &self.regions[n].region {
+            Region::Ram(data) | Region::Rom(data) => data[offset],
+            Region::Mmio(_, dev) => dev.borrow_mut().read(offset as u16),
+            Region::Banked {
+                register, banks, ..
+            } => banks[self.bank_index(*register, banks.len())][offset],
+            Region::Mirror { .. } | Region::Unmapped(_) => 0xff,
+        }
+    }
+
+    fn read_word(&self, addr: u16) -> u16 {
+        u16::from_le_bytes([self.read_byte(addr), self.read_byte(addr.wrapping_add(1))])
+    }
+
+    fn write_byte(&mut self, addr: u16, byte: u8) {
+        let (n, offset) = match self.find(addr) {
+            Some(found) => found,
+            None => return,
+        };
+
+        let bank = match &self.regions[n].region {
+            Region::Banked {
+                register, banks, ..
+            } => self.bank_index(*register, banks.len()),
+            _ => 0,
+        };
+
+        match &mut self.regions[n].region {
+            Region::Ram(data) => data[offset] = byte,
+            Region::Mmio(_, dev) => dev.borrow_mut().write(offset as u16, byte),
+            Region::Banked {
+                banks,
+                writable: true,
+                ..
+            } => banks[bank][offset] = byte,
+            _ => {}
+        }
+    }
+
+    fn write_word(&mut self, addr: u16, word: u16) {
+        let b = word.to_le_bytes();
+        self.write_byte(addr, b[0]);
+        self.write_byte(addr.wrapping_add(1), b[1]);
+    }
+
+    fn in_port(&mut self, port: u8) -> u8 {
+        match self.bank_ports.iter().find(|(p, _)| *p == port) {
+            Some((_, register)) => self.bank(*register),
+            None => 0xff,
+        }
+    }
+
+    fn out_port(&mut self, port: u8, byte: u8) {
+        for (p, register) in &self.bank_ports {
+            if *p == port {
+                self.banks[*register] = byte;
+            }
+        }
+    }
+}
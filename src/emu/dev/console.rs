@@ -0,0 +1,328 @@
+use super::{span, Device};
+use std::{
+    collections::VecDeque,
+    io::{self, IsTerminal, Read, Write},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+const ESC: u8 = 0x1b;
+
+/// Layout of the console ports, relative to the base port of the device.
+#[derive(Debug, Clone, Copy)]
+pub struct ConsolePorts {
+    /// Data port
+    pub data: u8,
+    /// Status port
+    pub status: u8,
+    /// Status bits set when a key is waiting
+    pub rx_ready: u8,
+    /// Status bits set when a character can be written
+    pub tx_ready: u8,
+    /// Status bits are inverted
+    pub active_low: bool,
+}
+
+impl Default for ConsolePorts {
+    fn default() -> Self {
+        Self {
+            data: 0,
+            status: 1,
+            rx_ready: 0x02,
+            tx_ready: 0x01,
+            active_low: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Vt52 {
+    Text,
+    Escape,
+    Row,
+    Column(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ansi {
+    Text,
+    Escape,
+    Csi,
+}
+
+/// Console mapping a data and a status port to a host stream.
+///
+/// Input is read on a separate thread so the cpu never blocks on it. With
+/// VT52 translation enabled, VT52 escape sequences written by the program
+/// are turned into ANSI ones and ANSI cursor keys into VT52 ones.
+pub struct Console {
+    ports: ConsolePorts,
+    /// Bytes read by the input thread
+    rx: Receiver<u8>,
+    /// Input ready for the program
+    input: VecDeque<u8>,
+    output: Box<dyn Write>,
+    /// Translate VT52 to and from ANSI
+    vt52: bool,
+    out_state: Vt52,
+    in_state: Ansi,
+    /// Key that stops the emulation instead of reaching the program
+    escape: Option<u8>,
+    /// Input stream closed
+    closed: bool,
+    /// Program polled for input after the input stream was closed
+    starved: bool,
+    /// Escape key pressed
+    quit: bool,
+}
+
+impl Console {
+    pub fn new<R, W>(ports: ConsolePorts, mut input: R, output: W) -> Self
+    where
+        R: Read + Send + 'static,
+        W: Write + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut buf = [0; 64];
+
+            while let Ok(n @ 1..) = input.read(&mut buf) {
+                if buf[..n].iter().any(|byte| tx.send(*byte).is_err()) {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            ports,
+            rx,
+            input: VecDeque::new(),
+            output: Box::new(output),
+            vt52: false,
+            out_state: Vt52::Text,
+            in_state: Ansi::Text,
+            escape: None,
+            closed: false,
+            starved: false,
+            quit: false,
+        }
+    }
+
+    /// Console on the standard input and output of the host.
+    pub fn stdio(ports: ConsolePorts) -> Self {
+        Self::new(ports, io::stdin(), io::stdout())
+    }
+
+    pub fn set_vt52(&mut self, vt52: bool) {
+        self.vt52 = vt52;
+    }
+
+    /// Sets the key that stops the emulation, `quit` reports it.
+    pub fn set_escape(&mut self, escape: Option<u8>) {
+        self.escape = escape;
+    }
+
+    pub fn quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Input is over and the program is waiting for more.
+    pub fn starved(&self) -> bool {
+        self.starved
+    }
+
+    fn poll(&mut self) {
+        loop {
+            match self.rx.try_recv() {
+                Ok(byte) => self.feed(byte),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    fn feed(&mut self, byte: u8) {
+        if Some(byte) == self.escape {
+            self.quit = true;
+            return;
+        }
+
+        if !self.vt52 {
+            self.input.push_back(byte);
+            return;
+        }
+
+        self.in_state = match (self.in_state, byte) {
+            (Ansi::Text, ESC) => Ansi::Escape,
+            (Ansi::Escape, b'[') => Ansi::Csi,
+            (Ansi::Csi, b'A'..=b'D') => {
+                self.input.extend([ESC, byte]);
+                Ansi::Text
+            }
+            (Ansi::Text, _) => {
+                self.input.push_back(byte);
+                Ansi::Text
+            }
+            (Ansi::Escape, _) => {
+                self.input.extend([ESC, byte]);
+                Ansi::Text
+            }
+            (Ansi::Csi, _) => {
+                self.input.extend([ESC, b'[', byte]);
+                Ansi::Text
+            }
+        };
+    }
+
+    fn emit(&mut self, bytes: &[u8]) {
+        let _ = self.output.write_all(bytes);
+    }
+
+    fn put(&mut self, byte: u8) {
+        if !self.vt52 {
+            self.emit(&[byte]);
+            let _ = self.output.flush();
+            return;
+        }
+
+        self.out_state = match (self.out_state, byte) {
+            (Vt52::Text, ESC) => Vt52::Escape,
+            (Vt52::Text, _) => {
+                self.emit(&[byte]);
+                Vt52::Text
+            }
+            (Vt52::Escape, b'Y') => Vt52::Row,
+            (Vt52::Escape, _) => {
+                match byte {
+                    b'A' => self.emit(b"\x1b[A"),
+                    b'B' => self.emit(b"\x1b[B"),
+                    b'C' => self.emit(b"\x1b[C"),
+                    b'D' => self.emit(b"\x1b[D"),
+                    b'H' => self.emit(b"\x1b[H"),
+                    b'I' => self.emit(b"\x1bM"),
+                    b'J' => self.emit(b"\x1b[J"),
+                    b'K' => self.emit(b"\x1b[K"),
+                    b'Z' => self.input.extend([ESC, b'/', b'K']),
+                    _ => {}
+                }
+                Vt52::Text
+            }
+            (Vt52::Row, _) => Vt52::Column(byte),
+            (Vt52::Column(row), _) => {
+                let seq = format!(
+                    "\x1b[{};{}H",
+                    row.saturating_sub(31),
+                    byte.saturating_sub(31)
+                );
+                self.emit(seq.as_bytes());
+                Vt52::Text
+            }
+        };
+        let _ = self.output.flush();
+    }
+
+    fn status(&mut self) -> u8 {
+        self.poll();
+
+        let mut status = self.ports.tx_ready;
+        if self.input.is_empty() {
+            self.starved = self.closed;
+        } else {
+            status |= self.ports.rx_ready;
+        }
+
+        if self.ports.active_low {
+            !status
+        } else {
+            status
+        }
+    }
+}
+
+impl Device for Console {
+    fn ports(&self) -> u16 {
+        span(&[self.ports.data, self.ports.status])
+    }
+
+    fn read(&mut self, port: u8) -> u8 {
+        if port == self.ports.status {
+            self.status()
+        } else if port == self.ports.data {
+            self.poll();
+            self.input.pop_front().unwrap_or(0)
+        } else {
+            0xff
+        }
+    }
+
+    fn write(&mut self, port: u8, byte: u8) {
+        if port == self.ports.data {
+            self.put(byte);
+        }
+    }
+}
+
+/// Puts the host terminal in raw mode until dropped.
+pub struct RawMode {
+    /// Terminal settings before raw mode
+    #[cfg(unix)]
+    saved: libc::termios,
+}
+
+impl RawMode {
+    /// Enables raw mode if the standard input is a terminal, fails if the
+    /// terminal can't be put in raw mode (or isn't a unix terminal).
+    pub fn enable() -> io::Result<Option<Self>> {
+        if !io::stdin().is_terminal() {
+            return Ok(None);
+        }
+        Self::raw().map(Some)
+    }
+
+    #[cfg(unix)]
+    fn raw() -> io::Result<Self> {
+        use std::os::fd::AsRawFd;
+
+        let fd = io::stdin().as_raw_fd();
+        // SAFETY: termios is plain data, filled by tcgetattr before it is read
+        unsafe {
+            let mut saved = std::mem::zeroed();
+            if libc::tcgetattr(fd, &mut saved) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut raw = saved;
+            libc::cfmakeraw(&mut raw);
+            if libc::tcsetattr(fd, libc::TCSANOW, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { saved })
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn raw() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "raw mode needs a unix terminal",
+        ))
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        {
+            use std::os::fd::AsRawFd;
+
+            // SAFETY: restores the settings read by tcgetattr
+            unsafe {
+                libc::tcsetattr(io::stdin().as_raw_fd(), libc::TCSANOW, &self.saved);
+            }
+        }
+    }
+}
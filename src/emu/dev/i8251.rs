@@ -0,0 +1,169 @@
+use super::Device;
+use alloc::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    Mode,
+    Sync1,
+    Sync2,
+    Command,
+}
+
+/// Intel 8251 programmable communication interface.
+///
+/// Port 0 is the data register and port 1 the mode, command and status
+/// register. Characters are exchanged with the host through `receive`
+/// and `transmit`; output line 0 is RxRDY and line 1 is TxRDY.
+#[derive(Debug, Clone)]
+pub struct I8251 {
+    /// Next word expected on the control port
+    expect: Expect,
+    /// Mode instruction
+    mode: u8,
+    /// Sync characters
+    sync: [u8; 2],
+    /// Command instruction
+    command: u8,
+    /// Parity, overrun and framing error bits
+    errors: u8,
+    /// Data set ready input
+    dsr: bool,
+    /// Characters waiting on the receive line
+    rx: VecDeque<u8>,
+    /// Characters sent on the transmit line
+    tx: VecDeque<u8>,
+}
+
+impl Default for I8251 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl I8251 {
+    pub fn new() -> Self {
+        Self {
+            expect: Expect::Mode,
+            mode: 0,
+            sync: [0; 2],
+            command: 0,
+            errors: 0,
+            dsr: false,
+            rx: VecDeque::new(),
+            tx: VecDeque::new(),
+        }
+    }
+
+    fn tx_enabled(&self) -> bool {
+        self.command & 0x01 != 0
+    }
+
+    fn rx_enabled(&self) -> bool {
+        self.command & 0x04 != 0
+    }
+
+    /// Queues `byte` on the receive line.
+    pub fn receive(&mut self, byte: u8) {
+        self.rx.push_back(byte);
+    }
+
+    /// Takes the oldest character sent by the cpu.
+    pub fn transmit(&mut self) -> Option<u8> {
+        self.tx.pop_front()
+    }
+
+    /// Drives the data set ready input.
+    pub fn set_dsr(&mut self, level: bool) {
+        self.dsr = level;
+    }
+
+    /// State of the data terminal ready output.
+    pub fn dtr(&self) -> bool {
+        self.command & 0x02 != 0
+    }
+
+    /// State of the request to send output.
+    pub fn rts(&self) -> bool {
+        self.command & 0x20 != 0
+    }
+
+    pub fn status(&self) -> u8 {
+        let mut status = self.errors;
+        status |= 0x01 | 0x04;
+        status |= ((self.rx_enabled() && !self.rx.is_empty()) as u8) << 1;
+        status |= (self.dsr as u8) << 7;
+        status
+    }
+
+    fn write_mode(&mut self, byte: u8) {
+        self.mode = byte;
+        self.expect = if byte & 0x03 != 0 {
+            Expect::Command
+        } else {
+            Expect::Sync1
+        };
+    }
+
+    fn write_command(&mut self, byte: u8) {
+        if byte & 0x40 != 0 {
+            self.command = 0;
+            self.expect = Expect::Mode;
+            return;
+        }
+
+        if byte & 0x10 != 0 {
+            self.errors = 0;
+        }
+        self.command = byte & !0x10;
+    }
+}
+
+impl Device for I8251 {
+    fn ports(&self) -> u16 {
+        2
+    }
+
+    fn read(&mut self, port: u8) -> u8 {
+        if port & 1 == 1 {
+            self.status()
+        } else if self.rx_enabled() {
+            self.rx.pop_front().unwrap_or(0)
+        } else {
+            0
+        }
+    }
+
+    fn write(&mut self, port: u8, byte: u8) {
+        if port & 1 == 0 {
+            if self.tx_enabled() {
+                self.tx.push_back(byte);
+            }
+            return;
+        }
+
+        match self.expect {
+            Expect::Mode => self.write_mode(byte),
+            Expect::Sync1 => {
+                self.sync[0] = byte;
+                self.expect = if self.mode & 0x80 != 0 {
+                    Expect::Command
+                } else {
+                    Expect::Sync2
+                };
+            }
+            Expect::Sync2 => {
+                self.sync[1] = byte;
+                self.expect = Expect::Command;
+            }
+            Expect::Command => self.write_command(byte),
+        }
+    }
+
+    fn line(&self, line: u8) -> bool {
+        match line {
+            0 => self.rx_enabled() && !self.rx.is_empty(),
+            1 => self.tx_enabled(),
+            _ => false,
+        }
+    }
+}
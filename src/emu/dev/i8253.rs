@@ -0,0 +1,321 @@
+use super::Device;
+
+#[derive(Debug, Default, Clone)]
+struct Counter {
+    /// Operating mode
+    mode: u8,
+    /// Binary coded decimal counting
+    bcd: bool,
+    /// Read/write access, 1 lsb, 2 msb, 3 lsb then msb
+    access: u8,
+    /// Count register
+    reload: u16,
+    /// Counting element
+    count: u32,
+    /// Output
+    out: bool,
+    /// Gate input
+    gate: bool,
+    /// Gate rising edge seen since the last clock
+    trigger: bool,
+    /// Count register written but not loaded yet
+    loading: bool,
+    /// Counting element running
+    running: bool,
+    /// Next write is the msb of a 2-byte count
+    write_msb: bool,
+    /// Next read is the msb of a 2-byte count
+    read_msb: bool,
+    /// Latched count
+    latch: Option<u16>,
+}
+
+impl Counter {
+    fn new() -> Self {
+        Self {
+            gate: true,
+            ..Self::default()
+        }
+    }
+
+    fn set_control(&mut self, byte: u8) {
+        let access = (byte >> 4) & 3;
+
+        if access == 0 {
+            if self.latch.is_none() {
+                self.latch = Some(self.value());
+            }
+            return;
+        }
+
+        self.access = access;
+        self.mode = match (byte >> 1) & 7 {
+            m @ 0..=5 => m,
+            m => m & 3,
+        };
+        self.bcd = byte & 1 != 0;
+        self.out = self.mode != 0;
+        self.running = false;
+        self.loading = false;
+        self.write_msb = false;
+        self.read_msb = false;
+        self.latch = None;
+    }
+
+    fn initial(&self) -> u32 {
+        if self.reload == 0 {
+            if self.bcd {
+                10000
+            } else {
+                0x10000
+            }
+        } else if self.bcd {
+            let mut value = 0;
+            for i in (0..4).rev() {
+                value = value * 10 + ((self.reload >> (i * 4)) & 0xf) as u32;
+            }
+            value
+        } else {
+            self.reload as u32
+        }
+    }
+
+    fn value(&self) -> u16 {
+        let count = self.count % if self.bcd { 10000 } else { 0x10000 };
+
+        if self.bcd {
+            let mut value = 0;
+            let mut count = count;
+            for i in 0..4 {
+                value |= ((count % 10) as u16) << (i * 4);
+                count /= 10;
+            }
+            value
+        } else {
+            count as u16
+        }
+    }
+
+    fn write(&mut self, byte: u8) {
+        match self.access {
+            1 => self.reload = byte as u16,
+            2 => self.reload = (byte as u16) << 8,
+            _ => {
+                if self.write_msb {
+                    self.reload = (self.reload & 0xff) | (byte as u16) << 8;
+                } else {
+                    self.reload = byte as u16;
+                    self.write_msb = true;
+                    if self.mode == 0 {
+                        self.out = false;
+                        self.running = false;
+                    }
+                    return;
+                }
+                self.write_msb = false;
+            }
+        }
+
+        match self.mode {
+            0 => {
+                self.out = false;
+                self.loading = true;
+            }
+            1 | 5 => {}
+            2 | 3 if self.running => {}
+            _ => self.loading = true,
+        }
+    }
+
+    fn read(&mut self) -> u8 {
+        let value = if let Some(latch) = self.latch {
+            if self.access != 3 || self.read_msb {
+                self.latch = None;
+            }
+            latch
+        } else {
+            self.value()
+        };
+
+        match self.access {
+            1 => value as u8,
+            2 => (value >> 8) as u8,
+            _ => {
+                self.read_msb = !self.read_msb;
+                if self.read_msb {
+                    value as u8
+                } else {
+                    (value >> 8) as u8
+                }
+            }
+        }
+    }
+
+    fn set_gate(&mut self, level: bool) {
+        if level && !self.gate {
+            self.trigger = true;
+        }
+        self.gate = level;
+
+        if !level && (self.mode == 2 || self.mode == 3) {
+            self.out = true;
+        }
+    }
+
+    fn load(&mut self) {
+        self.count = self.initial();
+        if self.mode == 3 {
+            self.count = self.count.div_ceil(2);
+        }
+        self.loading = false;
+        self.running = true;
+    }
+
+    fn clock(&mut self) {
+        let trigger = self.trigger;
+        self.trigger = false;
+
+        match self.mode {
+            0 | 4 => {
+                if self.loading {
+                    self.load();
+                } else if self.running && self.gate {
+                    self.count -= 1;
+                    if self.count == 0 {
+                        if self.mode == 0 {
+                            self.out = true;
+                            self.count = if self.bcd { 10000 } else { 0x10000 };
+                        } else {
+                            self.out = false;
+                            self.running = false;
+                        }
+                    }
+                } else if self.mode == 4 {
+                    self.out = true;
+                }
+            }
+            1 | 5 => {
+                if trigger && !self.write_msb {
+                    self.load();
+                    self.out = self.mode == 5;
+                } else if self.running {
+                    self.count -= 1;
+                    if self.count == 0 {
+                        self.running = false;
+                        self.out = self.mode == 1;
+                    }
+                } else if self.mode == 5 {
+                    self.out = true;
+                }
+            }
+            2 => {
+                if self.loading || trigger {
+                    self.load();
+                    self.out = true;
+                } else if self.running && self.gate {
+                    self.count -= 1;
+                    if self.count == 1 {
+                        self.out = false;
+                    } else if self.count == 0 {
+                        self.out = true;
+                        self.count = self.initial();
+                    }
+                }
+            }
+            _ => {
+                if self.loading || trigger {
+                    self.out = true;
+                    self.load();
+                } else if self.running && self.gate {
+                    self.count -= 1;
+                    if self.count == 0 {
+                        self.out = !self.out;
+                        let initial = self.initial();
+                        self.count = if self.out {
+                            initial.div_ceil(2)
+                        } else {
+                            (initial / 2).max(1)
+                        };
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Intel 8253 programmable interval timer.
+///
+/// Ports 0 to 2 access the counters and port 3 takes the control word.
+/// The counters are clocked once every `prescale` cpu cycles.
+#[derive(Debug, Clone)]
+pub struct I8253 {
+    /// Counters
+    counters: [Counter; 3],
+    /// Cpu cycles per counter clock
+    prescale: usize,
+    /// Cpu cycles not yet turned into a counter clock
+    pending: usize,
+}
+
+impl I8253 {
+    pub fn new(prescale: usize) -> Self {
+        Self {
+            counters: [Counter::new(), Counter::new(), Counter::new()],
+            prescale: prescale.max(1),
+            pending: 0,
+        }
+    }
+
+    /// Drives the gate input of `counter`.
+    pub fn set_gate(&mut self, counter: usize, level: bool) {
+        self.counters[counter].set_gate(level);
+    }
+
+    /// State of the output of `counter`.
+    pub fn out(&self, counter: usize) -> bool {
+        self.counters[counter].out
+    }
+
+    /// Clocks every counter once.
+    pub fn clock(&mut self) {
+        for counter in self.counters.iter_mut() {
+            counter.clock();
+        }
+    }
+}
+
+impl Device for I8253 {
+    fn ports(&self) -> u16 {
+        4
+    }
+
+    fn read(&mut self, port: u8) -> u8 {
+        match port & 3 {
+            3 => 0xff,
+            n => self.counters[n as usize].read(),
+        }
+    }
+
+    fn write(&mut self, port: u8, byte: u8) {
+        match port & 3 {
+            3 => match byte >> 6 {
+                3 => {}
+                n => self.counters[n as usize].set_control(byte),
+            },
+            n => self.counters[n as usize].write(byte),
+        }
+    }
+
+    fn tick(&mut self, cycles: usize) {
+        self.pending += cycles;
+
+        while self.pending >= self.prescale {
+            self.pending -= self.prescale;
+            self.clock();
+        }
+    }
+
+    fn line(&self, line: u8) -> bool {
+        self.counters.get(line as usize).is_some_and(|c| c.out)
+    }
+}
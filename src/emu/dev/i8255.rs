@@ -0,0 +1,98 @@
+use super::Device;
+
+/// Intel 8255 programmable peripheral interface.
+///
+/// Ports 0 to 2 are the A, B and C ports and port 3 takes the control word.
+/// Every group works in mode 0; modes 1 and 2 are accepted but their
+/// handshake lines on port C are left to the host. Output line `n` is bit
+/// `n` of port C.
+#[derive(Debug, Clone)]
+pub struct I8255 {
+    /// Last mode definition control word
+    control: u8,
+    /// Output latches
+    latch: [u8; 3],
+    /// Levels driven by the host on the port pins
+    pins: [u8; 3],
+}
+
+impl Default for I8255 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl I8255 {
+    pub fn new() -> Self {
+        Self {
+            control: 0x9b,
+            latch: [0; 3],
+            pins: [0; 3],
+        }
+    }
+
+    /// Bits of `port` configured as inputs.
+    fn input_mask(&self, port: usize) -> u8 {
+        match port {
+            0 if self.control & 0x10 != 0 => 0xff,
+            1 if self.control & 0x02 != 0 => 0xff,
+            2 => {
+                let mut mask = 0;
+                if self.control & 0x08 != 0 {
+                    mask |= 0xf0;
+                }
+                if self.control & 0x01 != 0 {
+                    mask |= 0x0f;
+                }
+                mask
+            }
+            _ => 0,
+        }
+    }
+
+    /// Drives the pins of `port` configured as inputs.
+    pub fn set_input(&mut self, port: usize, byte: u8) {
+        self.pins[port] = byte;
+    }
+
+    /// Levels of `port` as seen from either side.
+    pub fn output(&self, port: usize) -> u8 {
+        let mask = self.input_mask(port);
+        (self.pins[port] & mask) | (self.latch[port] & !mask)
+    }
+}
+
+impl Device for I8255 {
+    fn ports(&self) -> u16 {
+        4
+    }
+
+    fn read(&mut self, port: u8) -> u8 {
+        match port & 3 {
+            3 => 0xff,
+            n => self.output(n as usize),
+        }
+    }
+
+    fn write(&mut self, port: u8, byte: u8) {
+        match port & 3 {
+            3 if byte & 0x80 != 0 => {
+                self.control = byte;
+                self.latch = [0; 3];
+            }
+            3 => {
+                let bit = 1 << ((byte >> 1) & 7);
+                if byte & 1 != 0 {
+                    self.latch[2] |= bit;
+                } else {
+                    self.latch[2] &= !bit;
+                }
+            }
+            n => self.latch[n as usize] = byte,
+        }
+    }
+
+    fn line(&self, line: u8) -> bool {
+        self.output(2) & (1 << (line & 7)) != 0
+    }
+}
@@ -0,0 +1,255 @@
+use super::Device;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    Icw2,
+    Icw3,
+    Icw4,
+    Ocw,
+}
+
+/// Intel 8259 programmable interrupt controller in MCS-80 mode.
+///
+/// Port 0 takes ICW1, OCW2 and OCW3, port 1 takes the remaining initialization
+/// words and OCW1. Acknowledged interrupts yield the address of the CALL
+/// instruction the chip would put on the data bus.
+#[derive(Debug)]
+pub struct I8259 {
+    /// Initialization command words
+    icw: [u8; 4],
+    /// Next word expected on port 1
+    expect: Expect,
+    /// Interrupt request register
+    irr: u8,
+    /// In-service register
+    isr: u8,
+    /// Interrupt mask register
+    imr: u8,
+    /// Last level of each input, for edge detection
+    levels: u8,
+    /// Level with the lowest priority
+    lowest: u8,
+    /// Read the in-service register instead of the request register
+    read_isr: bool,
+    /// Next read of port 0 returns the poll word
+    poll: bool,
+    /// Rotate priorities in automatic EOI mode
+    rotate_aeoi: bool,
+}
+
+impl Default for I8259 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl I8259 {
+    pub fn new() -> Self {
+        Self {
+            icw: [0; 4],
+            expect: Expect::Icw2,
+            irr: 0,
+            isr: 0,
+            imr: 0xff,
+            levels: 0,
+            lowest: 7,
+            read_isr: false,
+            poll: false,
+            rotate_aeoi: false,
+        }
+    }
+
+    fn initialized(&self) -> bool {
+        self.expect == Expect::Ocw
+    }
+
+    fn level_triggered(&self) -> bool {
+        self.icw[0] & 0x08 != 0
+    }
+
+    fn auto_eoi(&self) -> bool {
+        self.icw[3] & 0x02 != 0
+    }
+
+    /// Drives the interrupt request input `ir`.
+    pub fn set_input(&mut self, ir: u8, level: bool) {
+        let bit = 1 << (ir & 7);
+
+        if level {
+            if self.level_triggered() || self.levels & bit == 0 {
+                self.irr |= bit;
+            }
+            self.levels |= bit;
+        } else {
+            if self.level_triggered() {
+                self.irr &= !bit;
+            }
+            self.levels &= !bit;
+        }
+    }
+
+    /// Priority rank of `ir`, 0 is the highest.
+    fn rank(&self, ir: u8) -> u8 {
+        ir.wrapping_sub(self.lowest + 1) & 7
+    }
+
+    fn highest(&self, reg: u8) -> Option<u8> {
+        (0..8)
+            .filter(|ir| reg & (1 << ir) != 0)
+            .min_by_key(|ir| self.rank(*ir))
+    }
+
+    fn pending(&self) -> Option<u8> {
+        let req = self.highest(self.irr & !self.imr)?;
+
+        match self.highest(self.isr) {
+            Some(serv) if self.rank(serv) <= self.rank(req) => None,
+            _ => Some(req),
+        }
+    }
+
+    /// State of the INT output.
+    pub fn int(&self) -> bool {
+        self.initialized() && self.pending().is_some()
+    }
+
+    fn service(&mut self) -> Option<u8> {
+        if !self.initialized() {
+            return None;
+        }
+
+        let ir = self.pending()?;
+        let bit = 1 << ir;
+        self.irr &= !bit;
+
+        if self.auto_eoi() {
+            if self.rotate_aeoi {
+                self.lowest = ir;
+            }
+        } else {
+            self.isr |= bit;
+        }
+
+        Some(ir)
+    }
+
+    /// Acknowledges the highest priority request, returns the call address.
+    pub fn acknowledge(&mut self) -> Option<u16> {
+        let ir = self.service()? as u16;
+
+        let low = if self.icw[0] & 0x04 != 0 {
+            (self.icw[0] & 0xe0) as u16 | (ir << 2)
+        } else {
+            (self.icw[0] & 0xc0) as u16 | (ir << 3)
+        };
+
+        Some((self.icw[1] as u16) << 8 | low)
+    }
+
+    fn eoi(&mut self, ir: Option<u8>, rotate: bool) {
+        if let Some(ir) = ir.or_else(|| self.highest(self.isr)) {
+            self.isr &= !(1 << ir);
+            if rotate {
+                self.lowest = ir;
+            }
+        }
+    }
+
+    fn write_ocw2(&mut self, byte: u8) {
+        let level = byte & 7;
+
+        match byte >> 5 {
+            0b001 => self.eoi(None, false),
+            0b011 => self.eoi(Some(level), false),
+            0b101 => self.eoi(None, true),
+            0b111 => self.eoi(Some(level), true),
+            0b100 => self.rotate_aeoi = true,
+            0b000 => self.rotate_aeoi = false,
+            0b110 => self.lowest = level,
+            _ => {}
+        }
+    }
+
+    fn write_ocw3(&mut self, byte: u8) {
+        if byte & 0x02 != 0 {
+            self.read_isr = byte & 0x01 != 0;
+        }
+        self.poll = byte & 0x04 != 0;
+    }
+}
+
+impl Device for I8259 {
+    fn ports(&self) -> u16 {
+        2
+    }
+
+    fn read(&mut self, port: u8) -> u8 {
+        if port & 1 == 1 {
+            self.imr
+        } else if self.poll {
+            self.poll = false;
+            match self.service() {
+                Some(ir) => 0x80 | ir,
+                None => 0,
+            }
+        } else if self.read_isr {
+            self.isr
+        } else {
+            self.irr
+        }
+    }
+
+    fn write(&mut self, port: u8, byte: u8) {
+        if port & 1 == 0 {
+            if byte & 0x10 != 0 {
+                self.icw = [byte, 0, 0, 0];
+                self.expect = Expect::Icw2;
+                self.irr = 0;
+                self.isr = 0;
+                self.imr = 0;
+                self.levels = 0;
+                self.lowest = 7;
+                self.read_isr = false;
+                self.poll = false;
+                self.rotate_aeoi = false;
+            } else if byte & 0x08 != 0 {
+                self.write_ocw3(byte);
+            } else {
+                self.write_ocw2(byte);
+            }
+            return;
+        }
+
+        match self.expect {
+            Expect::Icw2 => {
+                self.icw[1] = byte;
+                self.expect = if self.icw[0] & 0x02 == 0 {
+                    Expect::Icw3
+                } else if self.icw[0] & 0x01 != 0 {
+                    Expect::Icw4
+                } else {
+                    Expect::Ocw
+                };
+            }
+            Expect::Icw3 => {
+                self.icw[2] = byte;
+                self.expect = if self.icw[0] & 0x01 != 0 {
+                    Expect::Icw4
+                } else {
+                    Expect::Ocw
+                };
+            }
+            Expect::Icw4 => {
+                self.icw[3] = byte;
+                self.expect = Expect::Ocw;
+            }
+            Expect::Ocw => {
+                self.imr = byte;
+            }
+        }
+    }
+
+    fn line(&self, _line: u8) -> bool {
+        self.int()
+    }
+}
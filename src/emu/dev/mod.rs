@@ -0,0 +1,211 @@
+use super::Memory;
+use alloc::{boxed::Box, collections::VecDeque, rc::Rc, vec::Vec};
+use core::cell::RefCell;
+
+#[cfg(feature = "std")]
+pub mod console;
+pub mod i8251;
+pub mod i8253;
+pub mod i8255;
+pub mod i8259;
+#[cfg(feature = "std")]
+pub mod tape;
+
+#[cfg(feature = "std")]
+pub use console::Console;
+pub use i8251::I8251;
+pub use i8253::I8253;
+pub use i8255::I8255;
+pub use i8259::I8259;
+#[cfg(feature = "std")]
+pub use tape::Tape;
+
+/// A peripheral occupying a range of consecutive I/O ports.
+pub trait Device {
+    /// Number of ports used by the device, starting from its base port, up
+    /// to 256.
+    fn ports(&self) -> u16;
+
+    /// Reads from `port`, relative to the device base port.
+    fn read(&mut self, port: u8) -> u8;
+
+    /// Writes to `port`, relative to the device base port.
+    fn write(&mut self, port: u8, byte: u8);
+
+    /// Advances the device by `cycles` cpu cycles.
+    fn tick(&mut self, _cycles: usize) {}
+
+    /// State of the output `line`, used to drive interrupt requests.
+    fn line(&self, _line: u8) -> bool {
+        false
+    }
+}
+
+pub fn shared<T>(dev: T) -> Rc<RefCell<T>> {
+    Rc::new(RefCell::new(dev))
+}
+
+/// Number of ports spanned by a device using the ports at `offsets`.
+pub fn span(offsets: &[u8]) -> u16 {
+    offsets
+        .iter()
+        .map(|offset| *offset as u16 + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+struct Mapping {
+    base: u8,
+    dev: Rc<RefCell<dyn Device>>,
+}
+
+struct IrqLine {
+    dev: Rc<RefCell<dyn Device>>,
+    line: u8,
+    input: u8,
+}
+
+struct VectorLine {
+    dev: Rc<RefCell<dyn Device>>,
+    line: u8,
+    vector: u16,
+    level: bool,
+}
+
+/// Memory wrapper that dispatches port I/O to the mapped devices.
+pub struct PortBus {
+    /// Wrapped memory
+    mem: Box<dyn Memory>,
+    /// Mapped devices
+    devices: Vec<Mapping>,
+    /// Interrupt controller
+    pic: Option<Rc<RefCell<I8259>>>,
+    /// Device lines connected to the interrupt controller
+    irqs: Vec<IrqLine>,
+    /// Device lines connected straight to the cpu
+    vectors: Vec<VectorLine>,
+    /// Vectors requested by the device lines
+    pending: VecDeque<u16>,
+}
+
+impl PortBus {
+    pub fn new(mem: Box<dyn Memory>) -> Self {
+        Self {
+            mem,
+            devices: Vec::new(),
+            pic: None,
+            irqs: Vec::new(),
+            vectors: Vec::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn map(&mut self, base: u8, dev: Rc<RefCell<dyn Device>>) {
+        self.devices.push(Mapping { base, dev });
+    }
+
+    /// Maps `pic` at `base` and uses it as the interrupt source of the cpu.
+    pub fn map_pic(&mut self, base: u8, pic: Rc<RefCell<I8259>>) {
+        self.map(base, pic.clone());
+        self.pic = Some(pic);
+    }
+
+    /// Connects the output `line` of `dev` to the interrupt controller `input`.
+    pub fn connect(&mut self, dev: Rc<RefCell<dyn Device>>, line: u8, input: u8) {
+        self.irqs.push(IrqLine { dev, line, input });
+    }
+
+    /// Connects the output `line` of `dev` straight to the cpu, a rising edge
+    /// requests an interrupt calling `vector`.
+    pub fn connect_vector(&mut self, dev: Rc<RefCell<dyn Device>>, line: u8, vector: u16) {
+        self.vectors.push(VectorLine {
+            dev,
+            line,
+            vector,
+            level: false,
+        });
+    }
+
+    fn find(&self, port: u8) -> Option<(&Mapping, u8)> {
+        self.devices.iter().find_map(|map| {
+            let offset = port.wrapping_sub(map.base);
+            if port >= map.base && (offset as u16) < map.dev.borrow().ports() {
+                Some((map, offset))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl Memory for PortBus {
+    fn read_byte(&self, addr: u16) -> u8 {
+        self.mem.read_byte(addr)
+    }
+
+    fn read_word(&self, addr: u16) -> u16 {
+        self.mem.read_word(addr)
+    }
+
+    fn write_byte(&mut self, addr: u16, byte: u8) {
+        self.mem.write_byte(addr, byte);
+    }
+
+    fn write_word(&mut self, addr: u16, word: u16) {
+        self.mem.write_word(addr, word);
+    }
+
+    fn in_port(&mut self, port: u8) -> u8 {
+        if let Some((map, offset)) = self.find(port) {
+            map.dev.borrow_mut().read(offset)
+        } else {
+            self.mem.in_port(port)
+        }
+    }
+
+    fn out_port(&mut self, port: u8, byte: u8) {
+        if let Some((map, offset)) = self.find(port) {
+            map.dev.borrow_mut().write(offset, byte);
+        } else {
+            self.mem.out_port(port, byte);
+        }
+    }
+
+    fn tick(&mut self, cycles: usize) {
+        self.mem.tick(cycles);
+
+        for map in &self.devices {
+            map.dev.borrow_mut().tick(cycles);
+        }
+
+        if let Some(pic) = &self.pic {
+            let mut pic = pic.borrow_mut();
+            for irq in &self.irqs {
+                pic.set_input(irq.input, irq.dev.borrow().line(irq.line));
+            }
+        }
+
+        for v in self.vectors.iter_mut() {
+            let level = v.dev.borrow().line(v.line);
+            if level && !v.level {
+                self.pending.push_back(v.vector);
+            }
+            v.level = level;
+        }
+    }
+
+    fn interrupt(&mut self) -> Option<u16> {
+        let vector = match &self.pic {
+            Some(pic) => pic.borrow_mut().acknowledge(),
+            None => None,
+        };
+
+        vector
+            .or_else(|| self.pending.pop_front())
+            .or_else(|| self.mem.interrupt())
+    }
+
+    fn accepted(&mut self, vector: u16) {
+        self.mem.accepted(vector);
+    }
+}
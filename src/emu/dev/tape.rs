@@ -0,0 +1,392 @@
+use super::{span, Device};
+use crate::emu::audio::{read_wav, write_wav};
+use std::{f64::consts::PI, fs, io, path::Path};
+
+/// Layout of the tape ports, relative to the base port of the device.
+#[derive(Debug, Clone, Copy)]
+pub struct TapePorts {
+    /// Data port
+    pub data: u8,
+    /// Status port
+    pub status: u8,
+    /// Status bits set when a byte can be read
+    pub rx_ready: u8,
+    /// Status bits set when a byte can be punched
+    pub tx_ready: u8,
+    /// Status bits are inverted
+    pub active_low: bool,
+}
+
+impl Default for TapePorts {
+    /// Altair 88-ACR layout, status at 0 and data at 1.
+    fn default() -> Self {
+        Self {
+            data: 1,
+            status: 0,
+            rx_ready: 0x01,
+            tx_ready: 0x80,
+            active_low: true,
+        }
+    }
+}
+
+/// How the program sees the tape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapeMode {
+    /// Whole bytes through the data port, flagged by the status port
+    Byte,
+    /// Serial line on bit 0 of the data port, a start bit, 8 data bits
+    /// and 2 stop bits per byte at `baud` bits per second
+    Bit { baud: u32, clock: u64 },
+}
+
+/// Format of the file holding the tape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapeFormat {
+    /// Bytes as they are
+    Raw,
+    /// Kansas City Standard audio, 300 baud
+    Kcs,
+    /// Paper tape rows drawn as text, one per byte
+    Punched,
+}
+
+impl TapeFormat {
+    /// Format from the extension, `.wav` for KCS and `.tape` for punched text.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("wav") => TapeFormat::Kcs,
+            Some("tape") => TapeFormat::Punched,
+            _ => TapeFormat::Raw,
+        }
+    }
+}
+
+/// Sample rate of the KCS files written.
+pub const KCS_RATE: u32 = 48000;
+
+/// Mark bits before the first byte.
+const LEADER: usize = 20;
+
+/// Serial frame of `byte`, least significant bit first.
+fn frame(byte: u8) -> impl Iterator<Item = bool> {
+    std::iter::once(false)
+        .chain((0..8).map(move |i| byte & (1 << i) != 0))
+        .chain([true, true])
+}
+
+/// Serial receiver turning line bits back into bytes.
+#[derive(Debug, Clone, Default)]
+struct Receiver {
+    /// Bits received after the start bit
+    bits: Option<(u8, u8)>,
+    bytes: Vec<u8>,
+}
+
+impl Receiver {
+    fn push(&mut self, bit: bool) {
+        self.bits = match self.bits {
+            None if !bit => Some((0, 0)),
+            None => None,
+            Some((byte, n)) if n < 8 => Some((byte | (bit as u8) << n, n + 1)),
+            Some((byte, _)) => {
+                // Framing errors drop the byte
+                if bit {
+                    self.bytes.push(byte);
+                }
+                None
+            }
+        };
+    }
+}
+
+/// Encodes `bytes` as Kansas City Standard audio.
+pub fn kcs_encode(bytes: &[u8], rate: u32) -> Vec<i16> {
+    let bits = std::iter::repeat_n(true, LEADER)
+        .chain(bytes.iter().flat_map(|byte| frame(*byte)))
+        .chain(std::iter::repeat_n(true, LEADER));
+
+    let mut samples = Vec::new();
+    for (n, bit) in bits.enumerate() {
+        let freq = if bit { 2400.0 } else { 1200.0 };
+        let end = (n + 1) * rate as usize / 300;
+        let start = samples.len();
+        for i in start..end {
+            let t = (i - start) as f64 / rate as f64;
+            samples.push(((2.0 * PI * freq * t).sin() * 16000.0) as i16);
+        }
+    }
+    samples
+}
+
+/// Decodes Kansas City Standard audio, counting half cycles between zero
+/// crossings.
+pub fn kcs_decode(samples: &[i16], rate: u32) -> Vec<u8> {
+    // Half cycles are 1/2400 s long for a 0 and 1/4800 s long for a 1
+    let threshold = rate as usize / 3200;
+    let mut rx = Receiver::default();
+
+    let (mut last, mut positive) = (0, samples.first().is_some_and(|s| *s >= 0));
+    let (mut long, mut short) = (0, 0);
+
+    for (i, sample) in samples.iter().enumerate() {
+        if (*sample >= 0) == positive {
+            continue;
+        }
+        positive = !positive;
+
+        if i - last > threshold {
+            long += 1;
+            short = 0;
+            if long == 8 {
+                rx.push(false);
+                long = 0;
+            }
+        } else {
+            short += 1;
+            long = 0;
+            if short == 16 {
+                rx.push(true);
+                short = 0;
+            }
+        }
+        last = i;
+    }
+    rx.bytes
+}
+
+/// Draws `bytes` as paper tape rows, holes as `o` and the sprocket hole
+/// between bits 2 and 3.
+pub fn punch(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for byte in bytes {
+        text.push('|');
+        for bit in (0..8).rev() {
+            text.push(if byte & (1 << bit) != 0 { 'o' } else { ' ' });
+            if bit == 3 {
+                text.push('.');
+            }
+        }
+        text.push_str("|\n");
+    }
+    text
+}
+
+/// Reads back the rows drawn by `punch`, ignoring other lines.
+pub fn unpunch(text: &str) -> Vec<u8> {
+    text.lines()
+        .filter_map(|line| {
+            let row = line.trim_end().strip_prefix('|')?.strip_suffix('|')?;
+            let holes: Vec<char> = row.chars().filter(|c| *c != '.').collect();
+            if holes.len() != 8 || row.chars().nth(5) != Some('.') {
+                return None;
+            }
+            Some(
+                holes
+                    .iter()
+                    .fold(0, |byte, c| byte << 1 | (*c != ' ') as u8),
+            )
+        })
+        .collect()
+}
+
+/// Reads the tape at `path`.
+pub fn read_tape(path: &Path, format: TapeFormat) -> io::Result<Vec<u8>> {
+    match format {
+        TapeFormat::Raw => fs::read(path),
+        TapeFormat::Kcs => {
+            let (rate, samples) = read_wav(fs::File::open(path)?)?;
+            Ok(kcs_decode(&samples, rate))
+        }
+        TapeFormat::Punched => Ok(unpunch(&fs::read_to_string(path)?)),
+    }
+}
+
+/// Writes `bytes` as a tape at `path`.
+pub fn write_tape(path: &Path, format: TapeFormat, bytes: &[u8]) -> io::Result<()> {
+    match format {
+        TapeFormat::Raw => fs::write(path, bytes),
+        TapeFormat::Kcs => {
+            let out = io::BufWriter::new(fs::File::create(path)?);
+            write_wav(out, KCS_RATE, &kcs_encode(bytes, KCS_RATE))
+        }
+        TapeFormat::Punched => fs::write(path, punch(bytes)),
+    }
+}
+
+/// Tape reader and punch.
+///
+/// The reader starts with the first access to the data port; in bit mode the
+/// tape then runs at the baud rate whether the program keeps up or not.
+#[derive(Debug, Clone)]
+pub struct Tape {
+    ports: TapePorts,
+    mode: TapeMode,
+    /// Tape being read
+    input: Vec<u8>,
+    /// Next byte read in byte mode
+    pos: usize,
+    /// Bytes punched
+    output: Vec<u8>,
+    /// Cycles elapsed
+    cycles: usize,
+    /// Cycle the reader started at
+    start: Option<usize>,
+    /// Punch line level and cycle it was set at
+    line: (bool, usize),
+    /// Receiver decoding the punch line
+    rx: Receiver,
+}
+
+impl Tape {
+    pub fn new(ports: TapePorts, mode: TapeMode) -> Self {
+        Self {
+            ports,
+            mode,
+            input: Vec::new(),
+            pos: 0,
+            output: Vec::new(),
+            cycles: 0,
+            start: None,
+            line: (true, 0),
+            rx: Receiver::default(),
+        }
+    }
+
+    /// Loads `bytes` in the reader and rewinds it.
+    pub fn insert(&mut self, bytes: Vec<u8>) {
+        self.input = bytes;
+        self.pos = 0;
+        self.start = None;
+    }
+
+    /// Loads the tape at `path` in the reader.
+    pub fn load(&mut self, path: &Path) -> io::Result<()> {
+        self.insert(read_tape(path, TapeFormat::from_path(path))?);
+        Ok(())
+    }
+
+    /// Bytes punched so far.
+    pub fn punched(&self) -> Vec<u8> {
+        let mut output = self.output.clone();
+
+        if let TapeMode::Bit { .. } = self.mode {
+            // Bytes completed by the current level of the line
+            let mut rx = self.rx.clone();
+            let (level, since) = self.line;
+            for _ in 0..((self.cycles - since) / self.bit_cycles()).min(11) {
+                rx.push(level);
+            }
+            output.append(&mut rx.bytes);
+        }
+        output
+    }
+
+    /// Writes the bytes punched so far to `path`.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write_tape(path, TapeFormat::from_path(path), &self.punched())
+    }
+
+    fn bit_cycles(&self) -> usize {
+        match self.mode {
+            TapeMode::Bit { baud, clock } => (clock / baud.max(1) as u64).max(1) as usize,
+            TapeMode::Byte => 1,
+        }
+    }
+
+    /// Level of the reader line.
+    fn read_bit(&mut self) -> bool {
+        let start = *self.start.get_or_insert(self.cycles);
+        let n = (self.cycles - start) / self.bit_cycles();
+
+        match n.checked_sub(LEADER) {
+            Some(n) if n / 11 < self.input.len() => {
+                frame(self.input[n / 11]).nth(n % 11).unwrap_or(true)
+            }
+            _ => true,
+        }
+    }
+
+    /// Changes the level of the punch line, feeding the receiver with the
+    /// bits sent at the previous level.
+    fn set_line(&mut self, level: bool) {
+        let (old, since) = self.line;
+        if level == old {
+            return;
+        }
+
+        let bit = self.bit_cycles();
+        let bits = (self.cycles - since + bit / 2) / bit;
+        // Idle lines only need enough bits to end the current byte
+        for _ in 0..if old { bits.min(11) } else { bits } {
+            self.rx.push(old);
+        }
+
+        self.output.append(&mut self.rx.bytes);
+        self.line = (level, self.cycles);
+    }
+
+    /// The reader has input left, in bit mode until the last frame passed.
+    fn has_input(&self) -> bool {
+        match self.mode {
+            TapeMode::Byte => self.pos < self.input.len(),
+            TapeMode::Bit { .. } => {
+                let bits = self
+                    .start
+                    .map_or(0, |start| (self.cycles - start) / self.bit_cycles());
+                !self.input.is_empty() && bits < LEADER + 11 * self.input.len()
+            }
+        }
+    }
+
+    fn status(&self) -> u8 {
+        let mut status = self.ports.tx_ready;
+        if self.has_input() {
+            status |= self.ports.rx_ready;
+        }
+
+        if self.ports.active_low {
+            !status
+        } else {
+            status
+        }
+    }
+}
+
+impl Device for Tape {
+    fn ports(&self) -> u16 {
+        span(&[self.ports.data, self.ports.status])
+    }
+
+    fn read(&mut self, port: u8) -> u8 {
+        if port == self.ports.data {
+            match self.mode {
+                TapeMode::Byte => {
+                    let byte = self.input.get(self.pos).copied().unwrap_or(0);
+                    self.pos = (self.pos + 1).min(self.input.len());
+                    byte
+                }
+                TapeMode::Bit { .. } => self.read_bit() as u8,
+            }
+        } else if port == self.ports.status {
+            self.status()
+        } else {
+            0xff
+        }
+    }
+
+    fn write(&mut self, port: u8, byte: u8) {
+        if port != self.ports.data {
+            return;
+        }
+
+        match self.mode {
+            TapeMode::Byte => self.output.push(byte),
+            TapeMode::Bit { .. } => self.set_line(byte & 1 != 0),
+        }
+    }
+
+    fn tick(&mut self, cycles: usize) {
+        self.cycles += cycles;
+    }
+}
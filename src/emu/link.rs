@@ -0,0 +1,250 @@
+use super::{dev::I8251, Emulator};
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fs::OpenOptions,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::Path,
+    rc::Rc,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+/// Serial port whose bytes can be carried by the links of a `Scheduler`.
+pub trait SerialPort {
+    /// Takes the next byte sent by the port.
+    fn send(&mut self) -> Option<u8>;
+
+    /// Delivers a byte to the port.
+    fn receive(&mut self, byte: u8);
+}
+
+impl SerialPort for I8251 {
+    fn send(&mut self) -> Option<u8> {
+        self.transmit()
+    }
+
+    fn receive(&mut self, byte: u8) {
+        I8251::receive(self, byte)
+    }
+}
+
+/// Serial port of the host, e.g. a pty, a serial device or a socket.
+pub struct HostSerial {
+    rx: Receiver<u8>,
+    output: Box<dyn Write>,
+}
+
+impl HostSerial {
+    pub fn new<R, W>(mut input: R, output: W) -> Self
+    where
+        R: Read + Send + 'static,
+        W: Write + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut buf = [0; 64];
+            while let Ok(n @ 1..) = input.read(&mut buf) {
+                if buf[..n].iter().any(|byte| tx.send(*byte).is_err()) {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            rx,
+            output: Box::new(output),
+        }
+    }
+
+    /// Opens a device file, like a pty or a tty.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Self::new(file.try_clone()?, file))
+    }
+
+    /// Connects to a tcp socket.
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Ok(Self::new(stream.try_clone()?, stream))
+    }
+}
+
+impl SerialPort for HostSerial {
+    fn send(&mut self) -> Option<u8> {
+        self.rx.try_recv().ok()
+    }
+
+    fn receive(&mut self, byte: u8) {
+        let _ = self.output.write_all(&[byte]);
+        let _ = self.output.flush();
+    }
+}
+
+/// End of a link, on an emulator of the scheduler or on the host.
+struct End {
+    port: Rc<RefCell<dyn SerialPort>>,
+    /// Emulator the port belongs to, `None` for the host
+    node: Option<usize>,
+    /// Bytes travelling to this end and their arrival time in ns
+    inbound: VecDeque<(u64, u8)>,
+    /// Time the transmitter of this end is busy until
+    busy: u64,
+}
+
+/// Serial cable between two ports.
+struct Link {
+    ends: [End; 2],
+    /// Time a character takes on the line in ns
+    char_time: u64,
+}
+
+struct Node {
+    emu: Emulator,
+    /// Cpu clock in Hz
+    clock: u64,
+}
+
+/// Runs several emulators in lock step, exchanging bytes over serial links.
+///
+/// Every emulator has its own clock; the one furthest behind in time is
+/// stepped next, so no emulator gets more than an instruction ahead.
+#[derive(Default)]
+pub struct Scheduler {
+    nodes: Vec<Node>,
+    links: Vec<Link>,
+}
+
+/// Bits in a character, 8 data bits with a start and a stop bit.
+const CHAR_BITS: u64 = 10;
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an emulator running at `clock` Hz, returns its index.
+    pub fn add(&mut self, emu: Emulator, clock: u64) -> usize {
+        self.nodes.push(Node {
+            emu,
+            clock: clock.max(1),
+        });
+        self.nodes.len() - 1
+    }
+
+    pub fn emulator(&mut self, node: usize) -> &mut Emulator {
+        &mut self.nodes[node].emu
+    }
+
+    /// Time elapsed on `node` in ns.
+    pub fn time(&self, node: usize) -> u64 {
+        let node = &self.nodes[node];
+        (node.emu.cycles() as u128 * 1_000_000_000 / node.clock as u128) as u64
+    }
+
+    /// Time elapsed on the emulator furthest behind, in ns.
+    pub fn now(&self) -> u64 {
+        (0..self.nodes.len())
+            .map(|n| self.time(n))
+            .min()
+            .unwrap_or(0)
+    }
+
+    fn end(port: Rc<RefCell<dyn SerialPort>>, node: Option<usize>) -> End {
+        End {
+            port,
+            node,
+            inbound: VecDeque::new(),
+            busy: 0,
+        }
+    }
+
+    fn add_link(&mut self, ends: [End; 2], baud: u32) {
+        self.links.push(Link {
+            ends,
+            char_time: CHAR_BITS * 1_000_000_000 / baud.max(1) as u64,
+        });
+    }
+
+    /// Connects port `a` of emulator `node_a` to port `b` of emulator `node_b`.
+    pub fn link(
+        &mut self,
+        (node_a, a): (usize, Rc<RefCell<dyn SerialPort>>),
+        (node_b, b): (usize, Rc<RefCell<dyn SerialPort>>),
+        baud: u32,
+    ) {
+        let ends = [Self::end(a, Some(node_a)), Self::end(b, Some(node_b))];
+        self.add_link(ends, baud);
+    }
+
+    /// Connects port `port` of emulator `node` to a port of the host.
+    pub fn link_host(
+        &mut self,
+        (node, port): (usize, Rc<RefCell<dyn SerialPort>>),
+        host: Rc<RefCell<dyn SerialPort>>,
+        baud: u32,
+    ) {
+        let ends = [Self::end(port, Some(node)), Self::end(host, None)];
+        self.add_link(ends, baud);
+    }
+
+    /// Steps the emulator furthest behind, then moves the bytes on the links.
+    pub fn step(&mut self) {
+        let next = (0..self.nodes.len()).min_by_key(|n| self.time(*n));
+        if let Some(n) = next {
+            self.nodes[n].emu.step();
+        }
+
+        let times: Vec<u64> = (0..self.nodes.len()).map(|n| self.time(n)).collect();
+        let now = times.iter().copied().min().unwrap_or(0);
+
+        for link in self.links.iter_mut() {
+            for from in 0..2 {
+                let to = 1 - from;
+                // The host keeps up with the emulator on the other end
+                let time = |end: &End| end.node.map_or(now, |n| times[n]);
+
+                let sent = time(&link.ends[from]);
+                let end = &mut link.ends[from];
+                let byte = if sent >= end.busy {
+                    end.port.borrow_mut().send()
+                } else {
+                    None
+                };
+                if let Some(byte) = byte {
+                    end.busy = sent.max(end.busy) + link.char_time;
+                    let arrival = end.busy;
+                    link.ends[to].inbound.push_back((arrival, byte));
+                }
+
+                let received = time(&link.ends[to]);
+                let end = &mut link.ends[to];
+                while let Some((arrival, byte)) = end.inbound.front().copied() {
+                    if arrival > received {
+                        break;
+                    }
+                    end.port.borrow_mut().receive(byte);
+                    end.inbound.pop_front();
+                }
+            }
+        }
+    }
+
+    /// Runs until every emulator has run for `ns` more nanoseconds.
+    pub fn run_for(&mut self, ns: u64) {
+        let end = self.now() + ns;
+        while self.now() < end {
+            self.step();
+        }
+    }
+
+    /// Steps until `done` returns true.
+    pub fn run_until(&mut self, mut done: impl FnMut(&mut Self) -> bool) {
+        while !done(self) {
+            self.step();
+        }
+    }
+}
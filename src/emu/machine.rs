@@ -0,0 +1,658 @@
+use super::{
+    audio::{read_wav, Mixer, Sound, SoundEvent, Voice},
+    bus::Bus,
+    dev::{
+        console::ConsolePorts,
+        shared,
+        tape::{TapeMode, TapePorts},
+        Console, Device, PortBus, Tape, I8251, I8253, I8255, I8259,
+    },
+    video::{Bitmap, Rgb, Video},
+    Cpu, Emulator,
+};
+use serde::Deserialize;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+#[derive(Debug)]
+pub enum MachineError {
+    Io(PathBuf, io::Error),
+    Parse(String),
+    Invalid(String),
+}
+
+impl fmt::Display for MachineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MachineError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            MachineError::Parse(err) => write!(f, "malformed description: {}", err),
+            MachineError::Invalid(err) => write!(f, "invalid description: {}", err),
+        }
+    }
+}
+
+/// Board layout, as read from a TOML or JSON machine description.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Description {
+    /// Cpu, `"8080"`, `"8085"` or `"z80"`
+    #[serde(default)]
+    pub cpu: Cpu,
+    /// Cpu clock in Hz
+    pub clock: Option<u64>,
+    /// Initial program counter
+    #[serde(default)]
+    pub reset: u16,
+    #[serde(default)]
+    pub memory: Vec<RegionDesc>,
+    #[serde(default)]
+    pub device: Vec<DeviceDesc>,
+    #[serde(default)]
+    pub interrupt: Vec<InterruptDesc>,
+    pub video: Option<VideoDesc>,
+    pub audio: Option<AudioDesc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum RegionDesc {
+    Ram {
+        start: u16,
+        size: usize,
+        image: Option<PathBuf>,
+    },
+    Rom {
+        start: u16,
+        size: Option<usize>,
+        image: Option<PathBuf>,
+    },
+    /// `size` bytes repeating the `period` bytes at `target`
+    Mirror {
+        start: u16,
+        size: usize,
+        target: u16,
+        period: usize,
+    },
+    Unmapped {
+        start: u16,
+        size: usize,
+    },
+    /// Banks selected by writing their index to `port`
+    Banked {
+        start: u16,
+        size: usize,
+        banks: usize,
+        port: u8,
+        #[serde(default)]
+        rom: bool,
+        #[serde(default)]
+        images: Vec<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum DeviceKind {
+    I8251,
+    I8253 {
+        #[serde(default = "default_prescale")]
+        prescale: usize,
+    },
+    I8255,
+    I8259,
+    /// Sound latches
+    Sound {
+        #[serde(default = "default_ports")]
+        ports: u8,
+    },
+    /// Host terminal
+    Console(ConsoleDesc),
+    /// Tape reader and punch
+    Tape(TapeDesc),
+}
+
+/// Console ports relative to the device port, see `ConsolePorts`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ConsoleDesc {
+    pub data: u8,
+    pub status: u8,
+    pub rx_ready: u8,
+    pub tx_ready: u8,
+    pub active_low: bool,
+    /// Translate VT52 sequences to and from ANSI
+    pub vt52: bool,
+}
+
+impl Default for ConsoleDesc {
+    fn default() -> Self {
+        let ports = ConsolePorts::default();
+        Self {
+            data: ports.data,
+            status: ports.status,
+            rx_ready: ports.rx_ready,
+            tx_ready: ports.tx_ready,
+            active_low: ports.active_low,
+            vt52: false,
+        }
+    }
+}
+
+/// Tape ports relative to the device port, see `TapePorts`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TapeDesc {
+    pub data: u8,
+    pub status: u8,
+    pub rx_ready: u8,
+    pub tx_ready: u8,
+    pub active_low: bool,
+    /// Bit serial line at this rate instead of whole bytes
+    pub baud: Option<u32>,
+    /// Tape loaded in the reader
+    pub input: Option<PathBuf>,
+    /// File written by `Machine::save_tapes`
+    pub output: Option<PathBuf>,
+}
+
+impl Default for TapeDesc {
+    fn default() -> Self {
+        let ports = TapePorts::default();
+        Self {
+            data: ports.data,
+            status: ports.status,
+            rx_ready: ports.rx_ready,
+            tx_ready: ports.tx_ready,
+            active_low: ports.active_low,
+            baud: None,
+            input: None,
+            output: None,
+        }
+    }
+}
+
+fn default_prescale() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceDesc {
+    pub name: String,
+    /// Base port
+    pub port: u8,
+    #[serde(flatten)]
+    pub kind: DeviceKind,
+}
+
+/// Device output line driving either an 8259 input or a fixed vector.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InterruptDesc {
+    /// Name of the device driving the interrupt
+    pub source: String,
+    #[serde(default)]
+    pub line: u8,
+    /// 8259 input
+    pub input: Option<u8>,
+    /// Address called by the cpu
+    pub vector: Option<u16>,
+}
+
+/// Bitmap display, see `Bitmap`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VideoDesc {
+    pub base: u16,
+    pub width: usize,
+    pub height: usize,
+    #[serde(default)]
+    pub rotate: bool,
+    #[serde(default = "default_fps")]
+    pub fps: u64,
+    pub on: Option<Rgb>,
+    pub off: Option<Rgb>,
+    #[serde(default)]
+    pub interrupt: Vec<VideoInterruptDesc>,
+}
+
+/// Interrupt raised at a cycle of every frame, at vblank if `cycle` is missing.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VideoInterruptDesc {
+    pub cycle: Option<usize>,
+    /// Address called by the cpu
+    pub vector: u16,
+}
+
+/// Voices mixed from the writes to the sound devices.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AudioDesc {
+    /// Sample rate in Hz
+    #[serde(default = "default_rate")]
+    pub rate: u32,
+    #[serde(default)]
+    pub voice: Vec<VoiceDesc>,
+}
+
+/// See `Voice`, ports are absolute.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum VoiceDesc {
+    Level {
+        port: u8,
+        #[serde(default = "default_mask")]
+        mask: u8,
+    },
+    Trigger {
+        port: u8,
+        bit: u8,
+        /// 16 bit pcm wav file
+        sample: Option<PathBuf>,
+        #[serde(default)]
+        looped: bool,
+    },
+}
+
+fn default_ports() -> u8 {
+    1
+}
+
+fn default_rate() -> u32 {
+    44100
+}
+
+fn default_mask() -> u8 {
+    0xff
+}
+
+fn default_fps() -> u64 {
+    60
+}
+
+impl Description {
+    pub fn from_toml(src: &str) -> Result<Self, MachineError> {
+        toml::from_str(src).map_err(|e| MachineError::Parse(e.to_string()))
+    }
+
+    pub fn from_json(src: &str) -> Result<Self, MachineError> {
+        serde_json::from_str(src).map_err(|e| MachineError::Parse(e.to_string()))
+    }
+
+    /// Reads a description, JSON if the extension is `.json` and TOML otherwise.
+    pub fn open(path: &Path) -> Result<Self, MachineError> {
+        let src = fs::read_to_string(path).map_err(|e| MachineError::Io(path.to_path_buf(), e))?;
+
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json(&src)
+        } else {
+            Self::from_toml(&src)
+        }
+    }
+}
+
+/// Emulated system assembled from a `Description`.
+pub struct Machine {
+    emu: Emulator,
+    /// Cpu clock in Hz
+    clock: Option<u64>,
+    /// Devices by name
+    devices: HashMap<String, Rc<RefCell<dyn Device>>>,
+    /// Host terminal
+    console: Option<Rc<RefCell<Console>>>,
+    video: Option<Video>,
+    /// Sound devices
+    sounds: Vec<Rc<RefCell<Sound>>>,
+    mixer: Option<Mixer>,
+    /// Tapes and the files they are punched to
+    tapes: Vec<(Rc<RefCell<Tape>>, PathBuf)>,
+}
+
+fn read_image(base: &Path, image: &Option<PathBuf>) -> Result<Vec<u8>, MachineError> {
+    match image {
+        Some(image) => {
+            let path = base.join(image);
+            fs::read(&path).map_err(|e| MachineError::Io(path, e))
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+impl Machine {
+    /// Loads the description at `path`, images are relative to its directory.
+    pub fn open(path: &Path) -> Result<Self, MachineError> {
+        let desc = Description::open(path)?;
+        Self::build(&desc, path.parent().unwrap_or_else(|| Path::new("")))
+    }
+
+    /// Assembles `desc`, reading images relative to `base`.
+    pub fn build(desc: &Description, base: &Path) -> Result<Self, MachineError> {
+        let mut mem = Bus::new();
+        let mut bank_ports = HashMap::new();
+
+        for region in &desc.memory {
+            match region {
+                RegionDesc::Ram { start, size, image } => {
+                    let data = read_image(base, image)?;
+                    if data.len() > *size {
+                        return Err(MachineError::Invalid(format!(
+                            "image of {} bytes doesn't fit ram at {:#06x}",
+                            data.len(),
+                            start
+                        )));
+                    }
+                    mem.map_ram(*start, *size);
+                    mem.load(*start, &data);
+                }
+                RegionDesc::Rom { start, size, image } => {
+                    let mut data = read_image(base, image)?;
+                    match size {
+                        Some(size) if data.len() > *size => {
+                            return Err(MachineError::Invalid(format!(
+                                "image of {} bytes doesn't fit rom at {:#06x}",
+                                data.len(),
+                                start
+                            )));
+                        }
+                        Some(size) => data.resize(*size, 0xff),
+                        None => {}
+                    }
+                    mem.map_rom(*start, data);
+                }
+                RegionDesc::Mirror {
+                    start,
+                    size,
+                    target,
+                    period,
+                } => mem.map_mirror(*start, *size, *target, *period),
+                RegionDesc::Unmapped { start, size } => mem.map_unmapped(*start, *size),
+                RegionDesc::Banked {
+                    start,
+                    size,
+                    banks,
+                    port,
+                    rom,
+                    images,
+                } => {
+                    if images.len() > *banks {
+                        return Err(MachineError::Invalid(format!(
+                            "more images than banks at {:#06x}",
+                            start
+                        )));
+                    }
+
+                    let mut data = vec![vec![0; *size]; *banks];
+                    for (bank, image) in images.iter().enumerate() {
+                        let image = read_image(base, &Some(image.clone()))?;
+                        if image.len() > *size {
+                            return Err(MachineError::Invalid(format!(
+                                "image of {} bytes doesn't fit bank at {:#06x}",
+                                image.len(),
+                                start
+                            )));
+                        }
+                        data[bank][..image.len()].copy_from_slice(&image);
+                    }
+
+                    let register = bank_ports.len();
+                    let register = *bank_ports.entry(*port).or_insert_with(|| {
+                        mem.bank_port(*port, register);
+                        register
+                    });
+                    mem.map_banked(*start, register, data, !rom);
+                }
+            }
+        }
+
+        let mut bus = PortBus::new(Box::new(mem));
+        let mut devices = HashMap::new();
+        let mut pic = false;
+        let mut console = None;
+        let mut sounds = Vec::new();
+        let mut tapes = Vec::new();
+
+        for dev in &desc.device {
+            let handle: Rc<RefCell<dyn Device>> = match &dev.kind {
+                DeviceKind::I8251 => shared(I8251::new()),
+                DeviceKind::I8253 { prescale } => shared(I8253::new(*prescale)),
+                DeviceKind::I8255 => shared(I8255::new()),
+                DeviceKind::Sound { ports } => {
+                    let handle = shared(Sound::new(dev.port, *ports));
+                    sounds.push(handle.clone());
+                    handle
+                }
+                DeviceKind::I8259 => {
+                    if pic {
+                        return Err(MachineError::Invalid("more than one i8259".into()));
+                    }
+                    pic = true;
+
+                    let handle = shared(I8259::new());
+                    bus.map_pic(dev.port, handle.clone());
+                    handle
+                }
+                DeviceKind::Tape(tape) => {
+                    let mode = match (tape.baud, desc.clock) {
+                        (Some(baud), Some(clock)) => TapeMode::Bit { baud, clock },
+                        (Some(_), None) => {
+                            return Err(MachineError::Invalid(format!(
+                                "tape {} needs a clock",
+                                dev.name
+                            )));
+                        }
+                        (None, _) => TapeMode::Byte,
+                    };
+
+                    let mut dev = Tape::new(
+                        TapePorts {
+                            data: tape.data,
+                            status: tape.status,
+                            rx_ready: tape.rx_ready,
+                            tx_ready: tape.tx_ready,
+                            active_low: tape.active_low,
+                        },
+                        mode,
+                    );
+                    if let Some(input) = &tape.input {
+                        let path = base.join(input);
+                        dev.load(&path).map_err(|e| MachineError::Io(path, e))?;
+                    }
+
+                    let handle = shared(dev);
+                    if let Some(output) = &tape.output {
+                        tapes.push((handle.clone(), base.join(output)));
+                    }
+                    handle
+                }
+                DeviceKind::Console(desc) => {
+                    if console.is_some() {
+                        return Err(MachineError::Invalid("more than one console".into()));
+                    }
+
+                    let mut dev = Console::stdio(ConsolePorts {
+                        data: desc.data,
+                        status: desc.status,
+                        rx_ready: desc.rx_ready,
+                        tx_ready: desc.tx_ready,
+                        active_low: desc.active_low,
+                    });
+                    dev.set_vt52(desc.vt52);
+
+                    let handle = shared(dev);
+                    console = Some(handle.clone());
+                    handle
+                }
+            };
+
+            if !matches!(dev.kind, DeviceKind::I8259) {
+                bus.map(dev.port, handle.clone());
+            }
+            if devices.insert(dev.name.clone(), handle).is_some() {
+                return Err(MachineError::Invalid(format!(
+                    "device {} defined twice",
+                    dev.name
+                )));
+            }
+        }
+
+        for int in &desc.interrupt {
+            let dev = devices.get(&int.source).ok_or_else(|| {
+                MachineError::Invalid(format!("unknown interrupt source {}", int.source))
+            })?;
+
+            match (int.input, int.vector) {
+                (Some(input), None) if pic => bus.connect(dev.clone(), int.line, input),
+                (Some(_), None) => {
+                    return Err(MachineError::Invalid(format!(
+                        "interrupt from {} needs an i8259",
+                        int.source
+                    )))
+                }
+                (None, Some(vector)) => bus.connect_vector(dev.clone(), int.line, vector),
+                _ => {
+                    return Err(MachineError::Invalid(format!(
+                        "interrupt from {} needs either an input or a vector",
+                        int.source
+                    )))
+                }
+            }
+        }
+
+        let video = match (&desc.video, desc.clock) {
+            (Some(video), Some(clock)) => {
+                let mut fb = Bitmap::new(video.base, video.width, video.height);
+                fb.rotate = video.rotate;
+                fb.on = video.on.unwrap_or(fb.on);
+                fb.off = video.off.unwrap_or(fb.off);
+
+                let mut v = Video::new(Box::new(fb), clock, video.fps);
+                for int in &video.interrupt {
+                    match int.cycle {
+                        Some(cycle) => v.on_cycle(cycle, int.vector),
+                        None => v.on_vblank(int.vector),
+                    }
+                }
+                Some(v)
+            }
+            (Some(_), None) => {
+                return Err(MachineError::Invalid("video needs a clock".into()));
+            }
+            (None, _) => None,
+        };
+
+        let mixer = match (&desc.audio, desc.clock) {
+            (Some(audio), Some(clock)) => {
+                let mut mixer = Mixer::new(clock, audio.rate);
+                for voice in &audio.voice {
+                    mixer.add(match voice {
+                        VoiceDesc::Level { port, mask } => Voice::Level {
+                            port: *port,
+                            mask: *mask,
+                        },
+                        VoiceDesc::Trigger {
+                            port,
+                            bit,
+                            sample,
+                            looped,
+                        } => {
+                            if *bit > 7 {
+                                let msg = format!("trigger bit {} is out of range", bit);
+                                return Err(MachineError::Invalid(msg));
+                            }
+                            let sample = match sample {
+                                Some(sample) => {
+                                    let path = base.join(sample);
+                                    let file = fs::File::open(&path)
+                                        .map_err(|e| MachineError::Io(path.clone(), e))?;
+                                    read_wav(file).map_err(|e| MachineError::Io(path, e))?.1
+                                }
+                                None => Vec::new(),
+                            };
+
+                            Voice::Trigger {
+                                port: *port,
+                                bit: *bit,
+                                sample,
+                                looped: *looped,
+                            }
+                        }
+                    });
+                }
+                Some(mixer)
+            }
+            (Some(_), None) => {
+                return Err(MachineError::Invalid("audio needs a clock".into()));
+            }
+            (None, _) => None,
+        };
+
+        let mut emu = Emulator::with_cpu(Box::new(bus), desc.cpu);
+        emu.set_pc(desc.reset);
+
+        Ok(Self {
+            emu,
+            clock: desc.clock,
+            devices,
+            console,
+            video,
+            sounds,
+            mixer,
+            tapes,
+        })
+    }
+
+    pub fn emulator(&mut self) -> &mut Emulator {
+        &mut self.emu
+    }
+
+    pub fn clock(&self) -> Option<u64> {
+        self.clock
+    }
+
+    pub fn console(&self) -> Option<Rc<RefCell<Console>>> {
+        self.console.clone()
+    }
+
+    pub fn video(&self) -> Option<&Video> {
+        self.video.as_ref()
+    }
+
+    /// Emulator and video, to drive both at once.
+    pub fn parts(&mut self) -> (&mut Emulator, Option<&mut Video>) {
+        (&mut self.emu, self.video.as_mut())
+    }
+
+    pub fn mixer(&self) -> Option<&Mixer> {
+        self.mixer.as_ref()
+    }
+
+    /// Writes to every sound device, sorted by cycle.
+    pub fn sound_events(&self) -> Vec<SoundEvent> {
+        let mut events: Vec<_> = self
+            .sounds
+            .iter()
+            .flat_map(|sound| sound.borrow().events().to_vec())
+            .collect();
+        events.sort_by_key(|event| event.cycle);
+        events
+    }
+
+    /// Writes the punched tapes to their output files.
+    pub fn save_tapes(&self) -> Result<(), MachineError> {
+        for (tape, path) in &self.tapes {
+            tape.borrow()
+                .save(path)
+                .map_err(|e| MachineError::Io(path.clone(), e))?;
+        }
+        Ok(())
+    }
+
+    pub fn device(&self, name: &str) -> Option<Rc<RefCell<dyn Device>>> {
+        self.devices.get(name).cloned()
+    }
+}
@@ -1,3 +1,19 @@
+#[cfg(feature = "std")]
+pub mod audio;
+pub mod bus;
+pub mod dev;
+#[cfg(feature = "std")]
+pub mod link;
+#[cfg(feature = "std")]
+pub mod machine;
+#[cfg(feature = "std")]
+pub mod replay;
+#[cfg(feature = "std")]
+pub mod video;
+mod z80;
+
+use alloc::boxed::Box;
+
 const CYCLES: [usize; 256] = [
     4, 10, 7, 5, 5, 5, 7, 4, 4, 10, 7, 5, 5, 5, 7, 4, 4, 10, 7, 5, 5, 5, 7, 4, 4, 10, 7, 5, 5, 5,
     7, 4, 4, 10, 16, 5, 5, 5, 7, 4, 4, 10, 16, 5, 5, 5, 7, 4, 4, 10, 13, 5, 10, 10, 10, 4, 4, 10,
@@ -10,6 +26,94 @@ const CYCLES: [usize; 256] = [
     7, 11, 5, 10, 10, 4, 11, 11, 7, 11, 5, 5, 10, 4, 11, 17, 7, 11,
 ];
 
+const S_FLAG: u8 = 0x80;
+const Z_FLAG: u8 = 0x40;
+const H_FLAG: u8 = 0x10;
+const P_FLAG: u8 = 0x04;
+const C_FLAG: u8 = 0x01;
+
+/// Sign, zero and parity flags of every byte.
+const SZP: [u8; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let byte = i as u8;
+        table[i] = byte & S_FLAG;
+        if byte == 0 {
+            table[i] |= Z_FLAG;
+        }
+        if byte.count_ones().is_multiple_of(2) {
+            table[i] |= P_FLAG;
+        }
+        i += 1;
+    }
+    table
+};
+
+/// Carry out of bit 3, by bit 3 of the two operands and of the sum.
+const HALF_CARRY: [bool; 8] = [false, false, true, false, true, false, true, true];
+
+/// Cycles of the 8085 instructions, without the extra cycles of the taken
+/// conditional branches.
+const CYCLES_8085: [usize; 256] = [
+    4, 10, 7, 6, 4, 4, 7, 4, 10, 10, 7, 6, 4, 4, 7, 4, 7, 10, 7, 6, 4, 4, 7, 4, 10, 10, 7, 6, 4, 4,
+    7, 4, 4, 10, 16, 6, 4, 4, 7, 4, 10, 10, 16, 6, 4, 4, 7, 4, 4, 10, 13, 6, 10, 10, 10, 4, 10, 10,
+    13, 6, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4,
+    4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 7, 7, 7, 7, 7, 7, 5, 7, 4,
+    4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4,
+    4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4,
+    4, 4, 4, 4, 4, 7, 4, 6, 10, 7, 10, 9, 12, 7, 12, 6, 10, 7, 6, 9, 18, 7, 12, 6, 10, 7, 10, 9,
+    12, 7, 12, 6, 10, 7, 10, 9, 7, 7, 12, 6, 10, 7, 16, 9, 12, 7, 12, 6, 6, 7, 4, 9, 10, 7, 12, 6,
+    10, 7, 4, 9, 12, 7, 12, 6, 6, 7, 4, 9, 7, 7, 12,
+];
+
+/// Cpu emulated by `Emulator`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub enum Cpu {
+    #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "8080"))]
+    I8080,
+    #[cfg_attr(feature = "serde", serde(rename = "8085"))]
+    I8085,
+    #[cfg_attr(feature = "serde", serde(rename = "z80"))]
+    Z80,
+}
+
+/// Interrupt inputs of the 8085.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// Level triggered, calls 0x2c
+    Rst55,
+    /// Level triggered, calls 0x34
+    Rst65,
+    /// Edge triggered, calls 0x3c
+    Rst75,
+    /// Non maskable edge triggered, calls 0x24
+    Trap,
+}
+
+/// Snapshot of the cpu registers.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[cfg_attr(
+    feature = "python",
+    pyo3::pyclass(get_all, frozen, skip_from_py_object)
+)]
+pub struct Registers {
+    pub a: u8,
+    /// Flags, laid out as pushed by `PUSH PSW`
+    pub f: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub h: u8,
+    pub l: u8,
+    pub sp: u16,
+    pub pc: u16,
+}
+
 pub trait Memory {
     fn read_byte(&self, addr: u16) -> u8;
     fn read_word(&self, addr: u16) -> u16;
@@ -17,247 +121,472 @@ pub trait Memory {
     fn write_byte(&mut self, addr: u16, byte: u8);
     fn write_word(&mut self, addr: u16, word: u16);
 
-    fn in_port(&self, port: u8) -> u8;
-    fn out_port(&self, port: u8, byte: u8);
+    fn in_port(&mut self, port: u8) -> u8;
+    fn out_port(&mut self, port: u8, byte: u8);
+
+    /// Called after every step with the number of cycles it took.
+    fn tick(&mut self, _cycles: usize) {}
+
+    /// Called when the cpu can accept an interrupt, returns the address to call.
+    fn interrupt(&mut self) -> Option<u16> {
+        None
+    }
+
+    /// Called when the cpu accepts an interrupt from any source, with the
+    /// address it was given.
+    fn accepted(&mut self, _vector: u16) {}
 }
 
 pub struct Emulator {
     /// Memory
-    mem: Box<dyn Memory>,
+    mem: Mem,
     /// Halted
     halt: bool,
     /// Cycles count
     cycles: usize,
     /// Program counter
-    pc: Word,
+    pc: u16,
     /// Stack pointer
-    sp: Word,
-    /// Sign flag
-    s_flag: Flag,
-    /// Zero flag
-    z_flag: Flag,
-    /// Half-carry flag
-    h_flag: Flag,
-    /// Parity flag
-    p_flag: Flag,
-    /// Carry flag
-    c_flag: Flag,
-    a_reg: Register,
-    b_reg: Register,
-    c_reg: Register,
-    d_reg: Register,
-    e_reg: Register,
-    h_reg: Register,
-    l_reg: Register,
+    sp: u16,
+    /// Sign, zero, half-carry, parity and carry flags, laid out as in the PSW
+    flags: u8,
+    a_reg: u8,
+    b_reg: u8,
+    c_reg: u8,
+    d_reg: u8,
+    e_reg: u8,
+    h_reg: u8,
+    l_reg: u8,
     /// Interrupts information
     int: InterruptInfo,
+    cpu: Cpu,
+    /// Overflow flag, 8085 only
+    v_flag: bool,
+    /// Signed underflow flag, 8085 only
+    k_flag: bool,
+    /// 8085 interrupt inputs and serial lines
+    pins: Pins,
+    /// Add/subtract flag, Z80 only
+    n_flag: bool,
+    /// Z80 registers
+    z80: z80::State,
 }
 
-#[derive(Debug, Default)]
-struct Register(pub Byte);
-
-#[derive(Debug, Default)]
-struct Flag(pub bool);
-
 #[derive(Debug, Default)]
 struct InterruptInfo {
     pub pending: bool,
     pub filp_flop: bool,
-    pub vector: Byte,
-    pub delay: Byte,
+    pub vector: u16,
+    pub delay: u8,
+}
+
+#[derive(Debug, Default)]
+struct Pins {
+    /// RST 5.5, 6.5 and 7.5 masks, bits 0 to 2
+    masks: u8,
+    rst55: bool,
+    rst65: bool,
+    /// RST 7.5 input level
+    rst75: bool,
+    /// RST 7.5 flip flop, set by a rising edge
+    rst75_pending: bool,
+    trap: bool,
+    trap_pending: bool,
+    /// Interrupt enable before the last trap
+    trap_ie: Option<bool>,
+    /// Serial input data
+    sid: bool,
+    /// Serial output data
+    sod: bool,
+}
+
+/// Flat 64K of RAM, read and written by the cpu without going through the
+/// `Memory` trait, with the ports, ticks and interrupts going to `io`.
+pub struct FlatMemory {
+    ram: Box<[u8; 0x10000]>,
+    io: Box<dyn Memory>,
 }
 
-#[derive(Debug, Default, Clone, Copy)]
-struct Word(pub u16);
+impl FlatMemory {
+    /// RAM holding `image` at address 0.
+    pub fn new(image: &[u8], io: Box<dyn Memory>) -> Self {
+        let mut ram = Box::new([0; 0x10000]);
+        let len = image.len().min(ram.len());
+        ram[..len].copy_from_slice(&image[..len]);
+        Self { ram, io }
+    }
 
-#[derive(Debug, Default, Clone, Copy)]
-struct Byte(pub u8);
+    pub fn ram(&self) -> &[u8] {
+        &self.ram[..]
+    }
 
-impl Byte {
-    pub fn bit_parity(&self) -> bool {
-        let mut ones = 0;
+    pub fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram[..]
+    }
+}
 
-        for i in 0..8 {
-            ones += (self.0 >> i) & i;
-        }
+impl Memory for FlatMemory {
+    fn read_byte(&self, addr: u16) -> u8 {
+        self.ram[addr as usize]
+    }
+
+    fn read_word(&self, addr: u16) -> u16 {
+        u16::from_le_bytes([
+            self.ram[addr as usize],
+            self.ram[addr.wrapping_add(1) as usize],
+        ])
+    }
+
+    fn write_byte(&mut self, addr: u16, byte: u8) {
+        self.ram[addr as usize] = byte;
+    }
+
+    fn write_word(&mut self, addr: u16, word: u16) {
+        let [lo, hi] = word.to_le_bytes();
+        self.ram[addr as usize] = lo;
+        self.ram[addr.wrapping_add(1) as usize] = hi;
+    }
+
+    fn in_port(&mut self, port: u8) -> u8 {
+        self.io.in_port(port)
+    }
 
-        (ones & 1) == 0
+    fn out_port(&mut self, port: u8, byte: u8) {
+        self.io.out_port(port, byte);
     }
 
-    pub fn bit_carry(&self, other: Byte, alredy: bool, bits: i32) -> bool {
-        let res = (self.0 as u16) + (other.0 as u16) + (alredy as u16);
-        let carry = res ^ (self.0 as u16) ^ (other.0 as u16);
+    fn tick(&mut self, cycles: usize) {
+        self.io.tick(cycles);
+    }
+
+    fn interrupt(&mut self) -> Option<u16> {
+        self.io.interrupt()
+    }
 
-        carry & (1 << bits) != 0
+    fn accepted(&mut self, vector: u16) {
+        self.io.accepted(vector);
     }
 }
 
+/// Memory of the emulator, flat RAM is accessed directly.
+enum Mem {
+    Flat(FlatMemory),
+    Boxed(Box<dyn Memory>),
+}
+
 impl Emulator {
     pub fn new(mem: Box<dyn Memory>) -> Self {
+        Self::with_cpu(mem, Cpu::I8080)
+    }
+
+    pub fn with_cpu(mem: Box<dyn Memory>, cpu: Cpu) -> Self {
+        Self::with_mem(Mem::Boxed(mem), cpu)
+    }
+
+    /// Emulator running on flat RAM, the fastest memory.
+    pub fn with_flat(mem: FlatMemory, cpu: Cpu) -> Self {
+        Self::with_mem(Mem::Flat(mem), cpu)
+    }
+
+    fn with_mem(mem: Mem, cpu: Cpu) -> Self {
         Self {
             mem,
             halt: false,
             cycles: 0,
-            pc: Word::default(),
-            sp: Word::default(),
-            s_flag: Flag::default(),
-            z_flag: Flag::default(),
-            h_flag: Flag::default(),
-            p_flag: Flag::default(),
-            c_flag: Flag::default(),
-            a_reg: Register::default(),
-            b_reg: Register::default(),
-            c_reg: Register::default(),
-            d_reg: Register::default(),
-            e_reg: Register::default(),
-            h_reg: Register::default(),
-            l_reg: Register::default(),
+            pc: 0,
+            sp: 0,
+            flags: 0,
+            a_reg: 0,
+            b_reg: 0,
+            c_reg: 0,
+            d_reg: 0,
+            e_reg: 0,
+            h_reg: 0,
+            l_reg: 0,
             int: InterruptInfo::default(),
+            cpu,
+            v_flag: false,
+            k_flag: false,
+            pins: Pins::default(),
+            n_flag: false,
+            z80: z80::State::default(),
+        }
+    }
+
+    #[inline]
+    fn flag(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+
+    #[inline]
+    fn set_flag(&mut self, flag: u8, value: bool) {
+        if value {
+            self.flags |= flag;
+        } else {
+            self.flags &= !flag;
+        }
+    }
+
+    fn set_flags(&mut self, value: u8) {
+        self.flags = self.flags & !(S_FLAG | Z_FLAG | P_FLAG) | SZP[value as usize];
+    }
+
+    #[inline]
+    fn read_byte(&self, addr: u16) -> u8 {
+        match &self.mem {
+            Mem::Flat(mem) => mem.ram[addr as usize],
+            Mem::Boxed(mem) => mem.read_byte(addr),
         }
     }
 
-    fn set_flags(&mut self, value: Byte) {
-        self.z_flag.0 = value.0 == 0u8;
-        self.s_flag.0 = (value.0 >> 7) == 1;
-        self.p_flag.0 = value.bit_parity();
+    #[inline]
+    fn read_word(&self, addr: u16) -> u16 {
+        match &self.mem {
+            Mem::Flat(mem) => mem.read_word(addr),
+            Mem::Boxed(mem) => mem.read_word(addr),
+        }
+    }
+
+    #[inline]
+    fn write_byte(&mut self, addr: u16, byte: u8) {
+        match &mut self.mem {
+            Mem::Flat(mem) => mem.ram[addr as usize] = byte,
+            Mem::Boxed(mem) => mem.write_byte(addr, byte),
+        }
+    }
+
+    #[inline]
+    fn write_word(&mut self, addr: u16, word: u16) {
+        match &mut self.mem {
+            Mem::Flat(mem) => mem.write_word(addr, word),
+            Mem::Boxed(mem) => mem.write_word(addr, word),
+        }
+    }
+
+    /// Ports, ticks and interrupts.
+    fn io(&mut self) -> &mut dyn Memory {
+        match &mut self.mem {
+            Mem::Flat(mem) => mem.io.as_mut(),
+            Mem::Boxed(mem) => mem.as_mut(),
+        }
     }
 
     fn fetch_next_byte(&mut self) -> u8 {
-        let byte = self.mem.read_byte(self.pc.0);
-        self.pc.0 += 1;
+        let byte = self.read_byte(self.pc);
+        self.pc = self.pc.wrapping_add(1);
         byte
     }
 
     fn fetch_next_word(&mut self) -> u16 {
-        let word = self.mem.read_word(self.pc.0);
-        self.pc.0 += 2;
+        let word = self.read_word(self.pc);
+        self.pc = self.pc.wrapping_add(2);
         word
     }
 
     fn set_bc_pair(&mut self, value: u16) {
-        self.b_reg.0 = Byte((value >> 8) as u8);
-        self.c_reg.0 = Byte((value & 0xff) as u8);
+        self.b_reg = (value >> 8) as u8;
+        self.c_reg = (value & 0xff) as u8;
     }
 
     fn get_bc_pair(&self) -> u16 {
-        (self.b_reg.0 .0 as u16) << 8 | self.c_reg.0 .0 as u16
+        (self.b_reg as u16) << 8 | self.c_reg as u16
     }
 
     fn set_de_pair(&mut self, value: u16) {
-        self.d_reg.0 = Byte((value >> 8) as u8);
-        self.e_reg.0 = Byte((value & 0xff) as u8);
+        self.d_reg = (value >> 8) as u8;
+        self.e_reg = (value & 0xff) as u8;
     }
 
     fn get_de_pair(&self) -> u16 {
-        (self.d_reg.0 .0 as u16) << 8 | self.e_reg.0 .0 as u16
+        (self.d_reg as u16) << 8 | self.e_reg as u16
     }
 
     fn set_hl_pair(&mut self, value: u16) {
-        self.l_reg.0 = Byte((value >> 8) as u8);
-        self.h_reg.0 = Byte((value & 0xff) as u8);
+        self.h_reg = (value >> 8) as u8;
+        self.l_reg = (value & 0xff) as u8;
     }
 
     fn get_hl_pair(&self) -> u16 {
-        (self.h_reg.0 .0 as u16) << 8 | self.l_reg.0 .0 as u16
+        (self.h_reg as u16) << 8 | self.l_reg as u16
     }
 
     fn push_stack(&mut self, value: u16) {
-        self.sp.0 -= 2;
-        self.mem.write_word(self.sp.0, value);
+        self.sp = self.sp.wrapping_sub(2);
+        self.write_word(self.sp, value);
     }
 
     fn pop_stack(&mut self) -> u16 {
-        let value = self.mem.read_word(self.sp.0);
-        self.sp.0 += 2;
+        let value = self.read_word(self.sp);
+        self.sp = self.sp.wrapping_add(2);
         value
     }
 
-    fn op_inr(&mut self, value: Byte) -> Byte {
-        let res = Byte(value.0 + 1);
-        self.h_flag.0 = (res.0 & 0x0f) == 0;
+    /// Accumulator and flags, as pushed by `PUSH PSW`.
+    fn get_psw(&self) -> u16 {
+        let mut psw = self.flags;
+
+        match self.cpu {
+            Cpu::I8080 => psw |= 1 << 1,
+            Cpu::I8085 => {
+                psw |= (self.k_flag as u8) << 5;
+                psw |= (self.v_flag as u8) << 1;
+            }
+            Cpu::Z80 => psw |= (self.n_flag as u8) << 1,
+        }
+
+        (self.a_reg as u16) << 8 | (psw as u16)
+    }
+
+    fn set_psw(&mut self, value: u16) {
+        self.a_reg = (value >> 8) as u8;
+
+        let psw = (value & 0xff) as u8;
+        self.flags = psw & (S_FLAG | Z_FLAG | H_FLAG | P_FLAG | C_FLAG);
+
+        match self.cpu {
+            Cpu::I8080 => {}
+            Cpu::I8085 => {
+                self.k_flag = ((psw >> 5) & 1) != 0;
+                self.v_flag = ((psw >> 1) & 1) != 0;
+            }
+            Cpu::Z80 => self.n_flag = ((psw >> 1) & 1) != 0,
+        }
+    }
+
+    fn op_inr(&mut self, value: u8) -> u8 {
+        let res = value.wrapping_add(1);
+        self.set_flag(H_FLAG, (res & 0x0f) == 0);
         self.set_flags(res);
+
+        if self.cpu == Cpu::Z80 {
+            self.set_flag(P_FLAG, res == 0x80);
+            self.n_flag = false;
+        }
         res
     }
 
-    fn op_dcr(&mut self, value: Byte) -> Byte {
-        let res = Byte(value.0 - 1);
-        self.h_flag.0 = (res.0 & 0x0f) != 0x0f;
+    fn op_dcr(&mut self, value: u8) -> u8 {
+        let res = value.wrapping_sub(1);
+        self.set_flag(H_FLAG, (res & 0x0f) != 0x0f);
         self.set_flags(res);
+
+        if self.cpu == Cpu::Z80 {
+            self.set_flag(H_FLAG, !self.flag(H_FLAG));
+            self.set_flag(P_FLAG, res == 0x7f);
+            self.n_flag = true;
+        }
+        res
+    }
+
+    fn op_inx(&mut self, value: u16) -> u16 {
+        let res = value.wrapping_add(1);
+        self.k_flag = res == 0;
+        res
+    }
+
+    fn op_dcx(&mut self, value: u16) -> u16 {
+        let res = value.wrapping_sub(1);
+        self.k_flag = res == 0xffff;
         res
     }
 
     fn op_dad(&mut self, value: u16) {
-        self.c_flag.0 = ((((self.get_hl_pair() + value) as u32) >> 16) & 1) != 0;
-        self.set_hl_pair(self.get_hl_pair() + value);
+        let hl = self.get_hl_pair();
+        let res = hl as u32 + value as u32;
+        self.set_flag(C_FLAG, (res >> 16) != 0);
+        self.set_hl_pair(res as u16);
+
+        if self.cpu == Cpu::Z80 {
+            self.set_flag(H_FLAG, (hl & 0x0fff) + (value & 0x0fff) > 0x0fff);
+            self.n_flag = false;
+        }
     }
 
-    fn op_add(&mut self, reg: Byte, value: Byte, alredy: bool) -> Byte {
-        let res = Byte(reg.0 + value.0 + (alredy as u8));
-        let byte = Byte(reg.0);
-        self.c_flag.0 = byte.bit_carry(value, alredy, 8);
-        self.h_flag.0 = byte.bit_carry(value, alredy, 4);
+    fn op_add(&mut self, reg: u8, value: u8, alredy: bool) -> u8 {
+        let sum = reg as u16 + value as u16 + alredy as u16;
+        let res = sum as u8;
+        let index = (reg & 0x08) >> 1 | (value & 0x08) >> 2 | (res & 0x08) >> 3;
+        self.set_flag(C_FLAG, sum > 0xff);
+        self.set_flag(H_FLAG, HALF_CARRY[index as usize]);
+
+        let (a, b, r) = (reg >> 7, value >> 7, res >> 7);
+        self.v_flag = a == b && a != r;
+        self.k_flag = (a & b | a & !r | b & !r) & 1 != 0;
 
         self.set_flags(res);
+
+        if self.cpu == Cpu::Z80 {
+            self.set_flag(P_FLAG, self.v_flag);
+            self.n_flag = false;
+        }
         res
     }
 
-    fn op_sub(&mut self, reg: Byte, value: Byte, alredy: bool) -> Byte {
-        let res = self.op_add(reg, Byte(!value.0), !alredy);
-        self.c_flag.0 = !self.c_flag.0;
+    fn op_sub(&mut self, reg: u8, value: u8, alredy: bool) -> u8 {
+        let res = self.op_add(reg, !value, !alredy);
+        self.set_flag(C_FLAG, !self.flag(C_FLAG));
+
+        if self.cpu == Cpu::Z80 {
+            self.set_flag(H_FLAG, !self.flag(H_FLAG));
+            self.n_flag = true;
+        }
         res
     }
 
     fn op_daa(&mut self) {
-        let mut alredy = self.c_flag.0;
+        let mut alredy = self.flag(C_FLAG);
         let mut adj = 0;
-        let lsb = self.a_reg.0 .0 & 0x0f;
-        let msb = self.a_reg.0 .0 >> 4;
+        let lsb = self.a_reg & 0x0f;
+        let msb = self.a_reg >> 4;
 
-        if self.h_flag.0 || lsb > 9 {
+        if self.flag(H_FLAG) || lsb > 9 {
             adj += 0x06;
         }
 
-        if self.c_flag.0 || msb > 9 || (msb >= 9 && lsb > 9) {
+        if self.flag(C_FLAG) || msb > 9 || (msb >= 9 && lsb > 9) {
             adj += 0x60;
             alredy = true;
         }
 
-        self.a_reg.0 = self.op_add(self.a_reg.0, Byte(adj), alredy);
-        self.c_flag.0 = alredy;
+        self.a_reg = self.op_add(self.a_reg, adj, alredy);
+        self.set_flag(C_FLAG, alredy);
     }
 
     fn op_ana(&mut self, value: u8) {
-        let res = Byte(self.a_reg.0 .0 & value);
-        self.c_flag.0 = false;
-        self.h_flag.0 = ((self.a_reg.0 .0 | value) & 0x08) != 0;
+        let res = self.a_reg & value;
+        self.set_flag(C_FLAG, false);
+        self.set_flag(
+            H_FLAG,
+            self.cpu != Cpu::I8080 || ((self.a_reg | value) & 0x08) != 0,
+        );
+        self.n_flag = false;
 
         self.set_flags(res);
-        self.a_reg.0 = res;
+        self.a_reg = res;
     }
 
     fn op_xra(&mut self, value: u8) {
-        self.a_reg.0 .0 ^= value;
-        self.c_flag.0 = false;
-        self.h_flag.0 = false;
-        self.set_flags(self.a_reg.0);
+        self.a_reg ^= value;
+        self.set_flag(C_FLAG, false);
+        self.set_flag(H_FLAG, false);
+        self.n_flag = false;
+        self.set_flags(self.a_reg);
     }
 
     fn op_ora(&mut self, value: u8) {
-        self.a_reg.0 .0 |= value;
-        self.c_flag.0 = false;
-        self.h_flag.0 = false;
-        self.set_flags(self.a_reg.0);
+        self.a_reg |= value;
+        self.set_flag(C_FLAG, false);
+        self.set_flag(H_FLAG, false);
+        self.n_flag = false;
+        self.set_flags(self.a_reg);
     }
 
     fn op_cmp(&mut self, value: u8) {
-        let res = self.a_reg.0 .0 - value;
-        self.c_flag.0 = ((res as u16) >> 8) != 0;
-        self.h_flag.0 = (!(self.a_reg.0 .0 ^ res ^ value) & 0x10) != 0;
-        self.set_flags(Byte(res & 0xff));
+        self.op_sub(self.a_reg, value, false);
     }
 
     fn op_jmp(&mut self, addr: u16) {
-        self.pc.0 = addr;
+        self.pc = addr;
     }
 
     fn op_cond_jmp(&mut self, cond: bool) {
@@ -265,11 +594,14 @@ impl Emulator {
 
         if cond {
             self.op_jmp(addr);
+            if self.cpu == Cpu::I8085 {
+                self.cycles += 3;
+            }
         }
     }
 
     fn op_call(&mut self, addr: u16) {
-        self.push_stack(self.pc.0);
+        self.push_stack(self.pc);
         self.op_jmp(addr);
     }
 
@@ -278,12 +610,16 @@ impl Emulator {
 
         if cond {
             self.op_call(addr);
-            self.cycles += 6;
+            self.cycles += match self.cpu {
+                Cpu::I8080 => 6,
+                Cpu::I8085 => 9,
+                Cpu::Z80 => 7,
+            };
         }
     }
 
     fn op_ret(&mut self) {
-        self.pc.0 = self.pop_stack();
+        self.pc = self.pop_stack();
     }
 
     fn op_cond_ret(&mut self, cond: bool) {
@@ -293,13 +629,117 @@ impl Emulator {
         }
     }
 
+    /// Runs the 8085 instructions that differ from the 8080, returns false
+    /// for the common ones.
+    fn exec_8085(&mut self, op: u8) -> bool {
+        match op {
+            // DSUB
+            0x08 => {
+                let (hl, bc) = (self.get_hl_pair(), self.get_bc_pair());
+                let l = self.op_sub(self.l_reg, self.c_reg, false);
+                let h = self.op_sub(self.h_reg, self.b_reg, self.flag(C_FLAG));
+                self.l_reg = l;
+                self.h_reg = h;
+                self.set_flag(Z_FLAG, hl == bc);
+            }
+            // ARHL
+            0x10 => {
+                let hl = self.get_hl_pair();
+                self.set_flag(C_FLAG, hl & 1 != 0);
+                self.set_hl_pair((hl as i16 >> 1) as u16);
+            }
+            // RDEL
+            0x18 => {
+                let de = self.get_de_pair();
+                let res = de << 1 | self.flag(C_FLAG) as u16;
+                self.set_flag(C_FLAG, de & 0x8000 != 0);
+                self.v_flag = (de ^ res) & 0x8000 != 0;
+                self.set_de_pair(res);
+            }
+            // RIM
+            0x20 => {
+                let ie = self.pins.trap_ie.take().unwrap_or(self.int.filp_flop);
+                let pins = &self.pins;
+                self.a_reg = (pins.sid as u8) << 7
+                    | (pins.rst75_pending as u8) << 6
+                    | (pins.rst65 as u8) << 5
+                    | (pins.rst55 as u8) << 4
+                    | (ie as u8) << 3
+                    | pins.masks;
+            }
+            // LDHI
+            0x28 => {
+                let value = self.fetch_next_byte() as u16;
+                self.set_de_pair(self.get_hl_pair().wrapping_add(value));
+            }
+            // SIM
+            0x30 => {
+                let a = self.a_reg;
+                if a & 0x40 != 0 {
+                    self.pins.sod = a & 0x80 != 0;
+                }
+                if a & 0x10 != 0 {
+                    self.pins.rst75_pending = false;
+                }
+                if a & 0x08 != 0 {
+                    self.pins.masks = a & 0x07;
+                }
+            }
+            // LDSI
+            0x38 => {
+                let value = self.fetch_next_byte() as u16;
+                self.set_de_pair(self.sp.wrapping_add(value));
+            }
+            // RSTV
+            0xcb => {
+                if self.v_flag {
+                    self.op_call(0x40);
+                    self.cycles += 6;
+                }
+            }
+            // SHLX
+            0xd9 => {
+                let addr = self.get_de_pair();
+                self.write_word(addr, self.get_hl_pair());
+            }
+            // JNK
+            0xdd => self.op_cond_jmp(!self.k_flag),
+            // LHLX
+            0xed => {
+                let addr = self.get_de_pair();
+                let value = self.read_word(addr);
+                self.set_hl_pair(value);
+            }
+            // JK
+            0xfd => self.op_cond_jmp(self.k_flag),
+            _ => return false,
+        }
+        true
+    }
+
     pub fn exec(&mut self, op: u8) {
-        self.cycles += CYCLES[op as usize];
+        self.cycles += match self.cpu {
+            Cpu::I8080 => CYCLES[op as usize],
+            Cpu::I8085 => CYCLES_8085[op as usize],
+            Cpu::Z80 => z80::CYCLES[op as usize],
+        };
 
-        if self.int.delay.0 > 0 {
-            self.int.delay.0 -= 1;
+        if self.int.delay > 0 {
+            self.int.delay -= 1;
         }
 
+        let done = match self.cpu {
+            Cpu::I8080 => false,
+            Cpu::I8085 => self.exec_8085(op),
+            Cpu::Z80 => self.exec_z80(op),
+        };
+        if !done {
+            self.exec_8080(op);
+        }
+    }
+
+    /// Runs the instructions common to every cpu, without counting cycles.
+    fn exec_8080(&mut self, op: u8) {
         match op {
             0x00 => {}
             0x01 => {
@@ -308,47 +748,49 @@ impl Emulator {
             }
             0x02 => {
                 let value = self.get_bc_pair();
-                self.mem.write_byte(value, self.a_reg.0 .0);
+                self.write_byte(value, self.a_reg);
             }
             0x03 => {
                 let value = self.get_bc_pair();
-                self.set_bc_pair(value + 1);
+                let res = self.op_inx(value);
+                self.set_bc_pair(res);
             }
             0x04 => {
-                self.b_reg.0 = self.op_inr(self.b_reg.0);
+                self.b_reg = self.op_inr(self.b_reg);
             }
             0x05 => {
-                self.b_reg.0 = self.op_dcr(self.b_reg.0);
+                self.b_reg = self.op_dcr(self.b_reg);
             }
             0x06 => {
-                self.b_reg.0 = Byte(self.fetch_next_byte());
+                self.b_reg = self.fetch_next_byte();
             }
             0x07 => {
-                self.c_flag.0 = (self.a_reg.0 .0 >> 7) != 0;
-                self.a_reg.0 .0 = (self.a_reg.0 .0 << 1) | self.c_flag.0 as u8;
+                self.set_flag(C_FLAG, (self.a_reg >> 7) != 0);
+                self.a_reg = (self.a_reg << 1) | self.flag(C_FLAG) as u8;
             }
             0x09 => {
                 self.op_dad(self.get_bc_pair());
             }
             0x0a => {
-                self.a_reg.0 .0 = self.mem.read_byte(self.get_bc_pair());
+                self.a_reg = self.read_byte(self.get_bc_pair());
             }
             0x0b => {
                 let value = self.get_bc_pair();
-                self.set_bc_pair(value - 1);
+                let res = self.op_dcx(value);
+                self.set_bc_pair(res);
             }
             0x0c => {
-                self.c_reg.0 = self.op_inr(self.c_reg.0);
+                self.c_reg = self.op_inr(self.c_reg);
             }
             0x0d => {
-                self.c_reg.0 = self.op_dcr(self.c_reg.0);
+                self.c_reg = self.op_dcr(self.c_reg);
             }
             0x0e => {
-                self.c_reg.0 = Byte(self.fetch_next_byte());
+                self.c_reg = self.fetch_next_byte();
             }
             0x0f => {
-                self.c_flag.0 = (self.a_reg.0 .0 & 1) != 0;
-                self.a_reg.0 .0 = (self.a_reg.0 .0 >> 1) | ((self.c_flag.0 as u8) << 7)
+                self.set_flag(C_FLAG, (self.a_reg & 1) != 0);
+                self.a_reg = (self.a_reg >> 1) | ((self.flag(C_FLAG) as u8) << 7)
             }
             0x11 => {
                 let word = self.fetch_next_word();
@@ -356,49 +798,51 @@ impl Emulator {
             }
             0x12 => {
                 let addr = self.get_de_pair();
-                self.mem.write_byte(addr, self.a_reg.0 .0);
+                self.write_byte(addr, self.a_reg);
             }
             0x13 => {
                 let value = self.get_de_pair();
-                self.set_de_pair(value + 1);
+                let res = self.op_inx(value);
+                self.set_de_pair(res);
             }
             0x14 => {
-                self.d_reg.0 = self.op_inr(self.d_reg.0);
+                self.d_reg = self.op_inr(self.d_reg);
             }
             0x15 => {
-                self.d_reg.0 = self.op_dcr(self.d_reg.0);
+                self.d_reg = self.op_dcr(self.d_reg);
             }
             0x16 => {
-                self.d_reg.0 = Byte(self.fetch_next_byte());
+                self.d_reg = self.fetch_next_byte();
             }
             0x17 => {
-                let alredy = self.c_flag.0;
-                self.c_flag.0 = (self.a_reg.0 .0 >> 7) != 0;
-                self.a_reg.0 .0 = (self.a_reg.0 .0 << 1) | (alredy as u8);
+                let alredy = self.flag(C_FLAG);
+                self.set_flag(C_FLAG, (self.a_reg >> 7) != 0);
+                self.a_reg = (self.a_reg << 1) | (alredy as u8);
             }
             0x19 => {
                 self.op_dad(self.get_de_pair());
             }
             0x1a => {
-                self.a_reg.0 .0 = self.mem.read_byte(self.get_de_pair());
+                self.a_reg = self.read_byte(self.get_de_pair());
             }
             0x1b => {
                 let value = self.get_de_pair();
-                self.set_de_pair(value - 1);
+                let res = self.op_dcx(value);
+                self.set_de_pair(res);
             }
             0x1c => {
-                self.e_reg.0 = self.op_inr(self.e_reg.0);
+                self.e_reg = self.op_inr(self.e_reg);
             }
             0x1d => {
-                self.e_reg.0 = self.op_dcr(self.e_reg.0);
+                self.e_reg = self.op_dcr(self.e_reg);
             }
             0x1e => {
-                self.e_reg.0 = Byte(self.fetch_next_byte());
+                self.e_reg = self.fetch_next_byte();
             }
             0x1f => {
-                let alredy = self.c_flag.0;
-                self.c_flag.0 = (self.a_reg.0 .0 & 1) != 0;
-                self.a_reg.0 .0 = (self.a_reg.0 .0 >> 1) | ((alredy as u8) << 7);
+                let alredy = self.flag(C_FLAG);
+                self.set_flag(C_FLAG, (self.a_reg & 1) != 0);
+                self.a_reg = (self.a_reg >> 1) | ((alredy as u8) << 7);
             }
             0x21 => {
                 let word = self.fetch_next_word();
@@ -406,20 +850,21 @@ impl Emulator {
             }
             0x22 => {
                 let addr = self.fetch_next_word();
-                self.mem.write_word(addr, self.get_hl_pair());
+                self.write_word(addr, self.get_hl_pair());
             }
             0x23 => {
                 let value = self.get_hl_pair();
-                self.set_hl_pair(value + 1);
+                let res = self.op_inx(value);
+                self.set_hl_pair(res);
             }
             0x24 => {
-                self.h_reg.0 = self.op_inr(self.h_reg.0);
+                self.h_reg = self.op_inr(self.h_reg);
             }
             0x25 => {
-                self.h_reg.0 = self.op_dcr(self.h_reg.0);
+                self.h_reg = self.op_dcr(self.h_reg);
             }
             0x26 => {
-                self.h_reg.0 = Byte(self.fetch_next_byte());
+                self.h_reg = self.fetch_next_byte();
             }
             0x27 => {
                 self.op_daa();
@@ -429,504 +874,491 @@ impl Emulator {
             }
             0x2a => {
                 let addr = self.fetch_next_word();
-                self.set_hl_pair(self.mem.read_word(addr))
+                self.set_hl_pair(self.read_word(addr))
             }
             0x2b => {
                 let value = self.get_hl_pair();
-                self.set_hl_pair(value - 1);
+                let res = self.op_dcx(value);
+                self.set_hl_pair(res);
             }
             0x2c => {
-                self.l_reg.0 = self.op_inr(self.l_reg.0);
+                self.l_reg = self.op_inr(self.l_reg);
             }
             0x2d => {
-                self.l_reg.0 = self.op_dcr(self.l_reg.0);
+                self.l_reg = self.op_dcr(self.l_reg);
             }
             0x2e => {
-                self.l_reg.0 = Byte(self.fetch_next_byte());
+                self.l_reg = self.fetch_next_byte();
             }
             0x2f => {
-                self.a_reg.0 .0 = !self.a_reg.0 .0;
+                self.a_reg = !self.a_reg;
             }
             0x31 => {
                 let word = self.fetch_next_word();
-                self.sp = Word(word);
+                self.sp = word;
             }
             0x32 => {
                 let addr = self.fetch_next_word();
-                self.mem.write_byte(addr, self.a_reg.0 .0);
+                self.write_byte(addr, self.a_reg);
             }
             0x33 => {
-                self.sp.0 += 1;
+                self.sp = self.op_inx(self.sp);
             }
             0x34 => {
-                let byte = self.mem.read_byte(self.get_hl_pair());
-                let value = self.op_inr(Byte(byte));
-                self.mem.write_byte(self.get_hl_pair(), value.0);
+                let byte = self.read_byte(self.get_hl_pair());
+                let value = self.op_inr(byte);
+                self.write_byte(self.get_hl_pair(), value);
             }
             0x35 => {
-                let byte = self.mem.read_byte(self.get_hl_pair());
-                let value = self.op_dcr(Byte(byte));
-                self.mem.write_byte(self.get_hl_pair(), value.0);
+                let byte = self.read_byte(self.get_hl_pair());
+                let value = self.op_dcr(byte);
+                self.write_byte(self.get_hl_pair(), value);
             }
             0x36 => {
                 let value = self.fetch_next_byte();
-                self.mem.write_byte(self.get_hl_pair(), value);
+                self.write_byte(self.get_hl_pair(), value);
             }
             0x37 => {
-                self.c_flag.0 = true;
+                self.set_flag(C_FLAG, true);
             }
             0x39 => {
-                self.op_dad(self.sp.0);
+                self.op_dad(self.sp);
             }
             0x3a => {
                 let addr = self.fetch_next_word();
-                self.a_reg.0 .0 = self.mem.read_byte(addr);
+                self.a_reg = self.read_byte(addr);
             }
             0x3b => {
-                self.sp.0 -= 1;
+                self.sp = self.op_dcx(self.sp);
             }
             0x3c => {
-                self.a_reg.0 = self.op_inr(self.a_reg.0);
+                self.a_reg = self.op_inr(self.a_reg);
             }
             0x3d => {
-                self.a_reg.0 = self.op_dcr(self.a_reg.0);
+                self.a_reg = self.op_dcr(self.a_reg);
             }
             0x3e => {
-                self.a_reg.0 = Byte(self.fetch_next_byte());
+                self.a_reg = self.fetch_next_byte();
             }
             0x3f => {
-                self.c_flag.0 = !self.c_flag.0;
-            }
-            0x40 => {
-                self.b_reg.0 .0 = self.mem.read_byte(self.get_hl_pair());
+                self.set_flag(C_FLAG, !self.flag(C_FLAG));
             }
+            0x40 => {}
             0x41 => {
-                self.b_reg.0 = self.c_reg.0;
+                self.b_reg = self.c_reg;
             }
             0x42 => {
-                self.b_reg.0 = self.d_reg.0;
+                self.b_reg = self.d_reg;
             }
             0x43 => {
-                self.b_reg.0 = self.e_reg.0;
+                self.b_reg = self.e_reg;
             }
             0x44 => {
-                self.b_reg.0 = self.h_reg.0;
+                self.b_reg = self.h_reg;
             }
             0x45 => {
-                self.b_reg.0 = self.l_reg.0;
+                self.b_reg = self.l_reg;
             }
             0x46 => {
-                self.b_reg.0 .0 = self.mem.read_byte(self.get_hl_pair());
+                self.b_reg = self.read_byte(self.get_hl_pair());
             }
             0x47 => {
-                self.b_reg.0 = self.a_reg.0;
+                self.b_reg = self.a_reg;
             }
             0x48 => {
-                self.c_reg.0 = self.b_reg.0;
-            }
-            0x49 => {
-                self.c_reg.0 = self.c_reg.0;
+                self.c_reg = self.b_reg;
             }
+            0x49 => {}
             0x4a => {
-                self.c_reg.0 = self.d_reg.0;
+                self.c_reg = self.d_reg;
             }
             0x4b => {
-                self.c_reg.0 = self.e_reg.0;
+                self.c_reg = self.e_reg;
             }
             0x4c => {
-                self.c_reg.0 = self.h_reg.0;
+                self.c_reg = self.h_reg;
             }
             0x4d => {
-                self.c_reg.0 = self.l_reg.0;
+                self.c_reg = self.l_reg;
             }
             0x4e => {
-                self.c_reg.0 .0 = self.mem.read_byte(self.get_hl_pair());
+                self.c_reg = self.read_byte(self.get_hl_pair());
             }
             0x4f => {
-                self.c_reg.0 = self.a_reg.0;
+                self.c_reg = self.a_reg;
             }
             0x50 => {
-                self.d_reg.0 = self.b_reg.0;
+                self.d_reg = self.b_reg;
             }
             0x51 => {
-                self.d_reg.0 = self.c_reg.0;
-            }
-            0x52 => {
-                self.d_reg.0 = self.d_reg.0;
+                self.d_reg = self.c_reg;
             }
+            0x52 => {}
             0x53 => {
-                self.d_reg.0 = self.e_reg.0;
+                self.d_reg = self.e_reg;
             }
             0x54 => {
-                self.d_reg.0 = self.h_reg.0;
+                self.d_reg = self.h_reg;
             }
             0x55 => {
-                self.d_reg.0 = self.l_reg.0;
+                self.d_reg = self.l_reg;
             }
             0x56 => {
-                self.d_reg.0 .0 = self.mem.read_byte(self.get_hl_pair());
+                self.d_reg = self.read_byte(self.get_hl_pair());
             }
             0x57 => {
-                self.d_reg.0 = self.a_reg.0;
+                self.d_reg = self.a_reg;
             }
             0x58 => {
-                self.e_reg.0 = self.b_reg.0;
+                self.e_reg = self.b_reg;
             }
             0x59 => {
-                self.e_reg.0 = self.c_reg.0;
+                self.e_reg = self.c_reg;
             }
             0x5a => {
-                self.e_reg.0 = self.d_reg.0;
-            }
-            0x5b => {
-                self.e_reg.0 = self.e_reg.0;
+                self.e_reg = self.d_reg;
             }
+            0x5b => {}
             0x5c => {
-                self.e_reg.0 = self.h_reg.0;
+                self.e_reg = self.h_reg;
             }
             0x5d => {
-                self.e_reg.0 = self.l_reg.0;
+                self.e_reg = self.l_reg;
             }
             0x5e => {
-                self.e_reg.0 .0 = self.mem.read_byte(self.get_hl_pair());
+                self.e_reg = self.read_byte(self.get_hl_pair());
             }
             0x5f => {
-                self.e_reg.0 = self.a_reg.0;
+                self.e_reg = self.a_reg;
             }
             0x60 => {
-                self.h_reg.0 = self.b_reg.0;
+                self.h_reg = self.b_reg;
             }
             0x61 => {
-                self.h_reg.0 = self.c_reg.0;
+                self.h_reg = self.c_reg;
             }
             0x62 => {
-                self.h_reg.0 = self.d_reg.0;
+                self.h_reg = self.d_reg;
             }
             0x63 => {
-                self.h_reg.0 = self.e_reg.0;
-            }
-            0x64 => {
-                self.h_reg.0 = self.h_reg.0;
+                self.h_reg = self.e_reg;
             }
+            0x64 => {}
             0x65 => {
-                self.h_reg.0 = self.l_reg.0;
+                self.h_reg = self.l_reg;
             }
             0x66 => {
-                self.h_reg.0 .0 = self.mem.read_byte(self.get_hl_pair());
+                self.h_reg = self.read_byte(self.get_hl_pair());
             }
             0x67 => {
-                self.h_reg.0 = self.a_reg.0;
+                self.h_reg = self.a_reg;
             }
             0x68 => {
-                self.l_reg.0 = self.b_reg.0;
+                self.l_reg = self.b_reg;
             }
             0x69 => {
-                self.l_reg.0 = self.c_reg.0;
+                self.l_reg = self.c_reg;
             }
             0x6a => {
-                self.l_reg.0 = self.d_reg.0;
+                self.l_reg = self.d_reg;
             }
             0x6b => {
-                self.l_reg.0 = self.e_reg.0;
+                self.l_reg = self.e_reg;
             }
             0x6c => {
-                self.l_reg.0 = self.h_reg.0;
-            }
-            0x6d => {
-                self.l_reg.0 = self.l_reg.0;
+                self.l_reg = self.h_reg;
             }
+            0x6d => {}
             0x6e => {
-                self.l_reg.0 .0 = self.mem.read_byte(self.get_hl_pair());
+                self.l_reg = self.read_byte(self.get_hl_pair());
             }
             0x6f => {
-                self.l_reg.0 = self.a_reg.0;
+                self.l_reg = self.a_reg;
             }
             0x70 => {
-                self.mem.write_byte(self.get_hl_pair(), self.b_reg.0 .0);
+                self.write_byte(self.get_hl_pair(), self.b_reg);
             }
             0x71 => {
-                self.mem.write_byte(self.get_hl_pair(), self.c_reg.0 .0);
+                self.write_byte(self.get_hl_pair(), self.c_reg);
             }
             0x72 => {
-                self.mem.write_byte(self.get_hl_pair(), self.d_reg.0 .0);
+                self.write_byte(self.get_hl_pair(), self.d_reg);
             }
             0x73 => {
-                self.mem.write_byte(self.get_hl_pair(), self.e_reg.0 .0);
+                self.write_byte(self.get_hl_pair(), self.e_reg);
             }
             0x74 => {
-                self.mem.write_byte(self.get_hl_pair(), self.h_reg.0 .0);
+                self.write_byte(self.get_hl_pair(), self.h_reg);
             }
             0x75 => {
-                self.mem.write_byte(self.get_hl_pair(), self.l_reg.0 .0);
+                self.write_byte(self.get_hl_pair(), self.l_reg);
             }
             0x76 => {
                 self.halt = true;
             }
             0x77 => {
-                self.mem.write_byte(self.get_hl_pair(), self.a_reg.0 .0);
+                self.write_byte(self.get_hl_pair(), self.a_reg);
             }
             0x78 => {
-                self.a_reg.0 = self.b_reg.0;
+                self.a_reg = self.b_reg;
             }
             0x79 => {
-                self.a_reg.0 = self.c_reg.0;
+                self.a_reg = self.c_reg;
             }
             0x7a => {
-                self.a_reg.0 = self.d_reg.0;
+                self.a_reg = self.d_reg;
             }
             0x7b => {
-                self.a_reg.0 = self.e_reg.0;
+                self.a_reg = self.e_reg;
             }
             0x7c => {
-                self.a_reg.0 = self.h_reg.0;
+                self.a_reg = self.h_reg;
             }
             0x7d => {
-                self.a_reg.0 = self.l_reg.0;
+                self.a_reg = self.l_reg;
             }
             0x7e => {
-                self.a_reg.0 .0 = self.mem.read_byte(self.get_hl_pair());
-            }
-            0x7f => {
-                self.a_reg.0 = self.a_reg.0;
+                self.a_reg = self.read_byte(self.get_hl_pair());
             }
+            0x7f => {}
             0x80 => {
-                self.a_reg.0 = self.op_add(self.a_reg.0, self.b_reg.0, false);
+                self.a_reg = self.op_add(self.a_reg, self.b_reg, false);
             }
             0x81 => {
-                self.a_reg.0 = self.op_add(self.a_reg.0, self.c_reg.0, false);
+                self.a_reg = self.op_add(self.a_reg, self.c_reg, false);
             }
             0x82 => {
-                self.a_reg.0 = self.op_add(self.a_reg.0, self.d_reg.0, false);
+                self.a_reg = self.op_add(self.a_reg, self.d_reg, false);
             }
             0x83 => {
-                self.a_reg.0 = self.op_add(self.a_reg.0, self.e_reg.0, false);
+                self.a_reg = self.op_add(self.a_reg, self.e_reg, false);
             }
             0x84 => {
-                self.a_reg.0 = self.op_add(self.a_reg.0, self.h_reg.0, false);
+                self.a_reg = self.op_add(self.a_reg, self.h_reg, false);
             }
             0x85 => {
-                self.a_reg.0 = self.op_add(self.a_reg.0, self.l_reg.0, false);
+                self.a_reg = self.op_add(self.a_reg, self.l_reg, false);
             }
             0x86 => {
-                let value = self.mem.read_byte(self.get_hl_pair());
-                self.a_reg.0 = self.op_add(self.a_reg.0, Byte(value), false);
+                let value = self.read_byte(self.get_hl_pair());
+                self.a_reg = self.op_add(self.a_reg, value, false);
             }
             0x87 => {
-                self.a_reg.0 = self.op_add(self.a_reg.0, self.a_reg.0, false);
+                self.a_reg = self.op_add(self.a_reg, self.a_reg, false);
             }
             0x88 => {
-                self.a_reg.0 = self.op_add(self.a_reg.0, self.b_reg.0, self.c_flag.0);
+                self.a_reg = self.op_add(self.a_reg, self.b_reg, self.flag(C_FLAG));
             }
             0x89 => {
-                self.a_reg.0 = self.op_add(self.a_reg.0, self.c_reg.0, self.c_flag.0);
+                self.a_reg = self.op_add(self.a_reg, self.c_reg, self.flag(C_FLAG));
             }
             0x8a => {
-                self.a_reg.0 = self.op_add(self.a_reg.0, self.d_reg.0, self.c_flag.0);
+                self.a_reg = self.op_add(self.a_reg, self.d_reg, self.flag(C_FLAG));
             }
             0x8b => {
-                self.a_reg.0 = self.op_add(self.a_reg.0, self.e_reg.0, self.c_flag.0);
+                self.a_reg = self.op_add(self.a_reg, self.e_reg, self.flag(C_FLAG));
             }
             0x8c => {
-                self.a_reg.0 = self.op_add(self.a_reg.0, self.h_reg.0, self.c_flag.0);
+                self.a_reg = self.op_add(self.a_reg, self.h_reg, self.flag(C_FLAG));
             }
             0x8d => {
-                self.a_reg.0 = self.op_add(self.a_reg.0, self.l_reg.0, self.c_flag.0);
+                self.a_reg = self.op_add(self.a_reg, self.l_reg, self.flag(C_FLAG));
             }
             0x8e => {
-                let value = self.mem.read_byte(self.get_hl_pair());
-                self.a_reg.0 = self.op_add(self.a_reg.0, Byte(value), self.c_flag.0);
+                let value = self.read_byte(self.get_hl_pair());
+                self.a_reg = self.op_add(self.a_reg, value, self.flag(C_FLAG));
             }
             0x8f => {
-                self.a_reg.0 = self.op_add(self.a_reg.0, self.a_reg.0, self.c_flag.0);
+                self.a_reg = self.op_add(self.a_reg, self.a_reg, self.flag(C_FLAG));
             }
             0x90 => {
-                self.a_reg.0 = self.op_sub(self.a_reg.0, self.b_reg.0, false);
+                self.a_reg = self.op_sub(self.a_reg, self.b_reg, false);
             }
             0x91 => {
-                self.a_reg.0 = self.op_sub(self.a_reg.0, self.c_reg.0, false);
+                self.a_reg = self.op_sub(self.a_reg, self.c_reg, false);
             }
             0x92 => {
-                self.a_reg.0 = self.op_sub(self.a_reg.0, self.d_reg.0, false);
+                self.a_reg = self.op_sub(self.a_reg, self.d_reg, false);
             }
             0x93 => {
-                self.a_reg.0 = self.op_sub(self.a_reg.0, self.e_reg.0, false);
+                self.a_reg = self.op_sub(self.a_reg, self.e_reg, false);
             }
             0x94 => {
-                self.a_reg.0 = self.op_sub(self.a_reg.0, self.h_reg.0, false);
+                self.a_reg = self.op_sub(self.a_reg, self.h_reg, false);
             }
             0x95 => {
-                self.a_reg.0 = self.op_sub(self.a_reg.0, self.l_reg.0, false);
+                self.a_reg = self.op_sub(self.a_reg, self.l_reg, false);
             }
             0x96 => {
-                let value = self.mem.read_byte(self.get_hl_pair());
-                self.a_reg.0 = self.op_sub(self.a_reg.0, Byte(value), false);
+                let value = self.read_byte(self.get_hl_pair());
+                self.a_reg = self.op_sub(self.a_reg, value, false);
             }
             0x97 => {
-                self.a_reg.0 = self.op_sub(self.a_reg.0, self.a_reg.0, false);
+                self.a_reg = self.op_sub(self.a_reg, self.a_reg, false);
             }
             0x98 => {
-                self.a_reg.0 = self.op_sub(self.a_reg.0, self.b_reg.0, self.c_flag.0);
+                self.a_reg = self.op_sub(self.a_reg, self.b_reg, self.flag(C_FLAG));
             }
             0x99 => {
-                self.a_reg.0 = self.op_sub(self.a_reg.0, self.c_reg.0, self.c_flag.0);
+                self.a_reg = self.op_sub(self.a_reg, self.c_reg, self.flag(C_FLAG));
             }
             0x9a => {
-                self.a_reg.0 = self.op_sub(self.a_reg.0, self.d_reg.0, self.c_flag.0);
+                self.a_reg = self.op_sub(self.a_reg, self.d_reg, self.flag(C_FLAG));
             }
             0x9b => {
-                self.a_reg.0 = self.op_sub(self.a_reg.0, self.e_reg.0, self.c_flag.0);
+                self.a_reg = self.op_sub(self.a_reg, self.e_reg, self.flag(C_FLAG));
             }
             0x9c => {
-                self.a_reg.0 = self.op_sub(self.a_reg.0, self.h_reg.0, self.c_flag.0);
+                self.a_reg = self.op_sub(self.a_reg, self.h_reg, self.flag(C_FLAG));
             }
             0x9d => {
-                self.a_reg.0 = self.op_sub(self.a_reg.0, self.l_reg.0, self.c_flag.0);
+                self.a_reg = self.op_sub(self.a_reg, self.l_reg, self.flag(C_FLAG));
             }
             0x9e => {
-                let value = self.mem.read_byte(self.get_hl_pair());
-                self.a_reg.0 = self.op_sub(self.a_reg.0, Byte(value), self.c_flag.0);
+                let value = self.read_byte(self.get_hl_pair());
+                self.a_reg = self.op_sub(self.a_reg, value, self.flag(C_FLAG));
             }
             0x9f => {
-                self.a_reg.0 = self.op_sub(self.a_reg.0, self.a_reg.0, self.c_flag.0);
+                self.a_reg = self.op_sub(self.a_reg, self.a_reg, self.flag(C_FLAG));
             }
             0xa0 => {
-                self.op_ana(self.b_reg.0 .0);
+                self.op_ana(self.b_reg);
             }
             0xa1 => {
-                self.op_ana(self.c_reg.0 .0);
+                self.op_ana(self.c_reg);
             }
             0xa2 => {
-                self.op_ana(self.d_reg.0 .0);
+                self.op_ana(self.d_reg);
             }
             0xa3 => {
-                self.op_ana(self.e_reg.0 .0);
+                self.op_ana(self.e_reg);
             }
             0xa4 => {
-                self.op_ana(self.h_reg.0 .0);
+                self.op_ana(self.h_reg);
             }
             0xa5 => {
-                self.op_ana(self.l_reg.0 .0);
+                self.op_ana(self.l_reg);
             }
             0xa6 => {
-                let value = self.mem.read_byte(self.get_hl_pair());
+                let value = self.read_byte(self.get_hl_pair());
                 self.op_ana(value);
             }
             0xa7 => {
-                self.op_ana(self.a_reg.0 .0);
+                self.op_ana(self.a_reg);
             }
             0xa8 => {
-                self.op_xra(self.b_reg.0 .0);
+                self.op_xra(self.b_reg);
             }
             0xa9 => {
-                self.op_xra(self.c_reg.0 .0);
+                self.op_xra(self.c_reg);
             }
             0xaa => {
-                self.op_xra(self.d_reg.0 .0);
+                self.op_xra(self.d_reg);
             }
             0xab => {
-                self.op_xra(self.e_reg.0 .0);
+                self.op_xra(self.e_reg);
             }
             0xac => {
-                self.op_xra(self.h_reg.0 .0);
+                self.op_xra(self.h_reg);
             }
             0xad => {
-                self.op_xra(self.l_reg.0 .0);
+                self.op_xra(self.l_reg);
             }
             0xae => {
-                let value = self.mem.read_byte(self.get_hl_pair());
+                let value = self.read_byte(self.get_hl_pair());
                 self.op_xra(value);
             }
             0xaf => {
-                self.op_xra(self.a_reg.0 .0);
+                self.op_xra(self.a_reg);
             }
             0xb0 => {
-                self.op_ora(self.b_reg.0 .0);
+                self.op_ora(self.b_reg);
             }
             0xb1 => {
-                self.op_ora(self.c_reg.0 .0);
+                self.op_ora(self.c_reg);
             }
             0xb2 => {
-                self.op_ora(self.d_reg.0 .0);
+                self.op_ora(self.d_reg);
             }
             0xb3 => {
-                self.op_ora(self.e_reg.0 .0);
+                self.op_ora(self.e_reg);
             }
             0xb4 => {
-                self.op_ora(self.h_reg.0 .0);
+                self.op_ora(self.h_reg);
             }
             0xb5 => {
-                self.op_ora(self.l_reg.0 .0);
+                self.op_ora(self.l_reg);
             }
             0xb6 => {
-                let value = self.mem.read_byte(self.get_hl_pair());
+                let value = self.read_byte(self.get_hl_pair());
                 self.op_ora(value);
             }
             0xb7 => {
-                self.op_ora(self.a_reg.0 .0);
+                self.op_ora(self.a_reg);
             }
             0xb8 => {
-                self.op_cmp(self.b_reg.0 .0);
+                self.op_cmp(self.b_reg);
             }
             0xb9 => {
-                self.op_cmp(self.c_reg.0 .0);
+                self.op_cmp(self.c_reg);
             }
             0xba => {
-                self.op_cmp(self.d_reg.0 .0);
+                self.op_cmp(self.d_reg);
             }
             0xbb => {
-                self.op_cmp(self.e_reg.0 .0);
+                self.op_cmp(self.e_reg);
             }
             0xbc => {
-                self.op_cmp(self.h_reg.0 .0);
+                self.op_cmp(self.h_reg);
             }
             0xbd => {
-                self.op_cmp(self.l_reg.0 .0);
+                self.op_cmp(self.l_reg);
             }
             0xbe => {
-                let value = self.mem.read_byte(self.get_hl_pair());
+                let value = self.read_byte(self.get_hl_pair());
                 self.op_cmp(value);
             }
             0xbf => {
-                self.op_cmp(self.a_reg.0 .0);
+                self.op_cmp(self.a_reg);
             }
             0xc0 => {
-                self.op_cond_ret(!self.z_flag.0);
+                self.op_cond_ret(!self.flag(Z_FLAG));
             }
             0xc1 => {
                 let value = self.pop_stack();
                 self.set_bc_pair(value)
             }
             0xc2 => {
-                self.op_cond_jmp(!self.z_flag.0);
+                self.op_cond_jmp(!self.flag(Z_FLAG));
             }
             0xc3 => {
                 let addr = self.fetch_next_word();
                 self.op_jmp(addr);
             }
             0xc4 => {
-                self.op_cond_call(!self.z_flag.0);
+                self.op_cond_call(!self.flag(Z_FLAG));
             }
             0xc5 => {
                 self.push_stack(self.get_bc_pair());
             }
             0xc6 => {
                 let value = self.fetch_next_byte();
-                self.a_reg.0 = self.op_add(self.a_reg.0, Byte(value), false);
+                self.a_reg = self.op_add(self.a_reg, value, false);
             }
             0xc7 => {
                 self.op_call(0x00);
             }
             0xc8 => {
-                self.op_cond_ret(self.z_flag.0);
+                self.op_cond_ret(self.flag(Z_FLAG));
             }
             0xc9 => {
                 self.op_ret();
             }
             0xca => {
-                self.op_cond_jmp(self.z_flag.0);
+                self.op_cond_jmp(self.flag(Z_FLAG));
             }
             0xcc => {
-                self.op_cond_call(self.z_flag.0);
+                self.op_cond_call(self.flag(Z_FLAG));
             }
             0xcd => {
                 let addr = self.fetch_next_word();
@@ -934,75 +1366,76 @@ impl Emulator {
             }
             0xce => {
                 let value = self.fetch_next_byte();
-                self.a_reg.0 = self.op_add(self.a_reg.0, Byte(value), self.c_flag.0);
+                self.a_reg = self.op_add(self.a_reg, value, self.flag(C_FLAG));
             }
             0xcf => {
                 self.op_call(0x08);
             }
             0xd0 => {
-                self.op_cond_ret(!self.c_flag.0);
+                self.op_cond_ret(!self.flag(C_FLAG));
             }
             0xd1 => {
                 let value = self.pop_stack();
                 self.set_de_pair(value)
             }
             0xd2 => {
-                self.op_cond_jmp(!self.c_flag.0);
+                self.op_cond_jmp(!self.flag(C_FLAG));
             }
             0xd3 => {
                 let port = self.fetch_next_byte();
-                self.mem.out_port(port, self.a_reg.0 .0);
+                let value = self.a_reg;
+                self.io().out_port(port, value);
             }
             0xd4 => {
-                self.op_cond_call(!self.c_flag.0);
+                self.op_cond_call(!self.flag(C_FLAG));
             }
             0xd5 => {
                 self.push_stack(self.get_de_pair());
             }
             0xd6 => {
                 let value = self.fetch_next_byte();
-                self.a_reg.0 = self.op_sub(self.a_reg.0, Byte(value), false);
+                self.a_reg = self.op_sub(self.a_reg, value, false);
             }
             0xd7 => {
                 self.op_call(0x10);
             }
             0xd8 => {
-                self.op_cond_ret(self.c_flag.0);
+                self.op_cond_ret(self.flag(C_FLAG));
             }
             0xda => {
-                self.op_cond_jmp(self.c_flag.0);
+                self.op_cond_jmp(self.flag(C_FLAG));
             }
             0xdb => {
                 let port = self.fetch_next_byte();
-                self.a_reg.0 .0 = self.mem.in_port(port);
+                self.a_reg = self.io().in_port(port);
             }
             0xdc => {
-                self.op_cond_call(self.c_flag.0);
+                self.op_cond_call(self.flag(C_FLAG));
             }
             0xde => {
                 let value = self.fetch_next_byte();
-                self.a_reg.0 = self.op_sub(self.a_reg.0, Byte(value), self.c_flag.0);
+                self.a_reg = self.op_sub(self.a_reg, value, self.flag(C_FLAG));
             }
             0xdf => {
                 self.op_call(0x18);
             }
             0xe0 => {
-                self.op_cond_ret(!self.p_flag.0);
+                self.op_cond_ret(!self.flag(P_FLAG));
             }
             0xe1 => {
                 let value = self.pop_stack();
                 self.set_hl_pair(value)
             }
             0xe2 => {
-                self.op_cond_jmp(!self.p_flag.0);
+                self.op_cond_jmp(!self.flag(P_FLAG));
             }
             0xe3 => {
-                let value = self.mem.read_word(self.sp.0);
-                self.mem.write_word(self.sp.0, self.get_hl_pair());
+                let value = self.read_word(self.sp);
+                self.write_word(self.sp, self.get_hl_pair());
                 self.set_hl_pair(value);
             }
             0xe4 => {
-                self.op_cond_call(!self.p_flag.0);
+                self.op_cond_call(!self.flag(P_FLAG));
             }
             0xe5 => {
                 self.push_stack(self.get_hl_pair());
@@ -1015,13 +1448,13 @@ impl Emulator {
                 self.op_call(0x20);
             }
             0xe8 => {
-                self.op_cond_ret(self.p_flag.0);
+                self.op_cond_ret(self.flag(P_FLAG));
             }
             0xe9 => {
-                self.pc.0 = self.get_hl_pair();
+                self.pc = self.get_hl_pair();
             }
             0xea => {
-                self.op_cond_jmp(self.p_flag.0);
+                self.op_cond_jmp(self.flag(P_FLAG));
             }
             0xeb => {
                 let value = self.get_de_pair();
@@ -1029,7 +1462,7 @@ impl Emulator {
                 self.set_hl_pair(value);
             }
             0xec => {
-                self.op_cond_call(self.p_flag.0);
+                self.op_cond_call(self.flag(P_FLAG));
             }
             0xee => {
                 let value = self.fetch_next_byte();
@@ -1039,38 +1472,24 @@ impl Emulator {
                 self.op_call(0x28);
             }
             0xf0 => {
-                self.op_cond_ret(!self.s_flag.0);
+                self.op_cond_ret(!self.flag(S_FLAG));
             }
             0xf1 => {
                 let value = self.pop_stack();
-                self.a_reg.0 .0 = (value >> 8) as u8;
-
-                let psw = (value & 0xff) as u8;
-                self.s_flag.0 = ((psw >> 7) & 1) != 0;
-                self.z_flag.0 = ((psw >> 6) & 1) != 0;
-                self.h_flag.0 = ((psw >> 4) & 1) != 0;
-                self.p_flag.0 = ((psw >> 2) & 1) != 0;
-                self.c_flag.0 = ((psw >> 0) & 1) != 0;
+                self.set_psw(value);
             }
             0xf2 => {
-                self.op_cond_jmp(!self.s_flag.0);
+                self.op_cond_jmp(!self.flag(S_FLAG));
             }
             0xf3 => {
-                self.int.filp_flop = true;
+                self.int.filp_flop = false;
             }
             0xf4 => {
-                self.op_cond_call(!self.s_flag.0);
+                self.op_cond_call(!self.flag(S_FLAG));
             }
             0xf5 => {
-                let mut psw: u8 = 0;
-                psw |= (self.s_flag.0 as u8) << 7;
-                psw |= (self.z_flag.0 as u8) << 6;
-                psw |= (self.h_flag.0 as u8) << 4;
-                psw |= (self.p_flag.0 as u8) << 2;
-                psw |= 1 << 1;
-                psw |= (self.c_flag.0 as u8) << 0;
-
-                self.push_stack((self.a_reg.0 .0 as u16) << 8 | (psw as u16));
+                let value = self.get_psw();
+                self.push_stack(value);
             }
             0xf6 => {
                 let value = self.fetch_next_byte();
@@ -1080,20 +1499,20 @@ impl Emulator {
                 self.op_call(0x30);
             }
             0xf8 => {
-                self.op_cond_ret(self.s_flag.0);
+                self.op_cond_ret(self.flag(S_FLAG));
             }
             0xf9 => {
-                self.sp.0 = self.get_hl_pair();
+                self.sp = self.get_hl_pair();
             }
             0xfa => {
-                self.op_cond_jmp(self.s_flag.0);
+                self.op_cond_jmp(self.flag(S_FLAG));
             }
             0xfb => {
-                self.int.filp_flop = false;
-                self.int.delay.0 = 1;
+                self.int.filp_flop = true;
+                self.int.delay = 1;
             }
             0xfc => {
-                self.op_cond_call(self.s_flag.0);
+                self.op_cond_call(self.flag(S_FLAG));
             }
             0xfe => {
                 let value = self.fetch_next_byte();
@@ -1119,10 +1538,188 @@ impl Emulator {
         }
     }
 
+    pub fn step(&mut self) -> usize {
+        let start = self.cycles;
+        let mut accepted = None;
+
+        if let Some(addr) = self.interrupt_8085() {
+            self.int.filp_flop = false;
+            self.halt = false;
+            self.op_call(addr);
+            self.cycles += 12;
+            accepted = Some(addr);
+        } else if self.z80.nmi {
+            self.nmi_z80();
+            accepted = Some(0x66);
+        } else if self.int.filp_flop && self.int.delay == 0 {
+            let vector = if self.int.pending {
+                self.int.pending = false;
+                Some(self.int.vector)
+            } else {
+                self.io().interrupt()
+            };
+
+            if let Some(addr) = vector {
+                self.int.filp_flop = false;
+                self.halt = false;
+
+                if self.cpu == Cpu::Z80 {
+                    self.interrupt_z80(addr);
+                } else {
+                    self.op_call(addr);
+                    self.cycles += 11;
+                }
+                accepted = Some(addr);
+            }
+        }
+        if let Some(vector) = accepted {
+            self.io().accepted(vector);
+        }
+
+        if self.cycles == start {
+            if self.halt {
+                self.cycles += 4;
+            } else {
+                let op = self.fetch_next_byte();
+                self.exec(op);
+            }
+        }
+
+        let cycles = self.cycles - start;
+        self.io().tick(cycles);
+        cycles
+    }
+
+    /// Vector of the highest priority 8085 interrupt input to accept.
+    fn interrupt_8085(&mut self) -> Option<u16> {
+        if self.cpu != Cpu::I8085 {
+            return None;
+        }
+
+        let pins = &mut self.pins;
+        if pins.trap_pending {
+            pins.trap_pending = false;
+            pins.trap_ie = Some(self.int.filp_flop);
+            return Some(0x24);
+        }
+
+        if !self.int.filp_flop || self.int.delay > 0 {
+            return None;
+        }
+
+        if pins.rst75_pending && pins.masks & 0x04 == 0 {
+            pins.rst75_pending = false;
+            Some(0x3c)
+        } else if pins.rst65 && pins.masks & 0x02 == 0 {
+            Some(0x34)
+        } else if pins.rst55 && pins.masks & 0x01 == 0 {
+            Some(0x2c)
+        } else {
+            None
+        }
+    }
+
     pub fn run(&mut self) {
         while !self.halt {
-            let op = self.fetch_next_byte();
-            self.exec(op);
+            self.step();
+        }
+    }
+
+    /// Requests an interrupt that calls `vector` once interrupts are enabled.
+    pub fn interrupt(&mut self, vector: u16) {
+        self.int.pending = true;
+        self.int.vector = vector;
+    }
+
+    pub fn memory(&self) -> &dyn Memory {
+        match &self.mem {
+            Mem::Flat(mem) => mem,
+            Mem::Boxed(mem) => mem.as_ref(),
+        }
+    }
+
+    pub fn memory_mut(&mut self) -> &mut dyn Memory {
+        match &mut self.mem {
+            Mem::Flat(mem) => mem,
+            Mem::Boxed(mem) => mem.as_mut(),
         }
     }
+
+    /// Replaces the memory with `f(memory)`, e.g. to wrap it.
+    ///
+    /// Wrapped flat RAM goes through the `Memory` trait like any other memory.
+    pub fn wrap_memory(&mut self, f: impl FnOnce(Box<dyn Memory>) -> Box<dyn Memory>) {
+        let mem = match core::mem::replace(&mut self.mem, Mem::Boxed(Box::new(bus::Bus::new()))) {
+            Mem::Flat(mem) => Box::new(mem),
+            Mem::Boxed(mem) => mem,
+        };
+        self.mem = Mem::Boxed(f(mem));
+    }
+
+    pub fn cycles(&self) -> usize {
+        self.cycles
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
+
+    pub fn registers(&self) -> Registers {
+        Registers {
+            a: self.a_reg,
+            f: self.get_psw() as u8,
+            b: self.b_reg,
+            c: self.c_reg,
+            d: self.d_reg,
+            e: self.e_reg,
+            h: self.h_reg,
+            l: self.l_reg,
+            sp: self.sp,
+            pc: self.pc,
+        }
+    }
+
+    pub fn halted(&self) -> bool {
+        self.halt
+    }
+
+    pub fn cpu(&self) -> Cpu {
+        self.cpu
+    }
+
+    /// Sets the level of an 8085 interrupt input, RST 7.5 and TRAP trigger
+    /// on the rising edge.
+    pub fn set_input(&mut self, input: Input, level: bool) {
+        let pins = &mut self.pins;
+        match input {
+            Input::Rst55 => pins.rst55 = level,
+            Input::Rst65 => pins.rst65 = level,
+            Input::Rst75 => {
+                if level && !pins.rst75 {
+                    pins.rst75_pending = true;
+                }
+                pins.rst75 = level;
+            }
+            Input::Trap => {
+                if level && !pins.trap {
+                    pins.trap_pending = true;
+                }
+                pins.trap = level;
+            }
+        }
+    }
+
+    /// Sets the 8085 serial input line, read by `RIM`.
+    pub fn set_sid(&mut self, level: bool) {
+        self.pins.sid = level;
+    }
+
+    /// 8085 serial output line, written by `SIM`.
+    pub fn sod(&self) -> bool {
+        self.pins.sod
+    }
 }
@@ -0,0 +1,304 @@
+use super::Memory;
+use std::{cell::RefCell, fmt, rc::Rc, str::FromStr};
+
+/// Input seen by the cpu, at the cycle it happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoEvent {
+    /// Value read by `IN`
+    In { cycle: usize, port: u8, value: u8 },
+    /// Interrupt accepted by the cpu
+    Interrupt { cycle: usize, vector: u16 },
+}
+
+impl IoEvent {
+    pub fn cycle(&self) -> usize {
+        match self {
+            IoEvent::In { cycle, .. } | IoEvent::Interrupt { cycle, .. } => *cycle,
+        }
+    }
+}
+
+/// Recorded input, one event per line as `in CYCLE PORT VALUE` or
+/// `int CYCLE VECTOR`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IoLog {
+    pub events: Vec<IoEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogError(pub usize);
+
+impl fmt::Display for LogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "malformed event at line {}", self.0)
+    }
+}
+
+impl FromStr for IoLog {
+    type Err = LogError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let mut events = Vec::new();
+
+        for (n, line) in src.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("");
+            let words: Vec<&str> = line.split_ascii_whitespace().collect();
+            let err = || LogError(n + 1);
+
+            let event = match words[..] {
+                [] => continue,
+                ["in", cycle, port, value] => IoEvent::In {
+                    cycle: cycle.parse().map_err(|_| err())?,
+                    port: port.parse().map_err(|_| err())?,
+                    value: value.parse().map_err(|_| err())?,
+                },
+                ["int", cycle, vector] => IoEvent::Interrupt {
+                    cycle: cycle.parse().map_err(|_| err())?,
+                    vector: vector.parse().map_err(|_| err())?,
+                },
+                _ => return Err(err()),
+            };
+            events.push(event);
+        }
+
+        Ok(Self { events })
+    }
+}
+
+impl fmt::Display for IoLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for event in &self.events {
+            match event {
+                IoEvent::In { cycle, port, value } => {
+                    writeln!(f, "in {} {} {}", cycle, port, value)?
+                }
+                IoEvent::Interrupt { cycle, vector } => writeln!(f, "int {} {}", cycle, vector)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Memory wrapper logging the input of the wrapped memory and every
+/// interrupt the cpu accepts, whether from the memory, the host or the pins.
+pub struct Recorder {
+    mem: Box<dyn Memory>,
+    /// Cycles elapsed
+    cycles: usize,
+    log: Rc<RefCell<IoLog>>,
+}
+
+impl Recorder {
+    /// Wraps `mem`, appending its input to `log`.
+    pub fn new(mem: Box<dyn Memory>, log: Rc<RefCell<IoLog>>) -> Self {
+        Self {
+            mem,
+            cycles: 0,
+            log,
+        }
+    }
+}
+
+impl Memory for Recorder {
+    fn read_byte(&self, addr: u16) -> u8 {
+        self.mem.read_byte(addr)
+    }
+
+    fn read_word(&self, addr: u16) -> u16 {
+        self.mem.read_word(addr)
+    }
+
+    fn write_byte(&mut self, addr: u16, byte: u8) {
+        self.mem.write_byte(addr, byte);
+    }
+
+    fn write_word(&mut self, addr: u16, word: u16) {
+        self.mem.write_word(addr, word);
+    }
+
+    fn in_port(&mut self, port: u8) -> u8 {
+        let value = self.mem.in_port(port);
+        self.log.borrow_mut().events.push(IoEvent::In {
+            cycle: self.cycles,
+            port,
+            value,
+        });
+        value
+    }
+
+    fn out_port(&mut self, port: u8, byte: u8) {
+        self.mem.out_port(port, byte);
+    }
+
+    fn tick(&mut self, cycles: usize) {
+        self.cycles += cycles;
+        self.mem.tick(cycles);
+    }
+
+    fn interrupt(&mut self) -> Option<u16> {
+        self.mem.interrupt()
+    }
+
+    fn accepted(&mut self, vector: u16) {
+        self.log.borrow_mut().events.push(IoEvent::Interrupt {
+            cycle: self.cycles,
+            vector,
+        });
+        self.mem.accepted(vector);
+    }
+}
+
+/// Progress of a replay.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayState {
+    log: IoLog,
+    /// Next event to replay
+    next: usize,
+    /// First input that didn't match the log
+    diverged: Option<(usize, String)>,
+}
+
+impl ReplayState {
+    pub fn new(log: IoLog) -> Self {
+        Self {
+            log,
+            next: 0,
+            diverged: None,
+        }
+    }
+
+    /// Every event was replayed.
+    pub fn finished(&self) -> bool {
+        self.next >= self.log.events.len()
+    }
+
+    /// Cycle and description of the first input that didn't match the log.
+    pub fn diverged(&self) -> Option<&(usize, String)> {
+        self.diverged.as_ref()
+    }
+
+    fn diverge(&mut self, cycle: usize, msg: String) {
+        if self.diverged.is_none() {
+            self.diverged = Some((cycle, msg));
+        }
+    }
+}
+
+/// Memory wrapper feeding back the input recorded by a `Recorder`.
+///
+/// Memory, output and ticks still go to the wrapped memory, which is also
+/// acknowledged when a logged interrupt is replayed; once the run diverges
+/// from the log the input comes from the wrapped memory too. Interrupts the
+/// host raises again during the replay are matched against the log.
+pub struct Replayer {
+    mem: Box<dyn Memory>,
+    /// Cycles elapsed
+    cycles: usize,
+    state: Rc<RefCell<ReplayState>>,
+}
+
+impl Replayer {
+    pub fn new(mem: Box<dyn Memory>, state: Rc<RefCell<ReplayState>>) -> Self {
+        Self {
+            mem,
+            cycles: 0,
+            state,
+        }
+    }
+}
+
+impl Memory for Replayer {
+    fn read_byte(&self, addr: u16) -> u8 {
+        self.mem.read_byte(addr)
+    }
+
+    fn read_word(&self, addr: u16) -> u16 {
+        self.mem.read_word(addr)
+    }
+
+    fn write_byte(&mut self, addr: u16, byte: u8) {
+        self.mem.write_byte(addr, byte);
+    }
+
+    fn write_word(&mut self, addr: u16, word: u16) {
+        self.mem.write_word(addr, word);
+    }
+
+    fn in_port(&mut self, port: u8) -> u8 {
+        let mut state = self.state.borrow_mut();
+        if state.diverged.is_some() {
+            drop(state);
+            return self.mem.in_port(port);
+        }
+
+        let event = state.log.events.get(state.next).copied();
+        match event {
+            Some(IoEvent::In {
+                cycle,
+                port: p,
+                value,
+            }) if cycle == self.cycles && p == port => {
+                state.next += 1;
+                value
+            }
+            _ => {
+                state.diverge(
+                    self.cycles,
+                    format!("read port {} instead of {:?}", port, event),
+                );
+                drop(state);
+                self.mem.in_port(port)
+            }
+        }
+    }
+
+    fn out_port(&mut self, port: u8, byte: u8) {
+        self.mem.out_port(port, byte);
+    }
+
+    fn tick(&mut self, cycles: usize) {
+        self.cycles += cycles;
+        self.mem.tick(cycles);
+    }
+
+    fn interrupt(&mut self) -> Option<u16> {
+        let mut state = self.state.borrow_mut();
+        if state.diverged.is_some() {
+            drop(state);
+            return self.mem.interrupt();
+        }
+
+        match state.log.events.get(state.next).copied() {
+            // Consumed by `accepted`
+            Some(IoEvent::Interrupt { cycle, vector }) if cycle == self.cycles => {
+                drop(state);
+                self.mem.interrupt();
+                Some(vector)
+            }
+            Some(event) if event.cycle() < self.cycles => {
+                state.diverge(self.cycles, format!("missed {:?}", event));
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn accepted(&mut self, vector: u16) {
+        let mut state = self.state.borrow_mut();
+        if state.diverged.is_none() {
+            let expected = IoEvent::Interrupt {
+                cycle: self.cycles,
+                vector,
+            };
+            match state.log.events.get(state.next) {
+                Some(event) if *event == expected => state.next += 1,
+                event => {
+                    let msg = format!("accepted {:?} instead of {:?}", expected, event);
+                    state.diverge(self.cycles, msg);
+                }
+            }
+        }
+        drop(state);
+        self.mem.accepted(vector);
+    }
+}
@@ -0,0 +1,352 @@
+use super::{Emulator, Memory};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+/// Rgb color.
+pub type Rgb = [u8; 3];
+
+/// Rgb image, 3 bytes per pixel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 3],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Rgb {
+        let i = (y * self.width + x) * 3;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2]]
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Rgb) {
+        let i = (y * self.width + x) * 3;
+        self.pixels[i..i + 3].copy_from_slice(&color);
+    }
+
+    /// Rows of pixels, top to bottom.
+    pub fn data(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+/// Video hardware turning memory into pixels.
+pub trait Framebuffer {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+
+    /// Draws the whole screen from `mem` into `frame`.
+    fn render(&mut self, mem: &dyn Memory, frame: &mut Frame);
+}
+
+/// One bit per pixel bitmap, least significant bit first.
+///
+/// Rotated bitmaps are stored column by column from the bottom up, like on
+/// monitors turned counterclockwise (e.g. Space Invaders).
+#[derive(Debug, Clone)]
+pub struct Bitmap {
+    /// Address of the first byte
+    pub base: u16,
+    /// Width of the displayed image
+    pub width: usize,
+    /// Height of the displayed image
+    pub height: usize,
+    /// Monitor turned counterclockwise
+    pub rotate: bool,
+    /// Color of set bits
+    pub on: Rgb,
+    /// Color of clear bits
+    pub off: Rgb,
+}
+
+impl Bitmap {
+    pub fn new(base: u16, width: usize, height: usize) -> Self {
+        Self {
+            base,
+            width,
+            height,
+            rotate: false,
+            on: [0xff; 3],
+            off: [0; 3],
+        }
+    }
+}
+
+impl Framebuffer for Bitmap {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn render(&mut self, mem: &dyn Memory, frame: &mut Frame) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let bit = if self.rotate {
+                    x * self.height + (self.height - 1 - y)
+                } else {
+                    y * self.width + x
+                };
+
+                let byte = mem.read_byte(self.base.wrapping_add((bit / 8) as u16));
+                let color = if byte & (1 << (bit % 8)) != 0 {
+                    self.on
+                } else {
+                    self.off
+                };
+                frame.set_pixel(x, y, color);
+            }
+        }
+    }
+}
+
+/// Host backend receiving the rendered frames.
+pub trait FrameSink {
+    fn frame(&mut self, frame: &Frame) -> io::Result<()>;
+
+    /// Called after the last frame.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Frame sequence written as numbered files, e.g. `frame-00000.ppm`.
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    /// Files are named `{prefix}{index:05}.{ext}`
+    prefix: PathBuf,
+    format: ImageFormat,
+    count: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Ppm,
+    Png,
+}
+
+impl Sequence {
+    pub fn new(prefix: impl Into<PathBuf>, format: ImageFormat) -> Self {
+        Self {
+            prefix: prefix.into(),
+            format,
+            count: 0,
+        }
+    }
+
+    /// Number of frames written.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    fn path(&self, index: usize) -> PathBuf {
+        let ext = match self.format {
+            ImageFormat::Ppm => "ppm",
+            ImageFormat::Png => "png",
+        };
+
+        let mut name = self.prefix.clone().into_os_string();
+        name.push(format!("{:05}.{}", index, ext));
+        name.into()
+    }
+}
+
+impl FrameSink for Sequence {
+    fn frame(&mut self, frame: &Frame) -> io::Result<()> {
+        let out = BufWriter::new(File::create(self.path(self.count))?);
+        match self.format {
+            ImageFormat::Ppm => write_ppm(out, frame)?,
+            ImageFormat::Png => write_png(out, frame)?,
+        }
+        self.count += 1;
+        Ok(())
+    }
+}
+
+/// Frames collected into an animated png, written by `finish`.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    path: PathBuf,
+    /// Frames per second
+    fps: u16,
+    frames: Vec<Frame>,
+}
+
+impl Animation {
+    pub fn new(path: impl Into<PathBuf>, fps: u16) -> Self {
+        Self {
+            path: path.into(),
+            fps: fps.max(1),
+            frames: Vec::new(),
+        }
+    }
+}
+
+impl FrameSink for Animation {
+    fn frame(&mut self, frame: &Frame) -> io::Result<()> {
+        self.frames.push(frame.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let first = match self.frames.first() {
+            Some(first) => first,
+            None => return Ok(()),
+        };
+
+        let out = BufWriter::new(File::create(&self.path)?);
+        let mut enc = png::Encoder::new(out, first.width as u32, first.height as u32);
+        enc.set_color(png::ColorType::Rgb);
+        enc.set_depth(png::BitDepth::Eight);
+        enc.set_animated(self.frames.len() as u32, 0)?;
+        enc.set_frame_delay(1, self.fps)?;
+
+        let mut writer = enc.write_header()?;
+        for frame in &self.frames {
+            writer.write_image_data(&frame.pixels)?;
+        }
+        writer.finish()?;
+
+        self.frames.clear();
+        Ok(())
+    }
+}
+
+/// Writes `frame` as a binary ppm image.
+pub fn write_ppm<W: Write>(mut out: W, frame: &Frame) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", frame.width, frame.height)?;
+    out.write_all(&frame.pixels)?;
+    out.flush()
+}
+
+/// Writes `frame` as a png image.
+pub fn write_png<W: Write>(out: W, frame: &Frame) -> io::Result<()> {
+    let mut enc = png::Encoder::new(out, frame.width as u32, frame.height as u32);
+    enc.set_color(png::ColorType::Rgb);
+    enc.set_depth(png::BitDepth::Eight);
+
+    let mut writer = enc.write_header()?;
+    writer.write_image_data(&frame.pixels)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Video timing, raising interrupts at fixed cycles of every frame and
+/// rendering the framebuffer at vblank.
+pub struct Video {
+    fb: Box<dyn Framebuffer>,
+    frame: Frame,
+    /// Cycles per frame
+    period: usize,
+    /// Cycle of the start of the current frame
+    start: usize,
+    /// Interrupts as cycle in the frame and vector, sorted by cycle
+    events: Vec<(usize, u16)>,
+    /// Next interrupt to raise
+    next: usize,
+    /// Frames rendered
+    count: usize,
+}
+
+impl Video {
+    /// Video refreshing `fps` times per second with a cpu running at `clock` Hz.
+    pub fn new(fb: Box<dyn Framebuffer>, clock: u64, fps: u64) -> Self {
+        let frame = Frame::new(fb.width(), fb.height());
+        Self {
+            fb,
+            frame,
+            period: (clock / fps.max(1)).max(1) as usize,
+            start: 0,
+            events: Vec::new(),
+            next: 0,
+            count: 0,
+        }
+    }
+
+    /// Raises `vector` when `cycle` cycles of every frame have elapsed, a
+    /// `cycle` equal to the frame period is the vblank.
+    pub fn on_cycle(&mut self, cycle: usize, vector: u16) {
+        self.events.push((cycle.min(self.period), vector));
+        self.events.sort_by_key(|(cycle, _)| *cycle);
+    }
+
+    /// Raises `vector` at vblank.
+    pub fn on_vblank(&mut self, vector: u16) {
+        self.on_cycle(self.period, vector);
+    }
+
+    pub fn period(&self) -> usize {
+        self.period
+    }
+
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    /// Number of frames rendered.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Catches up with the cycles run by `emu`, returns true when a frame was
+    /// rendered.
+    pub fn update(&mut self, emu: &mut Emulator) -> bool {
+        let now = emu.cycles();
+
+        while let Some((cycle, vector)) = self.events.get(self.next) {
+            if now < self.start + cycle {
+                break;
+            }
+            emu.interrupt(*vector);
+            self.next += 1;
+        }
+
+        if now < self.start + self.period {
+            return false;
+        }
+
+        self.fb.render(emu.memory(), &mut self.frame);
+        self.start += self.period;
+        self.next = 0;
+        self.count += 1;
+        true
+    }
+
+    /// Runs `emu` for `frames` frames, passing each one to `sink`.
+    pub fn run(
+        &mut self,
+        emu: &mut Emulator,
+        frames: usize,
+        sink: &mut dyn FrameSink,
+    ) -> io::Result<()> {
+        let end = self.count + frames;
+        while self.count < end {
+            emu.step();
+            if self.update(emu) {
+                sink.frame(&self.frame)?;
+            }
+        }
+        sink.finish()
+    }
+}
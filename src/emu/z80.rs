@@ -0,0 +1,598 @@
+use super::{Emulator, C_FLAG, H_FLAG, P_FLAG, S_FLAG, Z_FLAG};
+
+/// Cycles of the unprefixed Z80 instructions, without the extra cycles of the
+/// taken conditional branches.
+pub(super) const CYCLES: [usize; 256] = [
+    4, 10, 7, 6, 4, 4, 7, 4, 4, 11, 7, 6, 4, 4, 7, 4, 8, 10, 7, 6, 4, 4, 7, 4, 12, 11, 7, 6, 4, 4,
+    7, 4, 7, 10, 16, 6, 4, 4, 7, 4, 7, 11, 16, 6, 4, 4, 7, 4, 7, 10, 13, 6, 11, 11, 10, 4, 7, 11,
+    13, 6, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4,
+    4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 7, 7, 7, 7, 7, 7, 4, 7, 4,
+    4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4,
+    4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, 4,
+    4, 4, 4, 4, 4, 7, 4, 5, 10, 10, 10, 10, 11, 7, 11, 5, 10, 10, 4, 10, 17, 7, 11, 5, 10, 10, 11,
+    10, 11, 7, 11, 5, 4, 10, 11, 10, 4, 7, 11, 5, 10, 10, 19, 10, 11, 7, 11, 5, 4, 10, 4, 10, 4, 7,
+    11, 5, 10, 10, 4, 10, 11, 7, 11, 5, 6, 10, 4, 10, 4, 7, 11,
+];
+
+/// Z80 registers missing on the 8080.
+#[derive(Debug, Default)]
+pub(super) struct State {
+    /// Alternate AF, BC, DE and HL
+    af: u16,
+    bc: u16,
+    de: u16,
+    hl: u16,
+    ix: u16,
+    iy: u16,
+    /// Interrupt vector base
+    i: u8,
+    /// Memory refresh counter
+    r: u8,
+    /// Interrupt enable saved by NMI
+    iff2: bool,
+    /// Interrupt mode
+    mode: u8,
+    /// Non maskable interrupt requested
+    pub(super) nmi: bool,
+}
+
+/// Instructions using `(HL)` as an operand, `(IX+d)` after a DD prefix.
+fn uses_memory(op: u8) -> bool {
+    matches!(op, 0x34..=0x36)
+        || (op & 0xc7 == 0x46 || op & 0xf8 == 0x70 || op & 0xc7 == 0x86) && op != 0x76
+}
+
+impl Emulator {
+    /// Requests a non maskable interrupt, Z80 only.
+    pub fn nmi(&mut self) {
+        self.z80.nmi = self.cpu == super::Cpu::Z80;
+    }
+
+    fn refresh(&mut self) {
+        let r = self.z80.r;
+        self.z80.r = (r & 0x80) | (r.wrapping_add(1) & 0x7f);
+    }
+
+    fn fetch_opcode(&mut self) -> u8 {
+        self.refresh();
+        self.fetch_next_byte()
+    }
+
+    /// Register by its index in the opcode, 6 is `(HL)`.
+    fn get_reg(&self, r: u8) -> u8 {
+        match r & 7 {
+            0 => self.b_reg,
+            1 => self.c_reg,
+            2 => self.d_reg,
+            3 => self.e_reg,
+            4 => self.h_reg,
+            5 => self.l_reg,
+            6 => self.read_byte(self.get_hl_pair()),
+            _ => self.a_reg,
+        }
+    }
+
+    fn set_reg(&mut self, r: u8, value: u8) {
+        match r & 7 {
+            0 => self.b_reg = value,
+            1 => self.c_reg = value,
+            2 => self.d_reg = value,
+            3 => self.e_reg = value,
+            4 => self.h_reg = value,
+            5 => self.l_reg = value,
+            6 => self.write_byte(self.get_hl_pair(), value),
+            _ => self.a_reg = value,
+        }
+    }
+
+    /// Register pair by its index in the opcode, 3 is `SP`.
+    fn get_pair(&self, p: u8) -> u16 {
+        match p & 3 {
+            0 => self.get_bc_pair(),
+            1 => self.get_de_pair(),
+            2 => self.get_hl_pair(),
+            _ => self.sp,
+        }
+    }
+
+    fn set_pair(&mut self, p: u8, value: u16) {
+        match p & 3 {
+            0 => self.set_bc_pair(value),
+            1 => self.set_de_pair(value),
+            2 => self.set_hl_pair(value),
+            _ => self.sp = value,
+        }
+    }
+
+    /// Arithmetic or logic operation by its index in the opcode.
+    fn op_alu(&mut self, n: u8, value: u8) {
+        let (a, value) = (self.a_reg, value);
+        match n & 7 {
+            0 => self.a_reg = self.op_add(a, value, false),
+            1 => self.a_reg = self.op_add(a, value, self.flag(C_FLAG)),
+            2 => self.a_reg = self.op_sub(a, value, false),
+            3 => self.a_reg = self.op_sub(a, value, self.flag(C_FLAG)),
+            4 => self.op_ana(value),
+            5 => self.op_xra(value),
+            6 => self.op_ora(value),
+            _ => self.op_cmp(value),
+        }
+    }
+
+    fn op_jr(&mut self, offset: u8) {
+        self.pc = self.pc.wrapping_add(offset as i8 as u16);
+    }
+
+    fn op_daa_z80(&mut self) {
+        let a = self.a_reg;
+        let mut adj = 0;
+        let mut carry = self.flag(C_FLAG);
+
+        if self.flag(H_FLAG) || a & 0x0f > 9 {
+            adj |= 0x06;
+        }
+
+        if carry || a > 0x99 {
+            adj |= 0x60;
+            carry = true;
+        }
+
+        let res = if self.n_flag {
+            self.set_flag(H_FLAG, self.flag(H_FLAG) && a & 0x0f < 6);
+            a.wrapping_sub(adj)
+        } else {
+            self.set_flag(H_FLAG, a & 0x0f > 9);
+            a.wrapping_add(adj)
+        };
+
+        self.a_reg = res;
+        self.set_flag(C_FLAG, carry);
+        self.set_flags(res);
+    }
+
+    /// `ADC HL` and `SBC HL`.
+    fn op_adc_hl(&mut self, value: u16, sub: bool) {
+        let hl = self.get_hl_pair();
+        let (value, carry) = if sub {
+            (!value, !self.flag(C_FLAG))
+        } else {
+            (value, self.flag(C_FLAG))
+        };
+
+        let res = hl as u32 + value as u32 + carry as u32;
+        let half = (hl & 0x0fff) as u32 + (value & 0x0fff) as u32 + carry as u32 > 0x0fff;
+        let word = res as u16;
+
+        self.set_flag(C_FLAG, (res > 0xffff) != sub);
+        self.set_flag(H_FLAG, half != sub);
+        self.set_flag(P_FLAG, (hl ^ word) & (value ^ word) & 0x8000 != 0);
+        self.set_flag(S_FLAG, word & 0x8000 != 0);
+        self.set_flag(Z_FLAG, word == 0);
+        self.n_flag = sub;
+        self.set_hl_pair(word);
+    }
+
+    /// Rotations and shifts of the CB prefix.
+    fn op_rot(&mut self, n: u8, value: u8) -> u8 {
+        let c = self.flag(C_FLAG) as u8;
+        let (res, carry) = match n & 7 {
+            0 => (value.rotate_left(1), value >> 7),
+            1 => (value.rotate_right(1), value & 1),
+            2 => (value << 1 | c, value >> 7),
+            3 => (value >> 1 | c << 7, value & 1),
+            4 => (value << 1, value >> 7),
+            5 => (value >> 1 | value & 0x80, value & 1),
+            6 => (value << 1 | 1, value >> 7),
+            _ => (value >> 1, value & 1),
+        };
+
+        self.set_flag(C_FLAG, carry != 0);
+        self.set_flag(H_FLAG, false);
+        self.n_flag = false;
+        self.set_flags(res);
+        res
+    }
+
+    fn op_bit(&mut self, bit: u8, value: u8) {
+        let set = value & (1 << bit) != 0;
+        self.set_flag(Z_FLAG, !set);
+        self.set_flag(P_FLAG, !set);
+        self.set_flag(S_FLAG, bit == 7 && set);
+        self.set_flag(H_FLAG, true);
+        self.n_flag = false;
+    }
+
+    /// Block transfer, compare, input and output instructions.
+    fn op_block(&mut self, op: u8) {
+        let step = if op & 0x08 != 0 { 0xffff } else { 1 };
+        let hl = self.get_hl_pair();
+        self.set_hl_pair(hl.wrapping_add(step));
+
+        let again = match op & 3 {
+            0 => {
+                let de = self.get_de_pair();
+                let bc = self.get_bc_pair().wrapping_sub(1);
+                self.write_byte(de, self.read_byte(hl));
+                self.set_de_pair(de.wrapping_add(step));
+                self.set_bc_pair(bc);
+
+                self.set_flag(H_FLAG, false);
+                self.n_flag = false;
+                self.set_flag(P_FLAG, bc != 0);
+                bc != 0
+            }
+            1 => {
+                let carry = self.flag(C_FLAG);
+                let value = self.read_byte(hl);
+                let res = self.op_sub(self.a_reg, value, false);
+                let bc = self.get_bc_pair().wrapping_sub(1);
+                self.set_bc_pair(bc);
+
+                self.set_flag(C_FLAG, carry);
+                self.set_flag(P_FLAG, bc != 0);
+                bc != 0 && res != 0
+            }
+            2 => {
+                let port = self.c_reg;
+                let value = self.io().in_port(port);
+                self.write_byte(hl, value);
+                self.b_reg = self.b_reg.wrapping_sub(1);
+
+                self.set_flag(Z_FLAG, self.b_reg == 0);
+                self.n_flag = true;
+                !self.flag(Z_FLAG)
+            }
+            _ => {
+                let value = self.read_byte(hl);
+                self.b_reg = self.b_reg.wrapping_sub(1);
+                let port = self.c_reg;
+                self.io().out_port(port, value);
+
+                self.set_flag(Z_FLAG, self.b_reg == 0);
+                self.n_flag = true;
+                !self.flag(Z_FLAG)
+            }
+        };
+
+        self.cycles += 12;
+        if op & 0x10 != 0 && again {
+            self.pc = self.pc.wrapping_sub(2);
+            self.cycles += 5;
+        }
+    }
+
+    /// Runs the Z80 instructions that differ from the 8080, returns false for
+    /// the common ones.
+    pub(super) fn exec_z80(&mut self, op: u8) -> bool {
+        self.refresh();
+
+        match op {
+            0xcb => {
+                let op = self.fetch_opcode();
+                self.exec_cb(op, None);
+            }
+            0xdd => self.exec_index(false),
+            0xed => {
+                let op = self.fetch_opcode();
+                self.exec_ed(op);
+            }
+            0xfd => self.exec_index(true),
+            _ => return self.exec_unprefixed(op),
+        }
+        true
+    }
+
+    fn exec_unprefixed(&mut self, op: u8) -> bool {
+        match op {
+            // EX AF,AF'
+            0x08 => {
+                let af = self.get_psw();
+                let alt = core::mem::replace(&mut self.z80.af, af);
+                self.set_psw(alt);
+            }
+            // DJNZ
+            0x10 => {
+                let offset = self.fetch_next_byte();
+                self.b_reg = self.b_reg.wrapping_sub(1);
+                if self.b_reg != 0 {
+                    self.op_jr(offset);
+                    self.cycles += 5;
+                }
+            }
+            // JR
+            0x18 => {
+                let offset = self.fetch_next_byte();
+                self.op_jr(offset);
+            }
+            // JR NZ, JR Z, JR NC, JR C
+            0x20 | 0x28 | 0x30 | 0x38 => {
+                let offset = self.fetch_next_byte();
+                let cond = match op {
+                    0x20 => !self.flag(Z_FLAG),
+                    0x28 => self.flag(Z_FLAG),
+                    0x30 => !self.flag(C_FLAG),
+                    _ => self.flag(C_FLAG),
+                };
+
+                if cond {
+                    self.op_jr(offset);
+                    self.cycles += 5;
+                }
+            }
+            0x07 | 0x0f | 0x17 | 0x1f | 0x37 => {
+                self.exec_8080(op);
+                self.set_flag(H_FLAG, false);
+                self.n_flag = false;
+            }
+            0x27 => self.op_daa_z80(),
+            0x2f => {
+                self.exec_8080(op);
+                self.set_flag(H_FLAG, true);
+                self.n_flag = true;
+            }
+            0x3f => {
+                self.set_flag(H_FLAG, self.flag(C_FLAG));
+                self.set_flag(C_FLAG, !self.flag(C_FLAG));
+                self.n_flag = false;
+            }
+            // EXX
+            0xd9 => {
+                let (bc, de, hl) = (self.get_bc_pair(), self.get_de_pair(), self.get_hl_pair());
+                self.set_bc_pair(self.z80.bc);
+                self.set_de_pair(self.z80.de);
+                self.set_hl_pair(self.z80.hl);
+                self.z80.bc = bc;
+                self.z80.de = de;
+                self.z80.hl = hl;
+            }
+            0xf3 => {
+                self.int.filp_flop = false;
+                self.z80.iff2 = false;
+            }
+            0xfb => {
+                self.int.filp_flop = true;
+                self.z80.iff2 = true;
+                self.int.delay = 1;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// CB prefixed instructions, on `(IX+d)` or `(IY+d)` when `addr` is given.
+    fn exec_cb(&mut self, op: u8, addr: Option<u16>) {
+        let (r, n) = (op & 7, (op >> 3) & 7);
+        let value = match addr {
+            Some(addr) => self.read_byte(addr),
+            None => self.get_reg(r),
+        };
+
+        self.cycles += match (addr, r, op >> 6) {
+            (Some(_), _, 1) => 16,
+            (Some(_), _, _) => 19,
+            (None, 6, 1) => 8,
+            (None, 6, _) => 11,
+            _ => 4,
+        };
+
+        let res = match op >> 6 {
+            0 => self.op_rot(n, value),
+            1 => return self.op_bit(n, value),
+            2 => value & !(1 << n),
+            _ => value | (1 << n),
+        };
+
+        match addr {
+            Some(addr) => {
+                self.write_byte(addr, res);
+                // Undocumented copy of the result
+                if r != 6 {
+                    self.set_reg(r, res);
+                }
+            }
+            None => self.set_reg(r, res),
+        }
+    }
+
+    /// DD and FD prefixed instructions, HL stands for IX or IY.
+    fn exec_index(&mut self, iy: bool) {
+        // A prefix cancels the previous one
+        if matches!(self.read_byte(self.pc), 0xdd | 0xed | 0xfd) {
+            return;
+        }
+
+        let op = self.fetch_opcode();
+        let index = if iy { self.z80.iy } else { self.z80.ix };
+
+        if op == 0xcb || uses_memory(op) {
+            let offset = self.fetch_next_byte() as i8;
+            let addr = index.wrapping_add(offset as u16);
+
+            if op == 0xcb {
+                let op = self.fetch_next_byte();
+                return self.exec_cb(op, Some(addr));
+            }
+
+            self.cycles += CYCLES[op as usize] + if op == 0x36 { 5 } else { 8 };
+            match op {
+                0x34 => {
+                    let res = self.op_inr(self.read_byte(addr));
+                    self.write_byte(addr, res);
+                }
+                0x35 => {
+                    let res = self.op_dcr(self.read_byte(addr));
+                    self.write_byte(addr, res);
+                }
+                0x36 => {
+                    let value = self.fetch_next_byte();
+                    self.write_byte(addr, value);
+                }
+                0x70..=0x77 => self.write_byte(addr, self.get_reg(op)),
+                0x40..=0x7f => self.set_reg(op >> 3, self.read_byte(addr)),
+                _ => self.op_alu(op >> 3, self.read_byte(addr)),
+            }
+            return;
+        }
+
+        self.cycles += CYCLES[op as usize];
+        // EX DE,HL and EXX always use HL
+        let swap = op != 0xeb && op != 0xd9;
+
+        let hl = self.get_hl_pair();
+        if swap {
+            self.set_hl_pair(index);
+        }
+
+        if !self.exec_unprefixed(op) {
+            self.exec_8080(op);
+        }
+
+        if swap {
+            let index = self.get_hl_pair();
+            self.set_hl_pair(hl);
+            if iy {
+                self.z80.iy = index;
+            } else {
+                self.z80.ix = index;
+            }
+        }
+    }
+
+    fn exec_ed(&mut self, op: u8) {
+        match op {
+            // IN r,(C)
+            0x40 | 0x48 | 0x50 | 0x58 | 0x60 | 0x68 | 0x70 | 0x78 => {
+                let port = self.c_reg;
+                let value = self.io().in_port(port);
+                self.set_flag(H_FLAG, false);
+                self.n_flag = false;
+                self.set_flags(value);
+
+                if op != 0x70 {
+                    self.set_reg(op >> 3, value);
+                }
+                self.cycles += 8;
+            }
+            // OUT (C),r
+            0x41 | 0x49 | 0x51 | 0x59 | 0x61 | 0x69 | 0x71 | 0x79 => {
+                let value = if op == 0x71 { 0 } else { self.get_reg(op >> 3) };
+                let port = self.c_reg;
+                self.io().out_port(port, value);
+                self.cycles += 8;
+            }
+            // SBC HL,rr and ADC HL,rr
+            0x42 | 0x52 | 0x62 | 0x72 | 0x4a | 0x5a | 0x6a | 0x7a => {
+                let value = self.get_pair(op >> 4);
+                self.op_adc_hl(value, op & 0x08 == 0);
+                self.cycles += 11;
+            }
+            // LD (nn),rr
+            0x43 | 0x53 | 0x63 | 0x73 => {
+                let addr = self.fetch_next_word();
+                self.write_word(addr, self.get_pair(op >> 4));
+                self.cycles += 16;
+            }
+            // LD rr,(nn)
+            0x4b | 0x5b | 0x6b | 0x7b => {
+                let addr = self.fetch_next_word();
+                let value = self.read_word(addr);
+                self.set_pair(op >> 4, value);
+                self.cycles += 16;
+            }
+            // NEG
+            0x44 | 0x4c | 0x54 | 0x5c | 0x64 | 0x6c | 0x74 | 0x7c => {
+                self.a_reg = self.op_sub(0, self.a_reg, false);
+                self.cycles += 4;
+            }
+            // RETN and RETI
+            0x45 | 0x4d | 0x55 | 0x5d | 0x65 | 0x6d | 0x75 | 0x7d => {
+                self.int.filp_flop = self.z80.iff2;
+                self.op_ret();
+                self.cycles += 10;
+            }
+            // IM 0, IM 1, IM 2
+            0x46 | 0x4e | 0x66 | 0x6e | 0x56 | 0x76 | 0x5e | 0x7e => {
+                self.z80.mode = match op & 0x18 {
+                    0x10 => 1,
+                    0x18 => 2,
+                    _ => 0,
+                };
+                self.cycles += 4;
+            }
+            // LD I,A
+            0x47 => {
+                self.z80.i = self.a_reg;
+                self.cycles += 5;
+            }
+            // LD R,A
+            0x4f => {
+                self.z80.r = self.a_reg;
+                self.cycles += 5;
+            }
+            // LD A,I and LD A,R
+            0x57 | 0x5f => {
+                let value = if op == 0x57 { self.z80.i } else { self.z80.r };
+                self.a_reg = value;
+                self.set_flags(value);
+                self.set_flag(H_FLAG, false);
+                self.n_flag = false;
+                self.set_flag(P_FLAG, self.z80.iff2);
+                self.cycles += 5;
+            }
+            // RRD and RLD
+            0x67 | 0x6f => {
+                let addr = self.get_hl_pair();
+                let (a, value) = (self.a_reg, self.read_byte(addr));
+                let (a, value) = if op == 0x67 {
+                    (a & 0xf0 | value & 0x0f, a << 4 | value >> 4)
+                } else {
+                    (a & 0xf0 | value >> 4, value << 4 | a & 0x0f)
+                };
+
+                self.write_byte(addr, value);
+                self.a_reg = a;
+                self.set_flags(a);
+                self.set_flag(H_FLAG, false);
+                self.n_flag = false;
+                self.cycles += 14;
+            }
+            0xa0..=0xa3 | 0xa8..=0xab | 0xb0..=0xb3 | 0xb8..=0xbb => self.op_block(op),
+            _ => self.cycles += 4,
+        }
+    }
+
+    /// Accepts a maskable interrupt, in mode 2 the low byte of `vector` is the
+    /// byte read from the data bus.
+    pub(super) fn interrupt_z80(&mut self, vector: u16) {
+        self.z80.iff2 = false;
+        self.refresh();
+
+        match self.z80.mode {
+            0 => {
+                self.op_call(vector);
+                self.cycles += 13;
+            }
+            1 => {
+                self.op_call(0x38);
+                self.cycles += 13;
+            }
+            _ => {
+                let table = (self.z80.i as u16) << 8 | (vector & 0xff);
+                let addr = self.read_word(table);
+                self.op_call(addr);
+                self.cycles += 19;
+            }
+        }
+    }
+
+    pub(super) fn nmi_z80(&mut self) {
+        self.z80.nmi = false;
+        self.z80.iff2 = self.int.filp_flop;
+        self.int.filp_flop = false;
+        self.halt = false;
+        self.refresh();
+
+        self.op_call(0x66);
+        self.cycles += 11;
+    }
+}
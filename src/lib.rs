@@ -1,4 +1,14 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
 pub mod asm;
+#[cfg(feature = "std")]
 pub mod dis;
 pub mod emu;
 pub mod op;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "wasm")]
+pub mod wasm;
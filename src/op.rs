@@ -1,7 +1,7 @@
 //! `src/op.rs` is automatically generated by `./utils/gen.py` from `utils/opcodes.txt`.
 //! don't modify this file directly, instead run `python3 ./utils/gen.py`.
 
-use std::{fmt, mem};
+use core::{fmt, mem};
 
 #[allow(non_camel_case_types)]
 #[repr(u8)]
@@ -512,7 +512,7 @@ impl From<u8> for RawOpcode {
             0xd9 => RawOpcode::RET,
             0xdd | 0xed | 0xfd => RawOpcode::CALL,
             0xcb => RawOpcode::JMP,
-            _ => unsafe { mem::transmute(t) },
+            _ => unsafe { mem::transmute::<u8, RawOpcode>(t) },
         }
     }
 }
@@ -523,9 +523,9 @@ impl From<&u8> for RawOpcode {
     }
 }
 
-impl Into<u8> for RawOpcode {
-    fn into(self) -> u8 {
-        unsafe { mem::transmute(self) }
+impl From<RawOpcode> for u8 {
+    fn from(t: RawOpcode) -> u8 {
+        t as u8
     }
 }
 
@@ -781,6 +781,8 @@ pub enum Opcode {
     Cm(u16),
     Cpi(u8),
     Rst7,
+    /// Raw data byte, from the `db`, `dw` and `ds` directives.
+    Db(u8),
 }
 
 impl Opcode {
@@ -1030,6 +1032,7 @@ impl Opcode {
             Opcode::Cm(_) => 3,
             Opcode::Cpi(_) => 2,
             Opcode::Rst7 => 1,
+            Opcode::Db(_) => 1,
         }
     }
 }
@@ -0,0 +1,322 @@
+//! Python bindings, built as an extension module with the `python` feature.
+
+use crate::asm::{self, lexer};
+use crate::dis;
+use crate::emu::{self, Cpu, FlatMemory, Memory, Registers};
+use crate::op::Opcode;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::{cell::RefCell, rc::Rc};
+
+/// Steps `run` takes between checks for signals, so Ctrl-C stops it.
+const SIGNAL_STEPS: usize = 10_000;
+
+/// Opcode with its operands.
+#[pyclass(name = "Opcode", frozen, eq, from_py_object)]
+#[derive(Clone, Copy, PartialEq)]
+struct PyOpcode(Opcode);
+
+#[pymethods]
+impl PyOpcode {
+    fn size(&self) -> usize {
+        self.0.size()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+/// Tokenizes `src` into opcodes, raises `ValueError` with the diagnostics
+/// one per line.
+#[pyfunction]
+fn tokenize(src: &str) -> PyResult<Vec<PyOpcode>> {
+    let ops = lexer::tokenize(src).map_err(|diags| {
+        let msg = diags.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+        PyValueError::new_err(msg.join("\n"))
+    })?;
+    Ok(ops.into_iter().map(PyOpcode).collect())
+}
+
+#[pyfunction]
+fn codegen<'py>(py: Python<'py>, ops: Vec<PyOpcode>) -> Bound<'py, PyBytes> {
+    let ops = ops.into_iter().map(|op| op.0).collect::<Vec<_>>();
+    PyBytes::new(py, &asm::codegen(&ops))
+}
+
+#[pyfunction]
+fn disassemble(bin: &[u8]) -> PyResult<Vec<PyOpcode>> {
+    let ops = dis::disassemble(bin).map_err(|err| PyValueError::new_err(err.to_string()))?;
+    Ok(ops.into_iter().map(PyOpcode).collect())
+}
+
+/// First error raised by a Python callback during a step.
+type Error = Rc<RefCell<Option<PyErr>>>;
+
+/// Memory implemented by a Python object with `read_byte`, `write_byte`,
+/// `in_port` and `out_port` methods, and optionally `tick` and `interrupt`.
+struct PyMemory {
+    obj: Py<PyAny>,
+    err: Error,
+}
+
+impl PyMemory {
+    fn call<T>(&self, default: T, f: impl FnOnce(Python, &Bound<PyAny>) -> PyResult<T>) -> T {
+        Python::attach(|py| match f(py, self.obj.bind(py)) {
+            Ok(value) => value,
+            Err(err) => {
+                self.err.borrow_mut().get_or_insert(err);
+                default
+            }
+        })
+    }
+}
+
+impl Memory for PyMemory {
+    fn read_byte(&self, addr: u16) -> u8 {
+        self.call(0xff, |_, obj| {
+            obj.call_method1("read_byte", (addr,))?.extract()
+        })
+    }
+
+    fn read_word(&self, addr: u16) -> u16 {
+        (self.read_byte(addr.wrapping_add(1)) as u16) << 8 | self.read_byte(addr) as u16
+    }
+
+    fn write_byte(&mut self, addr: u16, byte: u8) {
+        self.call((), |_, obj| {
+            obj.call_method1("write_byte", (addr, byte)).map(drop)
+        })
+    }
+
+    fn write_word(&mut self, addr: u16, word: u16) {
+        self.write_byte(addr, word as u8);
+        self.write_byte(addr.wrapping_add(1), (word >> 8) as u8);
+    }
+
+    fn in_port(&mut self, port: u8) -> u8 {
+        self.call(0xff, |_, obj| {
+            obj.call_method1("in_port", (port,))?.extract()
+        })
+    }
+
+    fn out_port(&mut self, port: u8, byte: u8) {
+        self.call((), |_, obj| {
+            obj.call_method1("out_port", (port, byte)).map(drop)
+        })
+    }
+
+    fn tick(&mut self, cycles: usize) {
+        self.call((), |py, obj| {
+            if obj.hasattr(pyo3::intern!(py, "tick"))? {
+                obj.call_method1("tick", (cycles,))?;
+            }
+            Ok(())
+        })
+    }
+
+    fn interrupt(&mut self) -> Option<u16> {
+        self.call(None, |py, obj| {
+            if obj.hasattr(pyo3::intern!(py, "interrupt"))? {
+                obj.call_method0("interrupt")?.extract()
+            } else {
+                Ok(None)
+            }
+        })
+    }
+}
+
+/// Ports of the flat memory, forwarded to the `in_port` and `out_port` callables.
+struct PyPorts {
+    input: Option<Py<PyAny>>,
+    output: Option<Py<PyAny>>,
+    err: Error,
+}
+
+impl Memory for PyPorts {
+    fn read_byte(&self, _addr: u16) -> u8 {
+        0
+    }
+
+    fn read_word(&self, _addr: u16) -> u16 {
+        0
+    }
+
+    fn write_byte(&mut self, _addr: u16, _byte: u8) {}
+
+    fn write_word(&mut self, _addr: u16, _word: u16) {}
+
+    fn in_port(&mut self, port: u8) -> u8 {
+        let Some(f) = &self.input else {
+            return 0xff;
+        };
+
+        Python::attach(
+            |py| match f.call1(py, (port,)).and_then(|v| v.extract(py)) {
+                Ok(byte) => byte,
+                Err(err) => {
+                    self.err.borrow_mut().get_or_insert(err);
+                    0xff
+                }
+            },
+        )
+    }
+
+    fn out_port(&mut self, port: u8, byte: u8) {
+        if let Some(f) = &self.output {
+            Python::attach(|py| {
+                if let Err(err) = f.call1(py, (port, byte)) {
+                    self.err.borrow_mut().get_or_insert(err);
+                }
+            })
+        }
+    }
+}
+
+/// Emulator running on a Python `memory` object, or on 64K of flat RAM with
+/// the ports going to the `in_port(port)` and `out_port(port, byte)` callables.
+#[pyclass(unsendable)]
+struct Emulator {
+    emu: emu::Emulator,
+    err: Error,
+}
+
+impl Emulator {
+    /// Raises the error of a callback, if any.
+    fn check(&self) -> PyResult<()> {
+        match self.err.borrow_mut().take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+#[pymethods]
+impl Emulator {
+    #[new]
+    #[pyo3(signature = (memory = None, cpu = "8080", in_port = None, out_port = None))]
+    fn new(
+        memory: Option<Py<PyAny>>,
+        cpu: &str,
+        in_port: Option<Py<PyAny>>,
+        out_port: Option<Py<PyAny>>,
+    ) -> PyResult<Self> {
+        let cpu = match cpu {
+            "8080" => Cpu::I8080,
+            "8085" => Cpu::I8085,
+            "z80" => Cpu::Z80,
+            _ => return Err(PyValueError::new_err(format!("Unknown cpu {}", cpu))),
+        };
+
+        let err = Error::default();
+        let emu = match memory {
+            Some(obj) => {
+                let mem = PyMemory {
+                    obj,
+                    err: err.clone(),
+                };
+                emu::Emulator::with_cpu(Box::new(mem), cpu)
+            }
+            None => {
+                let ports = PyPorts {
+                    input: in_port,
+                    output: out_port,
+                    err: err.clone(),
+                };
+                emu::Emulator::with_flat(FlatMemory::new(&[], Box::new(ports)), cpu)
+            }
+        };
+        Ok(Emulator { emu, err })
+    }
+
+    /// Copies `bin` into memory at `addr`.
+    #[pyo3(signature = (bin, addr = 0))]
+    fn load(&mut self, bin: &[u8], addr: u16) -> PyResult<()> {
+        self.write(addr, bin)
+    }
+
+    /// Executes one instruction, returns the cycles it took.
+    fn step(&mut self) -> PyResult<usize> {
+        let cycles = self.emu.step();
+        self.check()?;
+        Ok(cycles)
+    }
+
+    /// Runs until halted or at least `budget` cycles have passed, returns the
+    /// cycles run.
+    #[pyo3(signature = (budget = None))]
+    fn run(&mut self, py: Python, budget: Option<usize>) -> PyResult<usize> {
+        let start = self.emu.cycles();
+        let mut steps = 0;
+        while !self.emu.halted() && budget.is_none_or(|b| self.emu.cycles() - start < b) {
+            self.emu.step();
+            self.check()?;
+
+            steps += 1;
+            if steps % SIGNAL_STEPS == 0 {
+                py.check_signals()?;
+            }
+        }
+        Ok(self.emu.cycles() - start)
+    }
+
+    /// Requests an interrupt that calls `vector`.
+    fn interrupt(&mut self, vector: u16) {
+        self.emu.interrupt(vector);
+    }
+
+    fn registers(&self) -> Registers {
+        self.emu.registers()
+    }
+
+    /// Reads `len` bytes of memory from `addr`.
+    fn read<'py>(&self, py: Python<'py>, addr: u16, len: usize) -> PyResult<Bound<'py, PyBytes>> {
+        let mem = self.emu.memory();
+        let bytes = (0..len)
+            .map(|i| mem.read_byte(addr.wrapping_add(i as u16)))
+            .collect::<Vec<_>>();
+        self.check()?;
+        Ok(PyBytes::new(py, &bytes))
+    }
+
+    /// Writes `bytes` into memory at `addr`.
+    fn write(&mut self, addr: u16, bytes: &[u8]) -> PyResult<()> {
+        let mem = self.emu.memory_mut();
+        for (i, &byte) in bytes.iter().enumerate() {
+            mem.write_byte(addr.wrapping_add(i as u16), byte);
+        }
+        self.check()
+    }
+
+    #[getter]
+    fn cycles(&self) -> usize {
+        self.emu.cycles()
+    }
+
+    #[getter]
+    fn get_pc(&self) -> u16 {
+        self.emu.pc()
+    }
+
+    #[setter]
+    fn set_pc(&mut self, pc: u16) {
+        self.emu.set_pc(pc);
+    }
+
+    #[getter]
+    fn halted(&self) -> bool {
+        self.emu.halted()
+    }
+}
+
+#[pymodule]
+pub fn intel_8080_kit(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(tokenize, m)?)?;
+    m.add_function(wrap_pyfunction!(codegen, m)?)?;
+    m.add_function(wrap_pyfunction!(disassemble, m)?)?;
+    m.add_class::<PyOpcode>()?;
+    m.add_class::<Emulator>()?;
+    m.add_class::<Registers>()?;
+    Ok(())
+}
@@ -0,0 +1,200 @@
+//! JavaScript bindings, built for `wasm32-unknown-unknown` with the `wasm` feature.
+
+use crate::asm::{codegen, lexer::tokenize};
+use crate::dis;
+use crate::emu::{Cpu, Emulator, FlatMemory, Memory, Registers};
+use js_sys::Function;
+use std::{cell::RefCell, fmt::Write, rc::Rc};
+use wasm_bindgen::prelude::*;
+
+/// Assembles `src`, throws the diagnostics one per line.
+#[wasm_bindgen]
+pub fn assemble(src: &str) -> Result<Vec<u8>, JsError> {
+    let ops = tokenize(src).map_err(|diags| {
+        let msg = diags.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+        JsError::new(&msg.join("\n"))
+    })?;
+    Ok(codegen(&ops))
+}
+
+/// Disassembles `bin` into a listing, one instruction per line.
+#[wasm_bindgen]
+pub fn disassemble(bin: &[u8]) -> Result<String, JsError> {
+    let ops = dis::disassemble(bin).map_err(|err| JsError::new(&err.to_string()))?;
+
+    let mut out = String::new();
+    let mut pc = 0;
+    for op in ops {
+        writeln!(out, "{:04}{:<6}{:?}", pc, "", op).unwrap();
+        pc += op.size();
+    }
+    Ok(out)
+}
+
+/// Port hooks set from JavaScript.
+#[derive(Default)]
+struct Hooks {
+    input: Option<Function>,
+    output: Option<Function>,
+    /// First exception thrown by a hook during a step
+    error: Option<JsValue>,
+}
+
+/// Ports of the flat memory, forwarded to the hooks.
+struct Ports(Rc<RefCell<Hooks>>);
+
+impl Memory for Ports {
+    fn read_byte(&self, _addr: u16) -> u8 {
+        0
+    }
+
+    fn read_word(&self, _addr: u16) -> u16 {
+        0
+    }
+
+    fn write_byte(&mut self, _addr: u16, _byte: u8) {}
+
+    fn write_word(&mut self, _addr: u16, _word: u16) {}
+
+    fn in_port(&mut self, port: u8) -> u8 {
+        let mut hooks = self.0.borrow_mut();
+        let value = match &hooks.input {
+            Some(f) => f.call1(&JsValue::NULL, &port.into()),
+            None => return 0xff,
+        };
+        match value {
+            Ok(value) => value.as_f64().map_or(0xff, |value| value as u8),
+            Err(err) => {
+                hooks.error.get_or_insert(err);
+                0xff
+            }
+        }
+    }
+
+    fn out_port(&mut self, port: u8, byte: u8) {
+        let mut hooks = self.0.borrow_mut();
+        if let Some(f) = &hooks.output {
+            if let Err(err) = f.call2(&JsValue::NULL, &port.into(), &byte.into()) {
+                hooks.error.get_or_insert(err);
+            }
+        }
+    }
+}
+
+/// Emulator with 64K of flat RAM and ports hooked from JavaScript.
+#[wasm_bindgen(js_name = Emulator)]
+pub struct WasmEmulator {
+    emu: Emulator,
+    hooks: Rc<RefCell<Hooks>>,
+}
+
+impl WasmEmulator {
+    /// Throws the exception of a hook, if any.
+    fn check(&self) -> Result<(), JsValue> {
+        match self.hooks.borrow_mut().error.take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+#[wasm_bindgen(js_class = Emulator)]
+impl WasmEmulator {
+    /// Emulator for `cpu`, `"8080"`, `"8085"` or `"z80"`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(cpu: &str) -> Result<WasmEmulator, JsError> {
+        let cpu = match cpu {
+            "8080" => Cpu::I8080,
+            "8085" => Cpu::I8085,
+            "z80" => Cpu::Z80,
+            _ => return Err(JsError::new(&format!("Unknown cpu {}", cpu))),
+        };
+
+        let hooks = Rc::new(RefCell::new(Hooks::default()));
+        let mem = FlatMemory::new(&[], Box::new(Ports(hooks.clone())));
+        Ok(WasmEmulator {
+            emu: Emulator::with_flat(mem, cpu),
+            hooks,
+        })
+    }
+
+    /// Copies `bin` into memory at `addr`.
+    pub fn load(&mut self, bin: &[u8], addr: u16) {
+        self.write(addr, bin);
+    }
+
+    /// Executes one instruction, returns the cycles it took.
+    pub fn step(&mut self) -> Result<usize, JsValue> {
+        let cycles = self.emu.step();
+        self.check()?;
+        Ok(cycles)
+    }
+
+    /// Runs until halted or at least `budget` cycles have passed, returns the
+    /// cycles run.
+    pub fn run(&mut self, budget: usize) -> Result<usize, JsValue> {
+        let start = self.emu.cycles();
+        while !self.emu.halted() && self.emu.cycles() - start < budget {
+            self.emu.step();
+            self.check()?;
+        }
+        Ok(self.emu.cycles() - start)
+    }
+
+    /// Requests an interrupt that calls `vector`.
+    pub fn interrupt(&mut self, vector: u16) {
+        self.emu.interrupt(vector);
+    }
+
+    pub fn registers(&self) -> Registers {
+        self.emu.registers()
+    }
+
+    /// Reads `len` bytes of memory from `addr`.
+    pub fn read(&self, addr: u16, len: usize) -> Vec<u8> {
+        let mem = self.emu.memory();
+        (0..len)
+            .map(|i| mem.read_byte(addr.wrapping_add(i as u16)))
+            .collect()
+    }
+
+    /// Writes `bytes` into memory at `addr`.
+    pub fn write(&mut self, addr: u16, bytes: &[u8]) {
+        let mem = self.emu.memory_mut();
+        for (i, &byte) in bytes.iter().enumerate() {
+            mem.write_byte(addr.wrapping_add(i as u16), byte);
+        }
+    }
+
+    /// Sets the function called as `f(port)` by `IN`, returning the byte read.
+    #[wasm_bindgen(js_name = onInput)]
+    pub fn on_input(&mut self, f: Option<Function>) {
+        self.hooks.borrow_mut().input = f;
+    }
+
+    /// Sets the function called as `f(port, byte)` by `OUT`.
+    #[wasm_bindgen(js_name = onOutput)]
+    pub fn on_output(&mut self, f: Option<Function>) {
+        self.hooks.borrow_mut().output = f;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn cycles(&self) -> usize {
+        self.emu.cycles()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn pc(&self) -> u16 {
+        self.emu.pc()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_pc(&mut self, pc: u16) {
+        self.emu.set_pc(pc);
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn halted(&self) -> bool {
+        self.emu.halted()
+    }
+}
@@ -0,0 +1,182 @@
+use intel_8080_kit::emu::{
+    audio::{read_wav, write_wav, Mixer, Sound, SoundEvent, Voice},
+    bus::Bus,
+    dev::{shared, PortBus},
+    machine::{Description, Machine, MachineError},
+    Emulator,
+};
+use std::path::Path;
+
+#[test]
+fn timestamps() {
+    let mut bus = Bus::new();
+    bus.map_ram(0, 0x100);
+
+    #[rustfmt::skip]
+    bus.load(0, &[
+        0x3e, 0x01, 0xd3, 0x03,
+        0x00, 0x00,
+        0x3e, 0x00, 0xd3, 0x03,
+        0xd3, 0x05,
+        0x76,
+    ]);
+
+    let sound = shared(Sound::new(3, 1));
+    let sound5 = shared(Sound::new(5, 1));
+    let mut bus = PortBus::new(Box::new(bus));
+    bus.map(3, sound.clone());
+    bus.map(5, sound5.clone());
+
+    let mut emu = Emulator::new(Box::new(bus));
+    emu.run();
+
+    let sound = sound.borrow();
+    assert_eq!(
+        sound.events(),
+        [
+            SoundEvent {
+                cycle: 7,
+                port: 3,
+                value: 1
+            },
+            SoundEvent {
+                cycle: 7 + 10 + 8 + 7,
+                port: 3,
+                value: 0
+            },
+        ]
+    );
+    assert_eq!(sound5.borrow().events()[0].cycle, 7 + 10 + 8 + 7 + 10);
+    assert_eq!(sound.latch(3), Some(0));
+    assert_eq!(sound.latch(4), None);
+    assert_eq!(sound.latch(2), None);
+}
+
+#[test]
+fn mixing() {
+    let events = [
+        SoundEvent {
+            cycle: 100,
+            port: 3,
+            value: 0x01,
+        },
+        SoundEvent {
+            cycle: 400,
+            port: 3,
+            value: 0x03,
+        },
+        SoundEvent {
+            cycle: 600,
+            port: 3,
+            value: 0x00,
+        },
+    ];
+
+    // One sample every 100 cycles
+    let mut mixer = Mixer::new(1000, 10);
+    mixer.add(Voice::Trigger {
+        port: 3,
+        bit: 0,
+        sample: Vec::new(),
+        looped: false,
+    });
+    mixer.add(Voice::Trigger {
+        port: 3,
+        bit: 1,
+        sample: vec![1, 2],
+        looped: true,
+    });
+
+    let samples = mixer.render(&events, 1000);
+    let gate = 0x2000;
+    assert_eq!(
+        samples,
+        [0, gate, gate, gate, gate + 1, gate + 2, 1, 2, 0, 0]
+    );
+
+    let mut mixer = Mixer::new(1000, 10);
+    mixer.add(Voice::Level {
+        port: 3,
+        mask: 0x03,
+    });
+    assert_eq!(
+        mixer.render(&events[..2], 500),
+        [0, 0x2000 / 3, 0x2000 / 3, 0x2000 / 3, 0x2000]
+    );
+}
+
+#[test]
+fn wav_files() {
+    let mut wav = Vec::new();
+    write_wav(&mut wav, 22050, &[0, -1, 0x7fff]).unwrap();
+    assert_eq!(wav.len(), 44 + 6);
+    assert_eq!(&wav[..4], b"RIFF");
+
+    let (rate, samples) = read_wav(&wav[..]).unwrap();
+    assert_eq!(rate, 22050);
+    assert_eq!(samples, [0, -1, 0x7fff]);
+
+    assert!(read_wav(&b"RIFF\0\0\0\0WAVE"[..]).is_err());
+}
+
+#[test]
+fn audio_description() {
+    let desc = Description::from_toml(
+        r#"
+        clock = 1000
+
+        [[memory]]
+        kind = "ram"
+        start = 0
+        size = 0x100
+
+        [[device]]
+        name = "sound1"
+        kind = "sound"
+        port = 3
+
+        [audio]
+        rate = 10
+
+        [[audio.voice]]
+        kind = "trigger"
+        port = 3
+        bit = 0
+        "#,
+    )
+    .unwrap();
+
+    let mut machine = Machine::build(&desc, Path::new("tests")).unwrap();
+    let emu = machine.emulator();
+    for (i, b) in [0x3e, 0x01, 0xd3, 0x03, 0x76].iter().enumerate() {
+        emu.memory_mut().write_byte(i as u16, *b);
+    }
+    while emu.cycles() < 300 {
+        emu.step();
+    }
+
+    let events = machine.sound_events();
+    assert_eq!(events.len(), 1);
+    let samples = machine.mixer().unwrap().render(&events, 300);
+    assert_eq!(samples, [0, 0x2000, 0x2000]);
+}
+
+#[test]
+fn trigger_bit_range() {
+    let desc = Description::from_toml(
+        r#"
+        clock = 1000
+
+        [audio]
+        [[audio.voice]]
+        kind = "trigger"
+        port = 3
+        bit = 8
+        "#,
+    )
+    .unwrap();
+    assert!(matches!(
+        Machine::build(&desc, Path::new("tests")),
+        Err(MachineError::Invalid(_))
+    ));
+}
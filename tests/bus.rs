@@ -0,0 +1,107 @@
+use intel_8080_kit::emu::{
+    bus::{Bus, Mmio},
+    machine::{Description, Machine},
+    Emulator, Memory,
+};
+use std::{cell::RefCell, rc::Rc};
+
+#[test]
+fn mirror_and_holes() {
+    let mut bus = Bus::new();
+    bus.map_unmapped(0x0400, 0x0400);
+    bus.map_rom(0x0000, vec![0x11, 0x22, 0x33, 0x44]);
+    bus.map_mirror(0x1000, 0x1000, 0x0000, 4);
+    bus.map_ram(0x2000, 0x100);
+    bus.map_mirror(0x2100, 0x300, 0x2000, 0x100);
+
+    assert_eq!(bus.read_byte(0x1000), 0x11);
+    assert_eq!(bus.read_byte(0x1ffd), 0x22);
+    assert_eq!(bus.read_byte(0x0400), 0xff);
+
+    bus.write_byte(0x1001, 0x99);
+    assert_eq!(bus.read_byte(0x0001), 0x22);
+
+    bus.write_byte(0x2310, 0x5a);
+    assert_eq!(bus.read_byte(0x2010), 0x5a);
+    assert_eq!(bus.read_word(0x220f), 0x5a00);
+}
+
+#[test]
+fn mmio_callbacks() {
+    let written = Rc::new(RefCell::new(Vec::new()));
+    let log = written.clone();
+
+    let dev: Rc<RefCell<dyn Mmio>> = Rc::new(RefCell::new((
+        |offset: u16| offset as u8 ^ 0xff,
+        move |offset: u16, byte: u8| log.borrow_mut().push((offset, byte)),
+    )));
+
+    let mut bus = Bus::new();
+    bus.map_mmio(0xe000, 0x10, dev);
+
+    assert_eq!(bus.read_byte(0xe003), 0xfc);
+    bus.write_byte(0xe00f, 0x42);
+    bus.write_byte(0xe010, 0x43);
+    assert_eq!(*written.borrow(), vec![(0x0f, 0x42)]);
+}
+
+#[test]
+fn bank_switching() {
+    let mut bus = Bus::new();
+    bus.map_banked(0x8000, 0, vec![vec![0xaa; 0x100], vec![0xbb; 0x100]], true);
+    bus.map_ram(0x0000, 0x100);
+    bus.bank_port(0xfe, 0);
+
+    #[rustfmt::skip]
+    let prog = [
+        0x3e, 0x01, 0xd3, 0xfe,
+        0x3a, 0x00, 0x80,
+        0x47,
+        0x3e, 0x00, 0xd3, 0xfe,
+        0x3a, 0x00, 0x80,
+        0x76,
+    ];
+    bus.load(0, &prog);
+
+    let mut emu = Emulator::new(Box::new(bus));
+    emu.run();
+
+    let mem = emu.memory_mut();
+    assert_eq!(mem.in_port(0xfe), 0);
+    assert_eq!(mem.read_byte(0x8000), 0xaa);
+    mem.out_port(0xfe, 1);
+    assert_eq!(mem.read_byte(0x80ff), 0xbb);
+}
+
+#[test]
+fn banked_description() {
+    let desc = Description::from_toml(
+        r#"
+        [[memory]]
+        kind = "banked"
+        start = 0x4000
+        size = 0x4000
+        banks = 4
+        port = 0x10
+
+        [[memory]]
+        kind = "mirror"
+        start = 0x8000
+        size = 0x8000
+        target = 0x4000
+        period = 0x4000
+        "#,
+    )
+    .unwrap();
+
+    let mut machine = Machine::build(&desc, "tests".as_ref()).unwrap();
+    let mem = machine.emulator().memory_mut();
+
+    mem.out_port(0x10, 2);
+    mem.write_byte(0x4000, 0x77);
+    assert_eq!(mem.read_byte(0xc000), 0x77);
+
+    mem.out_port(0x10, 3);
+    assert_eq!(mem.read_byte(0x4000), 0);
+    assert_eq!(mem.in_port(0x10), 3);
+}
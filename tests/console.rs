@@ -0,0 +1,161 @@
+use intel_8080_kit::emu::{
+    dev::{console::ConsolePorts, shared, Console, Device, PortBus},
+    Emulator, Memory,
+};
+use std::{
+    cell::RefCell,
+    io::{Cursor, Result, Write},
+    rc::Rc,
+    thread,
+    time::Duration,
+};
+
+struct Ram(Vec<u8>);
+
+impl Memory for Ram {
+    fn read_byte(&self, addr: u16) -> u8 {
+        self.0[addr as usize]
+    }
+
+    fn read_word(&self, addr: u16) -> u16 {
+        u16::from_le_bytes([self.read_byte(addr), self.read_byte(addr.wrapping_add(1))])
+    }
+
+    fn write_byte(&mut self, addr: u16, byte: u8) {
+        self.0[addr as usize] = byte;
+    }
+
+    fn write_word(&mut self, addr: u16, word: u16) {
+        let b = word.to_le_bytes();
+        self.write_byte(addr, b[0]);
+        self.write_byte(addr.wrapping_add(1), b[1]);
+    }
+
+    fn in_port(&mut self, _port: u8) -> u8 {
+        0
+    }
+
+    fn out_port(&mut self, _port: u8, _byte: u8) {}
+}
+
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn console(input: &[u8], vt52: bool) -> (Rc<RefCell<Console>>, Output) {
+    let output = Output::default();
+    let mut console = Console::new(
+        ConsolePorts::default(),
+        Cursor::new(input.to_vec()),
+        output.clone(),
+    );
+    console.set_vt52(vt52);
+    (shared(console), output)
+}
+
+#[test]
+fn echo_until_eof() {
+    let (console, output) = console(b"hello", false);
+
+    #[rustfmt::skip]
+    let prog = [
+        0xdb, 0x11, 0xe6, 0x02, 0xca, 0x00, 0x00,
+        0xdb, 0x10, 0xd3, 0x10,
+        0xc3, 0x00, 0x00,
+    ];
+    let mut ram = Ram(vec![0; 0x100]);
+    ram.0[..prog.len()].copy_from_slice(&prog);
+
+    let mut bus = PortBus::new(Box::new(ram));
+    bus.map(0x10, console.clone());
+    let mut emu = Emulator::new(Box::new(bus));
+
+    while !console.borrow().starved() {
+        assert!(emu.cycles() < 10_000_000);
+        emu.step();
+    }
+    assert_eq!(*output.0.borrow(), b"hello");
+}
+
+#[test]
+fn vt52_translation() {
+    let (console, output) = console(b"\x1b[Ax", true);
+    let mut console = console.borrow_mut();
+
+    for b in b"\x1bH\x1bY\x25\x2aok\x1bK" {
+        console.write(0, *b);
+    }
+    assert_eq!(*output.0.borrow(), b"\x1b[H\x1b[6;11Hok\x1b[K");
+
+    thread::sleep(Duration::from_millis(50));
+    let mut input = Vec::new();
+    while console.read(1) & 0x02 != 0 {
+        input.push(console.read(0));
+    }
+    assert_eq!(input, b"\x1bAx");
+    assert!(console.starved());
+}
+
+#[test]
+fn escape_key() {
+    let (console, _) = console(b"a\x1db", false);
+    let mut console = console.borrow_mut();
+    console.set_escape(Some(0x1d));
+
+    thread::sleep(Duration::from_millis(50));
+    console.read(1);
+    assert!(console.quit());
+    assert_eq!(console.read(0), b'a');
+}
+
+#[test]
+fn last_port() {
+    let ports = ConsolePorts {
+        data: 255,
+        status: 0,
+        ..ConsolePorts::default()
+    };
+    let console = Console::new(ports, Cursor::new(b"z".to_vec()), Output::default());
+    assert_eq!(console.ports(), 256);
+
+    let mut bus = PortBus::new(Box::new(Ram(Vec::new())));
+    bus.map(0, shared(console));
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(bus.in_port(255), b'z');
+}
+
+#[test]
+fn piped() {
+    let dir = std::env::temp_dir().join("emu8080-piped");
+    std::fs::create_dir_all(&dir).unwrap();
+    #[rustfmt::skip]
+    let echo = [
+        0xdb, 0x11, 0xe6, 0x02, 0xca, 0x00, 0x00,
+        0xdb, 0x10, 0xd3, 0x10, 0xc3, 0x00, 0x00,
+    ];
+    std::fs::write(dir.join("echo.bin"), echo).unwrap();
+
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_emu8080"))
+        .current_dir(&dir)
+        .args(["--console", "0x10,0x11", "echo.bin"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"hello\n").unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"hello\n");
+}
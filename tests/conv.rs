@@ -6,13 +6,28 @@ fn raw_conversion() {
         let t: RawOpcode = i.into();
 
         match i {
-            0x08 | 0x10 | 0x18 | 0x20 | 0x28 | 0x30 | 0x38 | 0xcb | 0xd9 | 0xdd | 0xed | 0xfd => {
+            0x08 | 0x10 | 0x18 | 0x20 | 0x28 | 0x30 | 0x38 => {
                 if RawOpcode::NOP != t {
                     panic!("{} ({}) != {}", i, RawOpcode::NOP, t);
                 }
             }
+            0xcb => {
+                if RawOpcode::JMP != t {
+                    panic!("{} ({}) != {}", i, RawOpcode::JMP, t);
+                }
+            }
+            0xd9 => {
+                if RawOpcode::RET != t {
+                    panic!("{} ({}) != {}", i, RawOpcode::RET, t);
+                }
+            }
+            0xdd | 0xed | 0xfd => {
+                if RawOpcode::CALL != t {
+                    panic!("{} ({}) != {}", i, RawOpcode::CALL, t);
+                }
+            }
             _ => {
-                if i != t.into() {
+                if i != u8::from(t) {
                     panic!("{} != {}", i, t);
                 }
             }
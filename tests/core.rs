@@ -0,0 +1,211 @@
+use intel_8080_kit::emu::{Cpu, Emulator, FlatMemory, Memory};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Steps run on every program.
+const STEPS: usize = 20_000;
+
+/// Digests of the state after running the random programs of `random_image`
+/// on the 8080, 8085 and Z80, as computed by the core before the flag tables
+/// and the flat RAM.
+const DIGESTS: [[u64; 3]; 32] = [
+    [0x3512757d79d0f717, 0xcfb77bd053c5f49e, 0x15e9d1e011fe3e7b],
+    [0xc17f69d99b00c6a6, 0x7ef16b82d49413e1, 0x5cf39980721cb0cb],
+    [0xeb3887f67d137a38, 0xe671eb60fd739d4f, 0x289fe055d5506a27],
+    [0xd606edd2b56c7767, 0xf10d5071945ac578, 0x4d33fe7053413a80],
+    [0xac6f9d7701d4b495, 0x98a7123722d84ba2, 0xdb6f2bf7a45bb54d],
+    [0xc48202d46390d484, 0x70d8649a771a7838, 0xb54837f9c8b01baa],
+    [0xc906949b5f27700a, 0x7d62e74a2ec28243, 0x0fe771d5ff98902d],
+    [0xa7b70610603bcd9b, 0x5a55ac80755a809b, 0xd472861df2cd10b8],
+    [0xfaa61aa053aab1a6, 0xc9f2c8ba86e5806a, 0x58b6393aa5de5636],
+    [0x3aed71c75510c97a, 0x020c20a02fa3fdce, 0x8574f6629cf883d7],
+    [0xaf80ed00021e6fd8, 0xb8219c0b11b88404, 0x12b48df5401fcc97],
+    [0x081550df59d8b7d3, 0xc3dbace69e768153, 0x27f7bdedba3eade3],
+    [0x39c3f84a7b403072, 0xfcac9f1e8283293d, 0xb608411cb6757979],
+    [0x95add746e739a69a, 0xc13c421bb1f8cbf6, 0x43076a08965a7f54],
+    [0xdb2127a9b4c95e0e, 0x6154548c9a877acb, 0x994f2d979c56e5bb],
+    [0xcd88955a24a4760d, 0x3d6f5a5cd4776bfe, 0x9c705eba2cd7a181],
+    [0xd5dcbe4735a35be3, 0x484bb683c119a2b9, 0xe298ddf7a9952092],
+    [0x41674cbf64b7cf55, 0xe4401d0e1fa60990, 0x4cb18f23801de3e0],
+    [0x2a547f95b1fdd33b, 0x06772d9310f1bd8a, 0xaefaaa7f18151eff],
+    [0xd1859ed52e396a0c, 0xe11eda10b601697a, 0x46cf9ab93bd97c8f],
+    [0x62ba6cf7a953ca1c, 0xfbd94fa86b6f50c5, 0x1b2466acd0cb8b80],
+    [0xd0fb9f4270717b3e, 0xafff0f4a31f99333, 0xab7213ebcb891f64],
+    [0xc47690580ad7985c, 0xfa84049f394b51d9, 0xb674fce7ce261755],
+    [0x2bc543c371a8ebda, 0xa6851a84b884afe4, 0x4bb7acc01780e2c7],
+    [0xbd730a10a54d7286, 0x3a8cf081561291e0, 0x398eb0720b5d8386],
+    [0x8cbf5ab3657851b9, 0xa7eb112dda675f6a, 0x7156b9ec3e3862d0],
+    [0x6a4a2a0709787bd2, 0xa3aa201cecbcdc9d, 0xc65d0080a43a4579],
+    [0x0c41059fe29064b9, 0x7d059c3ec40fe3e5, 0x95bf3ed376650f19],
+    [0x391e158c8ce58dc7, 0xa6a209b1aaed1f2c, 0xf5a6c2c7b008dd19],
+    [0x54724d48da27f441, 0x316107d5b4981212, 0x2daa836d3b4ca4ab],
+    [0xf4ab568c875d9473, 0x177393cfa25f6306, 0x8f9a47b1aae96513],
+    [0xdb606a1234c5fb4b, 0x742551390b5d555b, 0x20b4c78aa645e66e],
+];
+
+/// FNV-1a, to fold the state into a digest.
+fn fold(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3)
+    })
+}
+
+/// 64K of random bytes, without `HLT` so the programs run to the end.
+fn random_image(seed: u64) -> Vec<u8> {
+    let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+    (0..0x10000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            match state as u8 {
+                0x76 => 0,
+                byte => byte,
+            }
+        })
+        .collect()
+}
+
+/// Deterministic input ports, the outputs are folded into a digest.
+struct Ports {
+    inputs: u8,
+    outputs: Rc<Cell<u64>>,
+}
+
+impl Ports {
+    fn in_port(&mut self, port: u8) -> u8 {
+        self.inputs = self.inputs.wrapping_add(1);
+        port ^ self.inputs.wrapping_mul(37)
+    }
+
+    fn out_port(&mut self, port: u8, byte: u8) {
+        self.outputs.set(fold(self.outputs.get(), &[port, byte]));
+    }
+}
+
+/// The RAM behind the `Memory` trait.
+struct Ram(Vec<u8>, Ports);
+
+impl Memory for Ram {
+    fn read_byte(&self, addr: u16) -> u8 {
+        self.0[addr as usize]
+    }
+
+    fn read_word(&self, addr: u16) -> u16 {
+        u16::from_le_bytes([self.read_byte(addr), self.read_byte(addr.wrapping_add(1))])
+    }
+
+    fn write_byte(&mut self, addr: u16, byte: u8) {
+        self.0[addr as usize] = byte;
+    }
+
+    fn write_word(&mut self, addr: u16, word: u16) {
+        let [lo, hi] = word.to_le_bytes();
+        self.write_byte(addr, lo);
+        self.write_byte(addr.wrapping_add(1), hi);
+    }
+
+    fn in_port(&mut self, port: u8) -> u8 {
+        self.1.in_port(port)
+    }
+
+    fn out_port(&mut self, port: u8, byte: u8) {
+        self.1.out_port(port, byte)
+    }
+}
+
+/// Only the ports, for the flat RAM.
+struct Io(Ports);
+
+impl Memory for Io {
+    fn read_byte(&self, _addr: u16) -> u8 {
+        0
+    }
+
+    fn read_word(&self, _addr: u16) -> u16 {
+        0
+    }
+
+    fn write_byte(&mut self, _addr: u16, _byte: u8) {}
+
+    fn write_word(&mut self, _addr: u16, _word: u16) {}
+
+    fn in_port(&mut self, port: u8) -> u8 {
+        self.0.in_port(port)
+    }
+
+    fn out_port(&mut self, port: u8, byte: u8) {
+        self.0.out_port(port, byte)
+    }
+}
+
+/// Pushes the registers and the flags, then the stack pointer.
+const DUMP: &[u8] = &[0xf5, 0xc5, 0xd5, 0xe5, 0x21, 0x00, 0x00, 0x39, 0xe5, 0x76];
+
+/// Runs the program with an interrupt every thousand steps, then dumps the
+/// registers in memory and folds the cycles, the outputs and the memory.
+fn digest(mut emu: Emulator, outputs: Rc<Cell<u64>>) -> u64 {
+    for step in 0..STEPS {
+        if step % 1000 == 999 {
+            emu.interrupt(0x38);
+        }
+        emu.step();
+    }
+
+    let pc = emu.pc();
+    for (i, &byte) in DUMP.iter().enumerate() {
+        emu.memory_mut().write_byte(0x100 + i as u16, byte);
+    }
+    emu.set_pc(0x100);
+    for _ in DUMP {
+        emu.step();
+    }
+
+    let mem = emu.memory();
+    let ram = (0..=0xffff).map(|addr| mem.read_byte(addr));
+    let hash = fold(outputs.get(), &ram.collect::<Vec<_>>());
+    let hash = fold(hash, &pc.to_le_bytes());
+    fold(hash, &emu.cycles().to_le_bytes())
+}
+
+fn on_ram(image: Vec<u8>, cpu: Cpu) -> u64 {
+    let outputs = Rc::new(Cell::new(0xcbf2_9ce4_8422_2325));
+    let ports = Ports {
+        inputs: 0,
+        outputs: outputs.clone(),
+    };
+    digest(
+        Emulator::with_cpu(Box::new(Ram(image, ports)), cpu),
+        outputs,
+    )
+}
+
+fn on_flat(image: Vec<u8>, cpu: Cpu) -> u64 {
+    let outputs = Rc::new(Cell::new(0xcbf2_9ce4_8422_2325));
+    let ports = Ports {
+        inputs: 0,
+        outputs: outputs.clone(),
+    };
+    let flat = FlatMemory::new(&image, Box::new(Io(ports)));
+    digest(Emulator::with_flat(flat, cpu), outputs)
+}
+
+#[test]
+fn same_as_previous_core() {
+    for (seed, digests) in DIGESTS.iter().enumerate() {
+        for (cpu, &digest) in [Cpu::I8080, Cpu::I8085, Cpu::Z80].iter().zip(digests) {
+            let image = random_image(seed as u64);
+            assert_eq!(on_ram(image, *cpu), digest, "seed {} on {:?}", seed, cpu);
+        }
+    }
+}
+
+#[test]
+fn flat_same_as_ram() {
+    for seed in 0..DIGESTS.len() as u64 {
+        for cpu in [Cpu::I8080, Cpu::I8085, Cpu::Z80] {
+            let image = random_image(seed);
+            assert_eq!(on_flat(image.clone(), cpu), on_ram(image, cpu));
+        }
+    }
+}
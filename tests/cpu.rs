@@ -0,0 +1,121 @@
+use intel_8080_kit::emu::{Emulator, Memory};
+use std::{cell::RefCell, rc::Rc};
+
+/// RAM filled with 0x99 past the program, bytes written to any port are
+/// collected in `out`.
+struct Ram {
+    bytes: Vec<u8>,
+    out: Rc<RefCell<Vec<u8>>>,
+}
+
+impl Memory for Ram {
+    fn read_byte(&self, addr: u16) -> u8 {
+        self.bytes[addr as usize]
+    }
+
+    fn read_word(&self, addr: u16) -> u16 {
+        u16::from_le_bytes([self.read_byte(addr), self.read_byte(addr.wrapping_add(1))])
+    }
+
+    fn write_byte(&mut self, addr: u16, byte: u8) {
+        self.bytes[addr as usize] = byte;
+    }
+
+    fn write_word(&mut self, addr: u16, word: u16) {
+        let [lo, hi] = word.to_le_bytes();
+        self.write_byte(addr, lo);
+        self.write_byte(addr.wrapping_add(1), hi);
+    }
+
+    fn in_port(&mut self, _port: u8) -> u8 {
+        0
+    }
+
+    fn out_port(&mut self, _port: u8, byte: u8) {
+        self.out.borrow_mut().push(byte);
+    }
+}
+
+fn emulator(program: &[(u16, &[u8])]) -> (Emulator, Rc<RefCell<Vec<u8>>>) {
+    let mut bytes = vec![0x99; 0x10000];
+    for (addr, code) in program {
+        let start = *addr as usize;
+        bytes[start..start + code.len()].copy_from_slice(code);
+    }
+    let out = Rc::new(RefCell::new(Vec::new()));
+    let ram = Ram {
+        bytes,
+        out: out.clone(),
+    };
+    (Emulator::new(Box::new(ram)), out)
+}
+
+/// Output of `code` run from address 0 until it halts.
+fn run(code: &[u8]) -> Vec<u8> {
+    let (mut emu, out) = emulator(&[(0, code)]);
+    emu.run();
+    let bytes = out.borrow().clone();
+    bytes
+}
+
+#[test]
+fn hl_pair() {
+    // lxi h, 1234h; mov a, h; out 0; mov a, l; out 0; hlt
+    let code = [0x21, 0x34, 0x12, 0x7c, 0xd3, 0, 0x7d, 0xd3, 0, 0x76];
+    assert_eq!(run(&code), [0x12, 0x34]);
+}
+
+#[test]
+fn flags() {
+    // lxi sp, 100h; mvi a, n; op; push psw; pop b; mov a, c; out 0; hlt
+    let flags = |a: u8, op: &[u8]| {
+        let mut code = vec![0x31, 0x00, 0x01, 0x3e, a];
+        code.extend_from_slice(op);
+        code.extend_from_slice(&[0xf5, 0xc1, 0x79, 0xd3, 0, 0x76]);
+        run(&code)[0]
+    };
+
+    // ora a, parity is set for an even number of ones
+    assert_eq!(flags(0x03, &[0xb7]) & 0x04, 0x04);
+    assert_eq!(flags(0x01, &[0xb7]) & 0x04, 0);
+    assert_eq!(flags(0xf0, &[0xb7]) & 0x04, 0x04);
+    // mvi b, 2; cmp b, carry is set when a < b
+    assert_eq!(flags(0x01, &[0x06, 0x02, 0xb8]) & 0x01, 0x01);
+    assert_eq!(flags(0x03, &[0x06, 0x02, 0xb8]) & 0x01, 0);
+}
+
+#[test]
+fn mov_same_register() {
+    // lxi h, 10h; mvi b, 5; mov b, b; mov a, b; out 0; hlt
+    let code = [0x21, 0x10, 0x00, 0x06, 0x05, 0x40, 0x78, 0xd3, 0, 0x76];
+    assert_eq!(run(&code), [0x05]);
+}
+
+#[test]
+fn wrapping() {
+    // mvi a, 0ffh; inr a; out 0; lxi b, 0; dcx b; mov a, b; out 0; hlt
+    let code = [
+        0x3e, 0xff, 0x3c, 0xd3, 0, 0x01, 0x00, 0x00, 0x0b, 0x78, 0xd3, 0, 0x76,
+    ];
+    assert_eq!(run(&code), [0x00, 0xff]);
+}
+
+#[test]
+fn enable_disable_interrupts() {
+    // At 10h: mvi a, 42h; out 0; hlt
+    let handler: &[u8] = &[0x3e, 0x42, 0xd3, 0, 0x76];
+
+    // lxi sp, 100h; ei; nop; nop; hlt
+    let ei: &[u8] = &[0x31, 0x00, 0x01, 0xfb, 0x00, 0x00, 0x76];
+    let (mut emu, out) = emulator(&[(0, ei), (0x10, handler)]);
+    emu.interrupt(0x10);
+    emu.run();
+    assert_eq!(*out.borrow(), [0x42]);
+
+    // lxi sp, 100h; ei; di; nop; hlt
+    let di: &[u8] = &[0x31, 0x00, 0x01, 0xfb, 0xf3, 0x00, 0x76];
+    let (mut emu, out) = emulator(&[(0, di), (0x10, handler)]);
+    emu.interrupt(0x10);
+    emu.run();
+    assert!(out.borrow().is_empty());
+}
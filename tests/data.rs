@@ -0,0 +1,73 @@
+use intel_8080_kit::{
+    asm::{codegen, lexer::tokenize},
+    op::Opcode,
+};
+
+fn bin(src: &str) -> Vec<u8> {
+    codegen(&tokenize(src).unwrap())
+}
+
+#[test]
+fn bytes() {
+    assert_eq!(
+        bin("db 1, 0FFH, -1, 'A' + 1\ndb \"hi\\n\", 0\ndb 'it''s'\n"),
+        [1, 0xff, 0xff, b'B', b'h', b'i', b'\n', 0, b'i', b't', b'\'', b's']
+    );
+    assert_eq!(
+        tokenize("db 'a'\n").unwrap(),
+        [Opcode::Db(b'a')],
+        "a single character is one byte"
+    );
+}
+
+#[test]
+fn words() {
+    assert_eq!(
+        bin("dw 1234H, -2, 'AB'\n"),
+        [0x34, 0x12, 0xfe, 0xff, 0x42, 0x41]
+    );
+}
+
+#[test]
+fn reserve() {
+    assert_eq!(bin("ds 3\nds 2, 0AAH\nds 0\n"), [0, 0, 0, 0xaa, 0xaa]);
+}
+
+#[test]
+fn table() {
+    let src = "\
+        lxi h, table\n\
+        lda msg + 1\n\
+        hlt\n\
+        table: dw entry, msg\n\
+        entry: db high entry, low msg\n\
+        msg: db \"ok\", 0\n";
+
+    assert_eq!(
+        bin(src),
+        [
+            0x21, 0x07, 0x00, // lxi h, table
+            0x3a, 0x0e, 0x00, // lda msg + 1
+            0x76, // hlt
+            0x0b, 0x00, 0x0d, 0x00, // table
+            0x00, 0x0d, // entry
+            b'o', b'k', 0, // msg
+        ]
+    );
+}
+
+#[test]
+fn errors() {
+    for (src, msg) in [
+        ("db\n", "`db` takes at least 1 operand"),
+        ("db 256\n", "`256` doesn't fit in 8 bits"),
+        ("dw 10000H\n", "`65536` doesn't fit in 16 bits"),
+        ("ds\n", "`ds` takes 1 or 2 operands, found 0"),
+        ("ds 1, 2, 3\n", "`ds` takes 1 or 2 operands, found 3"),
+        ("ds size\nsize:\n", "`size` must be defined before this use"),
+        ("db later\n", "use of undefined symbol `later`"),
+    ] {
+        let diags = tokenize(src).unwrap_err();
+        assert_eq!(diags[0].message, msg, "{}", src);
+    }
+}
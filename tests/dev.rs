@@ -0,0 +1,153 @@
+use intel_8080_kit::emu::{
+    dev::{shared, Device, PortBus, I8251, I8253, I8255, I8259},
+    Emulator, Memory,
+};
+
+struct Ram([u8; 0x10000]);
+
+impl Ram {
+    fn from_slice(base: &[u8]) -> Self {
+        let mut mem = Self([0; 0x10000]);
+        mem.0[..base.len()].copy_from_slice(base);
+        mem
+    }
+}
+
+impl Memory for Ram {
+    fn read_byte(&self, addr: u16) -> u8 {
+        self.0[addr as usize]
+    }
+
+    fn read_word(&self, addr: u16) -> u16 {
+        u16::from_le_bytes([self.read_byte(addr), self.read_byte(addr.wrapping_add(1))])
+    }
+
+    fn write_byte(&mut self, addr: u16, byte: u8) {
+        self.0[addr as usize] = byte;
+    }
+
+    fn write_word(&mut self, addr: u16, word: u16) {
+        let b = word.to_le_bytes();
+        self.write_byte(addr, b[0]);
+        self.write_byte(addr.wrapping_add(1), b[1]);
+    }
+
+    fn in_port(&mut self, _port: u8) -> u8 {
+        0xff
+    }
+
+    fn out_port(&mut self, _port: u8, _byte: u8) {}
+}
+
+#[test]
+fn timer_rate_generator() {
+    let mut pit = I8253::new(1);
+    pit.write(3, 0x14);
+    pit.write(0, 3);
+
+    let mut outs = Vec::new();
+    for _ in 0..7 {
+        pit.clock();
+        outs.push(pit.out(0));
+    }
+    assert_eq!(outs, vec![true, true, false, true, true, false, true]);
+
+    pit.write(3, 0x00);
+    pit.clock();
+    assert_eq!(pit.read(0), 3);
+    assert_eq!(pit.read(0), 2);
+}
+
+#[test]
+fn pic_priority() {
+    let mut pic = I8259::new();
+    pic.write(0, 0x36);
+    pic.write(1, 0x10);
+    pic.write(1, 0x00);
+
+    pic.set_input(5, true);
+    pic.set_input(2, true);
+    assert_eq!(pic.acknowledge(), Some(0x1028));
+    assert_eq!(pic.acknowledge(), None);
+
+    pic.write(0, 0x20);
+    assert_eq!(pic.acknowledge(), Some(0x1034));
+    assert_eq!(pic.acknowledge(), None);
+}
+
+#[test]
+fn ppi_ports() {
+    let mut ppi = I8255::new();
+    ppi.write(3, 0x90);
+
+    ppi.set_input(0, 0x5a);
+    assert_eq!(ppi.read(0), 0x5a);
+
+    ppi.write(1, 0x33);
+    assert_eq!(ppi.output(1), 0x33);
+
+    ppi.write(3, 0x07);
+    assert!(ppi.line(3));
+    ppi.write(3, 0x06);
+    assert!(!ppi.line(3));
+}
+
+#[test]
+fn usart_echo() {
+    #[rustfmt::skip]
+    let prog = [
+        0x3e, 0x4e, 0xd3, 0x11, 0x3e, 0x05, 0xd3, 0x11,
+        0xdb, 0x11, 0xe6, 0x02, 0xca, 0x08, 0x00,
+        0xdb, 0x10, 0xd3, 0x10, 0xfe, 0x0a, 0xc2, 0x08, 0x00,
+        0x76,
+    ];
+
+    let usart = shared(I8251::new());
+    for b in b"hi\n" {
+        usart.borrow_mut().receive(*b);
+    }
+
+    let mut bus = PortBus::new(Box::new(Ram::from_slice(&prog)));
+    bus.map(0x10, usart.clone());
+
+    let mut emu = Emulator::new(Box::new(bus));
+    emu.run();
+
+    let mut out = Vec::new();
+    while let Some(b) = usart.borrow_mut().transmit() {
+        out.push(b);
+    }
+    assert_eq!(out, b"hi\n");
+}
+
+#[test]
+fn timer_interrupt() {
+    let mut prog = vec![0; 0x30];
+    #[rustfmt::skip]
+    prog[..24].copy_from_slice(&[
+        0x31, 0x00, 0x10,
+        0x3e, 0x36, 0xd3, 0x20, 0x3e, 0x00, 0xd3, 0x21, 0x3e, 0xfe, 0xd3, 0x21,
+        0x3e, 0x10, 0xd3, 0x43, 0x3e, 0x20, 0xd3, 0x40,
+        0xfb,
+    ]);
+    prog[24] = 0x76;
+    prog[0x20..0x26].copy_from_slice(&[0x3e, 0x55, 0x32, 0x00, 0x01, 0x76]);
+
+    let pit = shared(I8253::new(1));
+    let pic = shared(I8259::new());
+
+    let mut bus = PortBus::new(Box::new(Ram::from_slice(&prog)));
+    bus.map(0x40, pit.clone());
+    bus.map_pic(0x20, pic);
+    bus.connect(pit, 0, 0);
+
+    let mut emu = Emulator::new(Box::new(bus));
+    emu.run();
+    assert_eq!(emu.pc(), 25);
+
+    while emu.memory().read_byte(0x100) != 0x55 {
+        assert!(emu.cycles() < 1000);
+        emu.step();
+    }
+    assert_eq!(emu.memory().read_word(0x0ffe), 25);
+}
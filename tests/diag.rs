@@ -0,0 +1,80 @@
+use intel_8080_kit::asm::{
+    diag::{Severity, Span},
+    lexer::tokenize,
+};
+
+#[test]
+fn positions() {
+    let src = "start:\n\tmvi a, 5\n\tfoo\n\tmov b, q\n\tadi 0x1g\n";
+    let diags = tokenize(src).unwrap_err();
+
+    let found = diags
+        .iter()
+        .map(|d| (d.line, d.column, &src[d.span.start..d.span.end]))
+        .collect::<Vec<_>>();
+    assert_eq!(found, [(5, 6, "0x1g"), (3, 2, "foo"), (4, 9, "q")]);
+    assert_eq!(diags[0].message, "invalid number `0x1g`");
+    assert_eq!(diags[1].message, "unknown opcode `foo`");
+    assert!(diags.iter().all(|d| d.severity == Severity::Error));
+}
+
+#[test]
+fn labels() {
+    let src = "loop:\n  jmp done\nloop:  ; again\n  call done\n";
+    let diags = tokenize(src).unwrap_err();
+
+    let found = diags
+        .iter()
+        .map(|d| (d.severity, d.line, d.message.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        [
+            (Severity::Error, 3, "label `loop` redefined"),
+            (Severity::Note, 1, "first defined here"),
+            (Severity::Error, 2, "use of undefined symbol `done`"),
+            (Severity::Error, 4, "use of undefined symbol `done`"),
+        ]
+    );
+    assert_eq!(diags[2].span, Span::new(12, 16));
+}
+
+#[test]
+fn render() {
+    let src = "\tnop\n\tmov\tb, x\n";
+    let mut diags = tokenize(src).unwrap_err();
+    assert_eq!(diags.len(), 1);
+
+    diags[0].file = Some("test.asm".into());
+    assert_eq!(
+        diags[0].to_string(),
+        "test.asm:2:9: error: unknown operand `x` for mov"
+    );
+    assert_eq!(
+        diags[0].render(src),
+        "error: unknown operand `x` for mov\n --> test.asm:2:9\n  |\n2 | \tmov\tb, x\n  | \t   \t   ^\n"
+    );
+}
+
+#[test]
+fn exit_status() {
+    let dir = std::env::temp_dir().join("asm8080-exit-status");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("bad.asm"), "\tbogus b\n").unwrap();
+    std::fs::write(dir.join("good.asm"), "\tnop\n").unwrap();
+
+    let status = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_asm8080"))
+            .current_dir(&dir)
+            .args(args)
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+    assert_eq!(status(&["good.asm"]), Some(0));
+    assert_eq!(status(&["bad.asm"]), Some(1));
+    assert_eq!(status(&["missing.asm"]), Some(1));
+    assert_eq!(status(&["-o"]), Some(1));
+    assert_eq!(status(&[]), Some(1));
+}
@@ -0,0 +1,77 @@
+use intel_8080_kit::{asm::lexer::tokenize, op::Opcode};
+
+/// Immediate byte of `mvi a, <expr>`.
+fn byte(expr: &str) -> u8 {
+    match tokenize(&format!("mvi a, {}\n", expr)).unwrap()[..] {
+        [Opcode::MviA(value)] => value,
+        ref ops => panic!("{:?}", ops),
+    }
+}
+
+fn error(src: &str) -> String {
+    tokenize(src).unwrap_err()[0].message.clone()
+}
+
+#[test]
+fn numbers() {
+    assert_eq!(byte("0FFH"), 0xff);
+    assert_eq!(byte("377o"), 0xff);
+    assert_eq!(byte("377Q"), 0xff);
+    assert_eq!(byte("1010B"), 10);
+    assert_eq!(byte("99D"), 99);
+    assert_eq!(byte("0x1b"), 0x1b);
+    assert_eq!(byte("0b11"), 3);
+    assert_eq!(byte("'A'"), 0x41);
+    assert_eq!(byte("''''"), b'\'');
+    assert_eq!(error("mvi a, 12AH\n"), "`298` doesn't fit in 8 bits");
+    assert_eq!(error("mvi a, 19O\n"), "invalid number `19O`");
+}
+
+#[test]
+fn operators() {
+    assert_eq!(byte("2 + 3 * 4"), 14);
+    assert_eq!(byte("(2 + 3) * 4"), 20);
+    assert_eq!(byte("17 / 5 + 17 MOD 5"), 5);
+    assert_eq!(byte("1 SHL 7 OR 1 shl 0"), 0x81);
+    assert_eq!(byte("0F0H SHR 4"), 0x0f);
+    assert_eq!(byte("0F0H AND 3CH XOR 0FH"), 0x3f);
+    assert_eq!(byte("NOT 0"), 0xff);
+    assert_eq!(byte("NOT 1 AND 3"), 2);
+    assert_eq!(byte("-1"), 0xff);
+    assert_eq!(byte("- -2"), 2);
+    assert_eq!(byte("HIGH 1234H"), 0x12);
+    assert_eq!(byte("LOW 1234H + 1"), 0x35);
+    assert_eq!(byte("'a' - 'A'"), 0x20);
+    assert_eq!(byte("1 + 1 EQ 2"), 0xff);
+    assert_eq!(byte("3 LT 2 OR 2 ge 2 AND 4"), 4);
+    assert_eq!(byte("NOT 1 NE 1"), 0xff);
+
+    assert_eq!(
+        tokenize("lxi h, 'AB'\nlxi d, (1 SHL 8) OR 34H\n").unwrap(),
+        [Opcode::LxiH(0x41, 0x42), Opcode::LxiD(0x01, 0x34)]
+    );
+}
+
+#[test]
+fn symbols() {
+    let src = "start:\n  lxi b, end - start\n  jmp $ + 3\n  mvi a, LOW end\nend:\n";
+    assert_eq!(
+        tokenize(src).unwrap(),
+        [Opcode::LxiB(0, 8), Opcode::Jmp(6), Opcode::MviA(8),]
+    );
+}
+
+#[test]
+fn errors() {
+    assert_eq!(error("mvi a, -129\n"), "`-129` doesn't fit in 8 bits");
+    assert_eq!(error("lxi h, 10000H\n"), "`65536` doesn't fit in 16 bits");
+    assert_eq!(error("mvi a, 1 / (2 - 2)\n"), "division by zero");
+    assert_eq!(error("mvi a, 'ABC'\n"), "string used as a number");
+    assert_eq!(
+        error("lxi h, 65535 * 65535 * 65535 * 65535 * 65535\n"),
+        "arithmetic overflow"
+    );
+    assert_eq!(error("mvi a, (1 + 2\n"), "expected `)`");
+    assert_eq!(error("mvi a, 1 +\n"), "expected operand");
+    assert_eq!(error("mvi a, mod\n"), "expected operand, found `mod`");
+}
@@ -0,0 +1,69 @@
+use intel_8080_kit::emu::{bus::Bus, Cpu, Emulator, FlatMemory, Memory};
+
+/// Sums, shifts and stores bytes, pushes the flags and halts.
+#[rustfmt::skip]
+const PROGRAM: &[u8] = &[
+    0x31, 0x00, 0x10, 0x21, 0x00, 0x02, 0x0e, 0x40, 0xaf,
+    0x81, 0x1f, 0x27, 0x77, 0xf5, 0xd1, 0x23, 0x73, 0x23, 0x0d, 0xc2, 0x09, 0x00,
+    0x3e, 0x99, 0xc6, 0x01, 0xe6, 0x0f, 0xf6, 0x80, 0xee, 0xff, 0xd6, 0x7f,
+    0xf5, 0xcd, 0x30, 0x00, 0x76,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0x3c, 0x3d, 0x29, 0x22, 0x00, 0x03, 0xc9,
+];
+
+struct Ports;
+
+impl Memory for Ports {
+    fn read_byte(&self, _addr: u16) -> u8 {
+        0
+    }
+
+    fn read_word(&self, _addr: u16) -> u16 {
+        0
+    }
+
+    fn write_byte(&mut self, _addr: u16, _byte: u8) {}
+
+    fn write_word(&mut self, _addr: u16, _word: u16) {}
+
+    fn in_port(&mut self, _port: u8) -> u8 {
+        0
+    }
+
+    fn out_port(&mut self, _port: u8, _byte: u8) {}
+}
+
+fn run(mut emu: Emulator) -> (usize, Vec<u8>) {
+    emu.run();
+    let mem = emu.memory();
+    (
+        emu.cycles(),
+        (0..0x1000).map(|addr| mem.read_byte(addr)).collect(),
+    )
+}
+
+#[test]
+fn same_as_bus() {
+    for cpu in [Cpu::I8080, Cpu::I8085, Cpu::Z80] {
+        let mut bus = Bus::new();
+        bus.map_ram(0, 0x10000);
+        bus.load(0, PROGRAM);
+
+        let flat = FlatMemory::new(PROGRAM, Box::new(Ports));
+        assert_eq!(
+            run(Emulator::with_cpu(Box::new(bus), cpu)),
+            run(Emulator::with_flat(flat, cpu))
+        );
+    }
+}
+
+#[test]
+fn wrapped() {
+    let mut emu = Emulator::with_flat(FlatMemory::new(PROGRAM, Box::new(Ports)), Cpu::I8080);
+    emu.wrap_memory(|mem| mem);
+    assert_eq!(emu.memory().read_byte(1), 0x00);
+    assert_eq!(emu.memory().read_byte(2), 0x10);
+
+    emu.run();
+    assert_eq!(emu.memory().read_word(0x300), 0x0500);
+}
@@ -0,0 +1,93 @@
+use intel_8080_kit::{
+    asm::{codegen, lexer::tokenize},
+    op::Opcode,
+};
+
+fn messages(src: &str) -> Vec<(usize, String)> {
+    tokenize(src)
+        .unwrap_err()
+        .into_iter()
+        .map(|d| (d.line, d.message))
+        .collect()
+}
+
+#[test]
+fn operands() {
+    let src = "\
+        lda var\n\
+        sta var + 1\n\
+        lhld ptr\n\
+        shld ptr\n\
+        lxi d, ptr - var\n\
+        mvi a, LOW ptr\n\
+        cpi HIGH ptr\n\
+        jmp done\n\
+        dw done\n\
+        db var\n\
+        var: db 0, 0\n\
+        ptr: dw 0\n\
+        done: hlt\n";
+
+    assert_eq!(
+        tokenize(src).unwrap()[..10],
+        [
+            Opcode::Lda(0x0019),
+            Opcode::Sta(0x001a),
+            Opcode::Lhld(0x001b),
+            Opcode::Shld(0x001b),
+            Opcode::LxiD(0x00, 0x02),
+            Opcode::MviA(0x1b),
+            Opcode::Cpi(0x00),
+            Opcode::Jmp(0x001d),
+            Opcode::Db(0x1d),
+            Opcode::Db(0x00),
+        ]
+    );
+}
+
+#[test]
+fn equ() {
+    let src = "\
+        size equ end - start\n\
+        half equ size / 2\n\
+        mvi b, half\n\
+        start: ds 4\n\
+        end: db size\n";
+    assert_eq!(codegen(&tokenize(src).unwrap()), [0x06, 2, 0, 0, 0, 0, 4]);
+}
+
+#[test]
+fn ranges() {
+    assert_eq!(
+        messages("mvi a, far\nadi far - 1\nout port\norg 300H\nfar:\nport equ 100H\n"),
+        [
+            (1, "`768` doesn't fit in 8 bits".into()),
+            (2, "`767` doesn't fit in 8 bits".into()),
+            (3, "`256` doesn't fit in 8 bits".into()),
+        ]
+    );
+    assert_eq!(
+        messages("db back SHL 8\nback: db 0\n"),
+        [(1, "`256` doesn't fit in 8 bits".into())]
+    );
+}
+
+#[test]
+fn needed_early() {
+    assert_eq!(
+        messages("org start\nds size\nstart:\nsize equ 4\nn set later\nlater:\n"),
+        [
+            (1, "`start` must be defined before this use".into()),
+            (2, "`size` must be defined before this use".into()),
+            (5, "`later` must be defined before this use".into()),
+        ]
+    );
+    assert_eq!(
+        messages("x equ y + 1\ny equ x\nu equ v\nv equ missing\n"),
+        [
+            (1, "circular definition of `x`".into()),
+            (2, "circular definition of `y`".into()),
+            (4, "use of undefined symbol `missing`".into()),
+        ]
+    );
+}
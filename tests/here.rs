@@ -0,0 +1,49 @@
+use intel_8080_kit::{
+    asm::{codegen, lexer::tokenize},
+    op::Opcode,
+};
+
+fn bin(src: &str) -> Vec<u8> {
+    codegen(&tokenize(src).unwrap())
+}
+
+#[test]
+fn instructions() {
+    assert_eq!(
+        tokenize("nop\njmp $\nmvi a, $\nlxi h, $ + 2\nrst 1\njz $ - 10\n").unwrap(),
+        [
+            Opcode::Nop,
+            Opcode::Jmp(1),
+            Opcode::MviA(4),
+            Opcode::LxiH(0, 8),
+            Opcode::Rst1,
+            Opcode::Jz(0),
+        ]
+    );
+}
+
+#[test]
+fn data() {
+    assert_eq!(bin("nop\ndw $, $ + 1\ndb $, $\n"), [0, 1, 0, 2, 0, 5, 5]);
+    assert_eq!(bin("ds 2\nds $, 0FFH\nds $ - 4, 1\n"), [0, 0, 0xff, 0xff]);
+    assert_eq!(
+        bin("msg: db \"abc\"\nlen equ $ - msg\ndb len\n"),
+        [b'a', b'b', b'c', 3]
+    );
+}
+
+#[test]
+fn org() {
+    let src = "\
+        org 10H\n\
+        here equ $\n\
+        jmp $ + 3\n\
+        org $ + 2\n\
+        dw $, here, next\n\
+        next:\n";
+    let bin = bin(src);
+    assert_eq!(
+        bin[0x10..],
+        [0xc3, 0x13, 0x00, 0, 0, 0x15, 0, 0x10, 0, 0x1b, 0]
+    );
+}
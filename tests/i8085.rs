@@ -0,0 +1,125 @@
+use intel_8080_kit::emu::{bus::Bus, Cpu, Emulator, Input};
+
+fn board(cpu: Cpu, code: &[(u16, &[u8])]) -> Emulator {
+    let mut bus = Bus::new();
+    bus.map_ram(0, 0x1000);
+    for (addr, bytes) in code {
+        bus.load(*addr, bytes);
+    }
+    Emulator::with_cpu(Box::new(bus), cpu)
+}
+
+fn word(emu: &Emulator, addr: u16) -> u16 {
+    emu.memory().read_word(addr)
+}
+
+#[test]
+fn rim_sim() {
+    #[rustfmt::skip]
+    let code: &[u8] = &[
+        0x3e, 0x1e, 0x30,
+        0x3e, 0xc0, 0x30,
+        0x20, 0x32, 0x00, 0x02,
+        0x76,
+    ];
+
+    let mut emu = board(Cpu::I8085, &[(0, code)]);
+    emu.set_sid(true);
+    emu.run();
+    assert!(emu.sod());
+    assert_eq!(emu.memory().read_byte(0x200), 0x86);
+
+    // Plain nops on the 8080
+    let mut emu = board(Cpu::I8080, &[(0, code)]);
+    emu.run();
+    assert!(!emu.sod());
+    assert_eq!(emu.memory().read_byte(0x200), 0xc0);
+}
+
+#[test]
+fn interrupt_inputs() {
+    #[rustfmt::skip]
+    let code: &[(u16, &[u8])] = &[
+        (0, &[0x31, 0x00, 0x10, 0x3e, 0x0a, 0x30, 0xfb, 0x76]),
+        (0x24, &[0x3e, 0x24, 0x32, 0x00, 0x02, 0x76]),
+        (0x2c, &[0x3e, 0x2c, 0x32, 0x00, 0x02, 0x76]),
+        (0x3c, &[0x3e, 0x3c, 0x32, 0x00, 0x02, 0x76]),
+    ];
+
+    // RST 6.5 is masked, RST 7.5 is served on the rising edge
+    let mut emu = board(Cpu::I8085, code);
+    emu.run();
+    emu.set_input(Input::Rst65, true);
+    emu.set_input(Input::Rst75, true);
+    emu.set_input(Input::Rst75, false);
+    emu.step();
+    emu.run();
+    assert_eq!(emu.memory().read_byte(0x200), 0x3c);
+
+    let mut emu = board(Cpu::I8085, code);
+    emu.run();
+    emu.set_input(Input::Rst55, true);
+    emu.step();
+    emu.run();
+    assert_eq!(emu.memory().read_byte(0x200), 0x2c);
+
+    // TRAP ignores the masks and the interrupt enable
+    let mut emu = board(Cpu::I8085, code);
+    emu.set_pc(7);
+    emu.run();
+    emu.set_input(Input::Trap, true);
+    emu.set_input(Input::Rst55, true);
+    emu.step();
+    emu.run();
+    assert_eq!(emu.memory().read_byte(0x200), 0x24);
+}
+
+#[test]
+fn undocumented() {
+    #[rustfmt::skip]
+    let code: &[u8] = &[
+        0x31, 0x00, 0x10,
+        0x21, 0x34, 0x12, 0x01, 0x34, 0x02, 0x08,
+        0x11, 0x00, 0x02, 0xd9,
+        0x10, 0x28, 0x05, 0xeb,
+        0x11, 0x02, 0x02, 0xd9,
+        0x11, 0x00, 0x02, 0xed, 0x22, 0x04, 0x02,
+        0x01, 0x00, 0x00, 0x0b, 0xfd, 0x2a, 0x00,
+        0x3e, 0x01, 0x32, 0x06, 0x02, 0x76,
+        0x3e, 0x02, 0x32, 0x06, 0x02, 0x76,
+    ];
+
+    let mut emu = board(Cpu::I8085, &[(0, code)]);
+    emu.run();
+    assert_eq!(word(&emu, 0x200), 0x1000);
+    assert_eq!(word(&emu, 0x202), 0x0805);
+    assert_eq!(word(&emu, 0x204), 0x1000);
+    assert_eq!(emu.memory().read_byte(0x206), 0x02);
+}
+
+#[test]
+fn cycles() {
+    let code: &[u8] = &[0x31, 0x00, 0x10, 0x78, 0xc5, 0x76];
+
+    let mut emu = board(Cpu::I8080, &[(0, code)]);
+    emu.run();
+    assert_eq!(emu.cycles(), 33);
+
+    let mut emu = board(Cpu::I8085, &[(0, code)]);
+    emu.run();
+    assert_eq!(emu.cycles(), 31);
+}
+
+#[test]
+fn description() {
+    use intel_8080_kit::emu::machine::{Description, Machine};
+    use std::path::Path;
+
+    let desc = Description::from_toml("cpu = \"8085\"").unwrap();
+    let mut machine = Machine::build(&desc, Path::new(".")).unwrap();
+    assert_eq!(machine.emulator().cpu(), Cpu::I8085);
+
+    let desc = Description::from_toml("").unwrap();
+    assert_eq!(desc.cpu, Cpu::I8080);
+    assert!(Description::from_toml("cpu = \"6502\"").is_err());
+}
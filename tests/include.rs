@@ -0,0 +1,150 @@
+use intel_8080_kit::asm::{assembler::assemble_files, codegen, source::SourceMap};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Directory with `files` written in it.
+fn project(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+    let dir = env::temp_dir().join(format!("include-{}-{}", name, std::process::id()));
+    for (path, data) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+    dir
+}
+
+fn messages(dir: &Path, files: &[&str]) -> Vec<(String, usize, String)> {
+    let paths = files.iter().map(|f| dir.join(f)).collect::<Vec<_>>();
+    let program = assemble_files(&paths, &[]).unwrap();
+    let prefix = format!("{}/", dir.display());
+    program
+        .diags
+        .into_iter()
+        .map(|d| {
+            let file = d.file.unwrap();
+            (
+                file.trim_start_matches(&prefix).to_string(),
+                d.line,
+                d.message,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn include() {
+    let dir = project(
+        "ok",
+        &[
+            (
+                "main.asm",
+                b"include \"defs.asm\"\ninclude \"lib.asm\"\ncall print\nhlt\n",
+            ),
+            ("inc/defs.asm", b"PORT equ 10H\n"),
+            ("lib/lib.asm", b"print: out PORT\nret\n"),
+            ("start.asm", b"jmp main\n"),
+            ("rest.asm", b"main: db 1\n"),
+        ],
+    );
+    let include = [dir.join("inc"), dir.join("lib")];
+
+    let program = assemble_files(&[dir.join("main.asm")], &include).unwrap();
+    assert!(program.diags.is_empty(), "{:?}", program.diags);
+    assert_eq!(
+        codegen(&program.ops),
+        [0xd3, 0x10, 0xc9, 0xcd, 0x00, 0x00, 0x76]
+    );
+
+    // Files given together are a single program
+    let paths = [dir.join("start.asm"), dir.join("rest.asm")];
+    let program = assemble_files(&paths, &[]).unwrap();
+    assert_eq!(codegen(&program.ops), [0xc3, 0x03, 0x00, 0x01]);
+    assert_eq!(program.sources.files.len(), 2);
+}
+
+#[test]
+fn incbin() {
+    let dir = project(
+        "bin",
+        &[
+            (
+                "main.asm",
+                b"incbin \"font.bin\"\nincbin \"font.bin\", 2\nincbin \"font.bin\", 1, 2\n",
+            ),
+            ("font.bin", &[1, 2, 3, 4]),
+            (
+                "bad.asm",
+                b"incbin \"font.bin\", 3, 2\nincbin \"none.bin\"\nincbin 1\n",
+            ),
+        ],
+    );
+
+    let program = assemble_files(&[dir.join("main.asm")], &[]).unwrap();
+    assert_eq!(codegen(&program.ops), [1, 2, 3, 4, 3, 4, 2, 3]);
+
+    assert_eq!(
+        messages(&dir, &["bad.asm"]),
+        [
+            (
+                "bad.asm".into(),
+                1,
+                format!(
+                    "`{}/font.bin` has 4 bytes, not 2 from offset 3",
+                    dir.display()
+                )
+            ),
+            ("bad.asm".into(), 2, "file `none.bin` not found".into()),
+            ("bad.asm".into(), 3, "expected a file name string".into()),
+        ]
+    );
+}
+
+#[test]
+fn origins() {
+    let dir = project(
+        "errors",
+        &[
+            ("main.asm", b"include \"a.asm\"\nbogus\n"),
+            ("a.asm", b"nop\nmvi a, 256\ninclude \"b.asm\"\n"),
+            ("b.asm", b"include \"a.asm\"\n"),
+            ("m.asm", b"include \"macros.asm\"\nload 300\n"),
+            ("macros.asm", b"load macro v\nmvi a, v\nendm\n"),
+        ],
+    );
+
+    assert_eq!(
+        messages(&dir, &["main.asm"]),
+        [
+            ("a.asm".into(), 2, "`256` doesn't fit in 8 bits".into()),
+            (
+                "b.asm".into(),
+                1,
+                format!(
+                    "include cycle: {0}/a.asm -> {0}/b.asm -> {0}/a.asm",
+                    dir.display()
+                )
+            ),
+            ("main.asm".into(), 2, "unknown opcode `bogus`".into()),
+        ]
+    );
+    assert_eq!(
+        messages(&dir, &["m.asm"]),
+        [
+            ("m.asm".into(), 2, "`300` doesn't fit in 8 bits".into()),
+            ("m.asm".into(), 2, "in expansion of macro `load`".into()),
+        ]
+    );
+}
+
+#[test]
+fn source_map() {
+    let mut sources = SourceMap::default();
+    sources.add(Some("a".into()), None, "nop\n".into());
+    let b = sources.add(Some("b".into()), None, "hlt\n".into());
+    assert_eq!(sources.files[b].start, 5);
+    assert_eq!(sources.index(4), 0);
+    assert_eq!(sources.index(5), 1);
+    assert_eq!(sources.source("b"), Some("hlt\n"));
+}
@@ -0,0 +1,103 @@
+use intel_8080_kit::emu::{
+    bus::Bus,
+    dev::{shared, PortBus, I8251},
+    link::{HostSerial, Scheduler, SerialPort},
+    Emulator,
+};
+use std::{
+    cell::RefCell,
+    io::{Cursor, Result, Write},
+    rc::Rc,
+    thread,
+    time::Duration,
+};
+
+/// Echoes characters from the 8251 at port 0x10 until a newline.
+#[rustfmt::skip]
+const ECHO: [u8; 25] = [
+    0x3e, 0x4e, 0xd3, 0x11, 0x3e, 0x05, 0xd3, 0x11,
+    0xdb, 0x11, 0xe6, 0x02, 0xca, 0x08, 0x00,
+    0xdb, 0x10, 0xd3, 0x10, 0xfe, 0x0a, 0xc2, 0x08, 0x00,
+    0x76,
+];
+
+fn board(prog: &[u8]) -> (Emulator, Rc<RefCell<I8251>>) {
+    let mut bus = Bus::new();
+    bus.map_ram(0, 0x1000);
+    bus.load(0, prog);
+
+    let usart = shared(I8251::new());
+    let mut bus = PortBus::new(Box::new(bus));
+    bus.map(0x10, usart.clone());
+    (Emulator::new(Box::new(bus)), usart)
+}
+
+#[test]
+fn two_boards() {
+    // Sends a line and stores the echo at 0x100
+    #[rustfmt::skip]
+    let (a, usart_a) = board(&[
+        0x3e, 0x4e, 0xd3, 0x11, 0x3e, 0x05, 0xd3, 0x11,
+        0x3e, 0x68, 0xd3, 0x10, 0x3e, 0x69, 0xd3, 0x10, 0x3e, 0x0a, 0xd3, 0x10,
+        0x21, 0x00, 0x01,
+        0xdb, 0x11, 0xe6, 0x02, 0xca, 0x17, 0x00,
+        0xdb, 0x10, 0x77, 0x23, 0xfe, 0x0a, 0xc2, 0x17, 0x00,
+        0x76,
+    ]);
+    let (b, usart_b) = board(&ECHO);
+
+    let mut sched = Scheduler::new();
+    let a = sched.add(a, 2_000_000);
+    let b = sched.add(b, 1_000_000);
+    sched.link((a, usart_a), (b, usart_b), 9600);
+
+    sched.run_until(|s| s.emulator(a).halted() || s.now() > 10_000_000);
+
+    let emu = sched.emulator(a);
+    assert!(emu.halted());
+    for (i, byte) in b"hi\n".iter().enumerate() {
+        assert_eq!(emu.memory().read_byte(0x100 + i as u16), *byte);
+    }
+
+    // Three characters on the way there and the last one on the way back,
+    // plus the time the programs take to poll the usarts
+    let char_time = 10_000_000_000 / 9600;
+    let time = sched.time(a);
+    assert!(time >= 4 * char_time && time < 4 * char_time + 200_000);
+    assert!(sched.emulator(b).halted());
+}
+
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn host_port() {
+    let output = Output::default();
+    let host: Rc<RefCell<dyn SerialPort>> = shared(HostSerial::new(
+        Cursor::new(b"ok\n".to_vec()),
+        output.clone(),
+    ));
+    thread::sleep(Duration::from_millis(50));
+
+    let (b, usart) = board(&ECHO);
+    let mut sched = Scheduler::new();
+    let b = sched.add(b, 2_000_000);
+    sched.link_host((b, usart), host, 19200);
+
+    sched.run_until(|s| s.emulator(b).halted() || s.now() > 10_000_000);
+    // The last echo is still on the line
+    assert_eq!(*output.0.borrow(), b"ok");
+    sched.run_for(10_000_000_000 / 19200);
+    assert_eq!(*output.0.borrow(), b"ok\n");
+}
@@ -0,0 +1,109 @@
+use intel_8080_kit::asm::{
+    assembler::{assemble_source, SymbolKind},
+    listing::listing,
+};
+
+#[test]
+fn lines() {
+    let src = "\
+        ; start\n\
+        size equ 3\n\
+        \x20       org 100h\n\
+        start:  mvi b, size\n\
+        \x20       db 1, 2, 3, 4, 5\n\
+        \x20       jmp start\n";
+    let program = assemble_source(src);
+    assert!(!program.has_errors());
+
+    let text = listing(&program);
+    let rows = text.lines().collect::<Vec<_>>();
+    assert_eq!(
+        rows[..8],
+        [
+            "                        1  ; start",
+            "      = 0003            2  size equ 3",
+            "0100                    3          org 100h",
+            "0100  06 03             4  start:  mvi b, size",
+            "0102  01 02 03 04       5          db 1, 2, 3, 4, 5",
+            "0106  05",
+            "0107  C3 00 01          6          jmp start",
+            "",
+        ]
+    );
+}
+
+#[test]
+fn expansions() {
+    let src = "\
+        twice macro op\n\
+        \x20   op\n\
+        \x20   op\n\
+        \x20   endm\n\
+        \x20   twice nop\n\
+        \x20   rept 2\n\
+        \x20   inr a\n\
+        \x20   endm\n";
+    let program = assemble_source(src);
+    let depths = program
+        .listing
+        .iter()
+        .map(|line| (line.depth, line.ops.len()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        depths,
+        [
+            (0, 0),
+            (0, 0),
+            (0, 0),
+            (0, 0),
+            (0, 0),
+            (1, 1),
+            (1, 1),
+            (0, 0),
+            (0, 0),
+            (0, 0),
+            (1, 1),
+            (1, 1),
+        ]
+    );
+    assert!(listing(&program).contains("0000  00           +    2      op"));
+}
+
+#[test]
+fn symbols() {
+    let src = "\
+        later equ last + 1\n\
+        count set 1\n\
+        count set count + 1\n\
+        start: lxi h, later\n\
+        \x20      jmp start\n\
+        last: jmp start\n";
+    let program = assemble_source(src);
+    let symbols = program
+        .symbols
+        .iter()
+        .map(|sym| (sym.name.as_str(), sym.value, sym.kind, sym.uses.len()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        symbols,
+        [
+            ("count", Some(2), SymbolKind::Set, 1),
+            ("last", Some(6), SymbolKind::Label, 1),
+            ("later", Some(7), SymbolKind::Equ, 1),
+            ("start", Some(0), SymbolKind::Label, 2),
+        ]
+    );
+
+    let text = listing(&program);
+    let table = text.split("\n\n").nth(1).unwrap();
+    assert_eq!(
+        table.lines().collect::<Vec<_>>(),
+        [
+            "Symbol  Value  Kind   Defined  Used",
+            "count   0002   set    2        3",
+            "last    0006   label  6        1",
+            "later   0007   equ    1        4",
+            "start   0000   label  4        5, 6",
+        ]
+    );
+}
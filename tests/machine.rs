@@ -0,0 +1,122 @@
+use intel_8080_kit::emu::machine::{Description, Machine};
+use std::{fs, io::Result, path::Path};
+
+#[test]
+fn memory_map() -> Result<()> {
+    let bin = fs::read("tests/mult.bin")?;
+    let desc = Description::from_toml(
+        r#"
+        reset = 0x0000
+
+        [[memory]]
+        kind = "rom"
+        start = 0x0000
+        size = 0x100
+        image = "mult.bin"
+
+        [[memory]]
+        kind = "ram"
+        start = 0x8000
+        size = 0x100
+        "#,
+    )
+    .unwrap();
+
+    let mut machine = Machine::build(&desc, Path::new("tests")).unwrap();
+    let mem = machine.emulator().memory_mut();
+
+    assert_eq!(mem.read_byte(0), bin[0]);
+    assert_eq!(mem.read_byte(bin.len() as u16), 0xff);
+
+    mem.write_byte(0, 0x12);
+    assert_eq!(mem.read_byte(0), bin[0]);
+
+    mem.write_byte(0x8010, 0x34);
+    assert_eq!(mem.read_byte(0x8010), 0x34);
+    assert_eq!(mem.read_byte(0x9000), 0xff);
+
+    Ok(())
+}
+
+#[test]
+fn timer_vector() {
+    let desc = Description::from_json(
+        r#"{
+            "clock": 2000000,
+            "memory": [{ "kind": "ram", "start": 0, "size": 4096 }],
+            "device": [{ "name": "pit", "kind": "i8253", "port": 64 }],
+            "interrupt": [{ "source": "pit", "line": 0, "vector": 56 }]
+        }"#,
+    )
+    .unwrap();
+
+    let mut machine = Machine::build(&desc, Path::new("tests")).unwrap();
+    assert_eq!(machine.clock(), Some(2000000));
+    assert!(machine.device("pit").is_some());
+
+    #[rustfmt::skip]
+    let prog = [
+        0x31, 0x00, 0x10,
+        0x3e, 0x10, 0xd3, 0x43, 0x3e, 0x20, 0xd3, 0x40,
+        0xfb, 0x76,
+    ];
+
+    let emu = machine.emulator();
+    for (i, b) in prog.iter().enumerate() {
+        emu.memory_mut().write_byte(i as u16, *b);
+    }
+    for (i, b) in [0x3e, 0x55, 0x32, 0x00, 0x01, 0x76].iter().enumerate() {
+        emu.memory_mut().write_byte(0x38 + i as u16, *b);
+    }
+
+    while emu.memory().read_byte(0x100) != 0x55 {
+        assert!(emu.cycles() < 1000);
+        emu.step();
+    }
+}
+
+#[test]
+fn invalid() {
+    let desc = Description::from_toml(
+        r#"
+        [[device]]
+        name = "ppi"
+        kind = "i8255"
+        port = 0x10
+
+        [[interrupt]]
+        source = "ppi"
+        line = 3
+        input = 2
+        "#,
+    )
+    .unwrap();
+    assert!(Machine::build(&desc, Path::new("tests")).is_err());
+
+    assert!(Description::from_toml("[[memory]]\nkind = \"flash\"").is_err());
+}
+
+#[test]
+fn exit_status() {
+    let dir = std::env::temp_dir().join("emu8080-exit-status");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("halt.bin"), [0x76]).unwrap();
+    fs::write(dir.join("bad.toml"), "[[memory]]\nkind = \"flash\"\n").unwrap();
+
+    let status = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_emu8080"))
+            .current_dir(&dir)
+            .args(args)
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+    assert_eq!(status(&["halt.bin"]), Some(0));
+    assert_eq!(status(&["missing.bin"]), Some(1));
+    assert_eq!(status(&["--machine", "missing.toml"]), Some(1));
+    assert_eq!(status(&["--machine", "bad.toml"]), Some(1));
+    assert_eq!(status(&["--cpu", "6502", "halt.bin"]), Some(1));
+    assert_eq!(status(&["--tape", "missing.tap", "halt.bin"]), Some(1));
+    assert_eq!(status(&[]), Some(1));
+}
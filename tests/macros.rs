@@ -0,0 +1,188 @@
+use intel_8080_kit::{
+    asm::{codegen, diag::Severity, lexer::tokenize},
+    op::Opcode,
+};
+
+fn bin(src: &str) -> Vec<u8> {
+    codegen(&tokenize(src).unwrap())
+}
+
+fn messages(src: &str) -> Vec<(Severity, usize, String)> {
+    tokenize(src)
+        .unwrap_err()
+        .into_iter()
+        .map(|d| (d.severity, d.line, d.message))
+        .collect()
+}
+
+#[test]
+fn macros() {
+    let src = "\
+        load macro reg, value\n\
+            local skip\n\
+            mvi reg, value\n\
+            jmp skip\n\
+        skip:\n\
+        endm\n\
+        \n\
+        load a, 1\n\
+        LOAD b, 'x' + 1\n";
+
+    assert_eq!(
+        tokenize(src).unwrap(),
+        [
+            Opcode::MviA(1),
+            Opcode::Jmp(5),
+            Opcode::MviB(b'y'),
+            Opcode::Jmp(10),
+        ]
+    );
+}
+
+#[test]
+fn repeat() {
+    assert_eq!(bin("rept 3\ndb $\nendm\n"), [0, 1, 2]);
+    assert_eq!(bin("irp x, 1, 2, 4\ndb x * 2\nendm\n"), [2, 4, 8]);
+    assert_eq!(bin("irpc c, \"ab\"\ndb c, c - 20H\nendm\n"), b"aAbB");
+
+    let src = "\
+        n set 0\n\
+        rept 10\n\
+            n set n + 1\n\
+            if n GT 2\n\
+                exitm\n\
+            endif\n\
+            db n\n\
+        endm\n\
+        db n\n";
+    assert_eq!(bin(src), [1, 2, 3]);
+}
+
+#[test]
+fn conditions() {
+    let src = "\
+        DEBUG equ 1\n\
+        if DEBUG\n\
+            db 1\n\
+            if DEBUG - 1\n\
+                db 2\n\
+            else\n\
+                db 3\n\
+            endif\n\
+        else\n\
+            db 4\n\
+        endif\n\
+        ifdef DEBUG\n\
+            db 5\n\
+        endif\n\
+        ifndef DEBUG\n\
+            db 6\n\
+        else\n\
+            db 7\n\
+        endif\n\
+        ifdef RELEASE\n\
+            bogus\n\
+        endif\n";
+    assert_eq!(bin(src), [1, 3, 5, 7]);
+
+    // Recursion, ending with a condition
+    let src = "\
+        count macro n\n\
+            if n\n\
+                db n\n\
+                count n - 1\n\
+            endif\n\
+        endm\n\
+        count 3\n";
+    assert_eq!(bin(src), [3, 2, 1]);
+}
+
+#[test]
+fn errors() {
+    let src = "\
+        twice macro x\n\
+            mvi a, x\n\
+            mvi b, x\n\
+        endm\n\
+        twice 256\n\
+        twice\n\
+        endif\n\
+        exitm\n\
+        rept 2\n";
+
+    assert_eq!(
+        messages(src),
+        [
+            (Severity::Error, 5, "`256` doesn't fit in 8 bits".into()),
+            (Severity::Note, 5, "in expansion of macro `twice`".into()),
+            (Severity::Error, 5, "`256` doesn't fit in 8 bits".into()),
+            (Severity::Note, 5, "in expansion of macro `twice`".into()),
+            (
+                Severity::Error,
+                6,
+                "macro `twice` takes 1 argument, found 0".into()
+            ),
+            (Severity::Error, 7, "`endif` without `if`".into()),
+            (
+                Severity::Error,
+                8,
+                "`exitm` outside of a macro or repeat block".into()
+            ),
+            (Severity::Error, 9, "`rept` without `endm`".into()),
+        ]
+    );
+
+    let diags = tokenize("m macro\nbogus\nendm\nm\n").unwrap_err();
+    let found = diags
+        .iter()
+        .map(|d| (d.line, d.column, d.message.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        [
+            (2, 1, "unknown opcode `bogus`"),
+            (4, 1, "in expansion of macro `m`")
+        ]
+    );
+
+    assert_eq!(
+        messages("if 1\nm macro\nm\nendm\nm\n")[0].2,
+        "macro expansion too deep"
+    );
+}
+
+#[test]
+fn irp_list() {
+    assert_eq!(bin("irp r, <b, c>\ninr r\nendm\n"), [0x04, 0x0c]);
+    assert_eq!(bin("irp x, <1, 2 + 1>\ndb x\nendm\n"), [1, 3]);
+    assert_eq!(bin("irp x, <>\ndb x\nendm\nnop\n"), [0]);
+
+    assert_eq!(
+        messages("irp x, <1, 2\ndb x\nendm\n")[0],
+        (Severity::Error, 1, "expected `,` or `>`".into())
+    );
+}
+
+#[test]
+fn recursion_notes() {
+    assert_eq!(
+        messages("m macro\nm\nendm\nm\n"),
+        [
+            (Severity::Error, 2, "macro expansion too deep".into()),
+            (Severity::Note, 2, "in expansion of macro `m`".into()),
+            (
+                Severity::Note,
+                2,
+                "... and 62 more expansions of `m`".into()
+            ),
+            (Severity::Note, 4, "in expansion of macro `m`".into()),
+        ]
+    );
+
+    let diags = messages("a macro\nb\nendm\nb macro\na\nendm\na\n");
+    assert_eq!(diags.len(), 9);
+    assert_eq!(
+        diags[8],
+        (Severity::Note, 7, "... and 57 more notes".into())
+    );
+}
@@ -15,7 +15,7 @@ fn assert_eq_vec<T: PartialEq + fmt::Debug>(v1: &Vec<T>, v2: &Vec<T>) {
             }
         }
 
-        if diff.len() != 0 {
+        if !diff.is_empty() {
             panic!(
                 "Vectors {:?} and {:?} are different, differences: {:?}",
                 v1, v2, diff
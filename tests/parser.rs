@@ -0,0 +1,97 @@
+use intel_8080_kit::{
+    asm::{
+        diag::Span,
+        lexer::tokenize,
+        parser::{parse, Expr, ExprKind, StatementKind},
+    },
+    op::Opcode,
+};
+
+#[test]
+fn ast() {
+    let src = "start: MOV A,C ; copy\n\n  org 0x10\nend:\n";
+    let mut diags = Vec::new();
+    let lines = parse(src, &mut diags);
+    assert!(diags.is_empty());
+    assert_eq!(lines.len(), 4);
+
+    let label = lines[0].label.as_ref().unwrap();
+    assert_eq!(
+        (label.name.as_str(), label.span),
+        ("start", Span::new(0, 5))
+    );
+
+    let stmt = lines[0].statement.as_ref().unwrap();
+    assert_eq!(stmt.kind, StatementKind::Instruction);
+    assert_eq!(stmt.name.name, "MOV");
+    assert_eq!(stmt.span, Span::new(7, 14));
+    assert_eq!(
+        stmt.operands,
+        [
+            Expr {
+                kind: ExprKind::Symbol("A".into()),
+                span: Span::new(11, 12)
+            },
+            Expr {
+                kind: ExprKind::Symbol("C".into()),
+                span: Span::new(13, 14)
+            },
+        ]
+    );
+    assert_eq!(lines[0].comment.as_ref().unwrap().text, " copy");
+
+    assert_eq!(lines[1].statement, None);
+    let org = lines[2].statement.as_ref().unwrap();
+    assert_eq!(org.kind, StatementKind::Directive);
+    assert_eq!(org.operands[0].kind, ExprKind::Number(0x10));
+    assert!(lines[3].label.is_some() && lines[3].statement.is_none());
+}
+
+#[test]
+fn missing_operands() {
+    for (src, msg) in [
+        ("mvi a\n", "`mvi` takes 2 operands, found 1"),
+        ("mvi a,\n", "expected operand"),
+        ("jmp\n", "`jmp` takes 1 operand, found 0"),
+        ("mov a c\n", "expected `,`, found `c`"),
+        ("add , b\n", "expected operand, found `,`"),
+        ("mvi a, 'x\n", "unterminated string"),
+    ] {
+        let diags = tokenize(src).unwrap_err();
+        assert_eq!(diags[0].message, msg, "{}", src);
+    }
+}
+
+#[test]
+fn operands() {
+    let src = "\
+        mov a,c\n\
+        MVI B,';'   # comment\n\
+        mvi c, '\\''\n\
+        lxi sp, 0x1234\n\
+        lxi h, data\n\
+        lda data\n\
+        rst 7\n\
+        data: jmp data\n";
+
+    assert_eq!(
+        tokenize(src).unwrap(),
+        [
+            Opcode::MovAC,
+            Opcode::MviB(b';'),
+            Opcode::MviC(b'\''),
+            Opcode::LxiSp(0x12, 0x34),
+            Opcode::LxiH(0x00, 0x0f),
+            Opcode::Lda(0x000f),
+            Opcode::Rst7,
+            Opcode::Jmp(0x000f),
+        ]
+    );
+
+    let diags = tokenize("mvi a, 256\nrst 8\n").unwrap_err();
+    let msgs = diags.iter().map(|d| d.message.as_str()).collect::<Vec<_>>();
+    assert_eq!(
+        msgs,
+        ["`256` doesn't fit in 8 bits", "unknown operand `8` for rst"]
+    );
+}
@@ -0,0 +1,90 @@
+#![cfg(feature = "python")]
+
+use intel_8080_kit::python::intel_8080_kit;
+use pyo3::{ffi::c_str, prelude::*, types::PyDict};
+use std::{ffi::CStr, sync::Once};
+
+/// Runs `code` in an interpreter with the module registered.
+fn run(code: &CStr) {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        pyo3::append_to_inittab!(intel_8080_kit);
+        Python::initialize();
+    });
+
+    Python::attach(|py| {
+        let globals = PyDict::new(py);
+        py.run(code, Some(&globals), None)
+            .map_err(|err| err.display(py))
+            .unwrap();
+    });
+}
+
+#[test]
+fn assemble_disassemble() {
+    run(c_str!(
+        r#"
+import intel_8080_kit as kit
+ops = kit.tokenize("mvi a, 5\nout 1\nhlt\n")
+assert repr(ops) == "[MviA(5), Out(1), Hlt]", ops
+assert [op.size() for op in ops] == [2, 2, 1]
+bin = kit.codegen(ops)
+assert bin == bytes([0x3e, 5, 0xd3, 1, 0x76])
+assert kit.disassemble(bin) == ops
+try:
+    kit.disassemble(bytes([0x3e]))
+    assert False
+except ValueError:
+    pass
+"#
+    ));
+}
+
+#[test]
+fn flat_ports() {
+    run(c_str!(
+        r#"
+import intel_8080_kit as kit
+out = []
+emu = kit.Emulator(cpu="8085", in_port=lambda port: port + 40, out_port=lambda port, byte: out.append((port, byte)))
+emu.load(kit.codegen(kit.tokenize("mvi a, 5\nout 1\nin 2\nmov b, a\nhlt\n")), 0x100)
+emu.pc = 0x100
+emu.write(0x200, b"\x01\x02")
+assert emu.run() == 7 + 10 + 10 + 4 + 5
+regs = emu.registers()
+assert (regs.a, regs.b, regs.pc) == (42, 42, 0x108)
+assert out == [(1, 5)]
+assert emu.read(0x1ff, 4) == b"\x00\x01\x02\x00"
+"#
+    ));
+}
+
+#[test]
+fn python_memory() {
+    run(c_str!(
+        r#"
+import intel_8080_kit as kit
+class Memory:
+    def __init__(self):
+        self.ram = bytearray(0x10000)
+        self.cycles = 0
+    def read_byte(self, addr): return self.ram[addr]
+    def write_byte(self, addr, byte): self.ram[addr] = byte
+    def in_port(self, port): raise RuntimeError("port %d" % port)
+    def out_port(self, port, byte): pass
+    def tick(self, cycles): self.cycles += cycles
+
+mem = Memory()
+emu = kit.Emulator(mem, cpu="z80")
+emu.load(kit.codegen(kit.tokenize("loop:\njmp loop\n")))
+assert emu.run(100) == 100 and mem.cycles == 100 and not emu.halted
+emu.load(bytes([0xdb, 0x07]))
+emu.pc = 0
+try:
+    emu.step()
+    assert False
+except RuntimeError as err:
+    assert str(err) == "port 7"
+"#
+    ));
+}
@@ -0,0 +1,223 @@
+use intel_8080_kit::emu::{
+    bus::Bus,
+    dev::{shared, Device, PortBus, I8253, I8259},
+    replay::{IoEvent, IoLog, Recorder, ReplayState, Replayer},
+    Emulator,
+};
+use std::{cell::RefCell, rc::Rc};
+
+/// Port returning a different value on every read.
+struct Noise(u8);
+
+impl Device for Noise {
+    fn ports(&self) -> u16 {
+        1
+    }
+
+    fn read(&mut self, _port: u8) -> u8 {
+        self.0 = self.0.wrapping_mul(5).wrapping_add(37);
+        self.0
+    }
+
+    fn write(&mut self, _port: u8, _byte: u8) {}
+}
+
+/// Stores 8 reads of port 0x20 at 0x200 while a timer interrupt counts at 0x300.
+fn board(seed: u8) -> Emulator {
+    let mut bus = Bus::new();
+    bus.map_ram(0, 0x1000);
+
+    #[rustfmt::skip]
+    bus.load(0, &[
+        0x31, 0x00, 0x10,
+        0x3e, 0x10, 0xd3, 0x43, 0x3e, 0x20, 0xd3, 0x40,
+        0xfb,
+        0x21, 0x00, 0x02,
+        0x0e, 0x08,
+        0xdb, 0x20, 0x77, 0x23, 0x0d, 0xc2, 0x11, 0x00,
+        0x76,
+    ]);
+    bus.load(
+        0x38,
+        &[
+            0xf5, 0x3a, 0x00, 0x03, 0x3c, 0x32, 0x00, 0x03, 0xf1, 0xfb, 0xc9,
+        ],
+    );
+
+    let pit = shared(I8253::new(1));
+    let mut bus = PortBus::new(Box::new(bus));
+    bus.map(0x40, pit.clone());
+    bus.map(0x20, shared(Noise(seed)));
+    if seed == 0 {
+        bus.connect_vector(pit, 0, 0x38);
+    }
+    Emulator::new(Box::new(bus))
+}
+
+fn snapshot(emu: &Emulator) -> (usize, Vec<u8>) {
+    let mem = emu.memory();
+    (
+        emu.cycles(),
+        (0..0x1000).map(|a| mem.read_byte(a)).collect(),
+    )
+}
+
+#[test]
+fn record_and_replay() {
+    let log = Rc::new(RefCell::new(IoLog::default()));
+    let mut emu = board(0);
+    emu.wrap_memory(|mem| Box::new(Recorder::new(mem, log.clone())));
+    emu.run();
+
+    let recorded = snapshot(&emu);
+    assert_eq!(recorded.1[0x300], 1);
+    assert_eq!(log.borrow().events.len(), 9);
+    assert!(matches!(
+        log.borrow().events[..],
+        [IoEvent::In { port: 0x20, .. }, ..]
+    ));
+
+    // Different input and no timer interrupt, the log supplies both
+    let text = log.borrow().to_string();
+    let state = Rc::new(RefCell::new(ReplayState::new(text.parse().unwrap())));
+    let mut emu = board(1);
+    emu.wrap_memory(|mem| Box::new(Replayer::new(mem, state.clone())));
+    emu.run();
+
+    assert!(state.borrow().diverged().is_none());
+    assert!(state.borrow().finished());
+    assert_eq!(snapshot(&emu), recorded);
+}
+
+#[test]
+fn divergence() {
+    let log: IoLog = "# cycle port value\nin 100 32 7\n".parse().unwrap();
+    let state = Rc::new(RefCell::new(ReplayState::new(log)));
+
+    let mut emu = board(1);
+    emu.wrap_memory(|mem| Box::new(Replayer::new(mem, state.clone())));
+    emu.run();
+
+    let state = state.borrow();
+    let (cycle, _) = state.diverged().unwrap();
+    assert!(*cycle < 100);
+    assert!(!state.finished());
+
+    assert_eq!("in 1 2\n".parse::<IoLog>().unwrap_err().0, 1);
+}
+
+/// Halts until the timer interrupt goes through the 8259 to 0x20, which
+/// stores 0x55 at 0x100 and halts again.
+fn pic_board() -> (Emulator, std::rc::Rc<RefCell<I8259>>) {
+    let mut bus = Bus::new();
+    bus.map_ram(0, 0x1000);
+
+    #[rustfmt::skip]
+    bus.load(0, &[
+        0x31, 0x00, 0x10,
+        0x3e, 0x36, 0xd3, 0x20, 0x3e, 0x00, 0xd3, 0x21, 0x3e, 0xfe, 0xd3, 0x21,
+        0x3e, 0x10, 0xd3, 0x43, 0x3e, 0x20, 0xd3, 0x40,
+        0xfb, 0x76,
+    ]);
+    bus.load(0x20, &[0x3e, 0x55, 0x32, 0x00, 0x01, 0x76]);
+
+    let pit = shared(I8253::new(1));
+    let pic = shared(I8259::new());
+    let mut bus = PortBus::new(Box::new(bus));
+    bus.map(0x40, pit.clone());
+    bus.map_pic(0x20, pic.clone());
+    bus.connect(pit, 0, 0);
+    (Emulator::new(Box::new(bus)), pic)
+}
+
+fn run_pic(emu: &mut Emulator) {
+    while emu.memory().read_byte(0x100) != 0x55 {
+        assert!(emu.cycles() < 1000);
+        emu.step();
+    }
+    emu.run();
+}
+
+#[test]
+fn pic_interrupt() {
+    let log = Rc::new(RefCell::new(IoLog::default()));
+    let (mut emu, pic) = pic_board();
+    emu.wrap_memory(|mem| Box::new(Recorder::new(mem, log.clone())));
+    run_pic(&mut emu);
+
+    let recorded = snapshot(&emu);
+    assert_eq!(recorded.1[0x100], 0x55);
+    assert!(!pic.borrow().int());
+    assert!(matches!(
+        log.borrow().events[..],
+        [IoEvent::Interrupt { vector: 0x20, .. }]
+    ));
+
+    // The 8259 is acknowledged when the logged interrupt is replayed
+    let state = Rc::new(RefCell::new(ReplayState::new(log.borrow().clone())));
+    let (mut emu, pic) = pic_board();
+    emu.wrap_memory(|mem| Box::new(Replayer::new(mem, state.clone())));
+    run_pic(&mut emu);
+
+    assert!(state.borrow().diverged().is_none());
+    assert!(state.borrow().finished());
+    assert!(!pic.borrow().int());
+    assert_eq!(snapshot(&emu), recorded);
+}
+
+#[test]
+fn host_interrupt() {
+    let board = || {
+        let mut bus = Bus::new();
+        bus.map_ram(0, 0x1000);
+        bus.load(0, &[0x31, 0x00, 0x10, 0xfb, 0x00, 0x76]);
+        bus.load(0x10, &[0x3e, 0x55, 0x32, 0x00, 0x01, 0x76]);
+        Emulator::new(Box::new(bus))
+    };
+
+    let log = Rc::new(RefCell::new(IoLog::default()));
+    let mut emu = board();
+    emu.wrap_memory(|mem| Box::new(Recorder::new(mem, log.clone())));
+    emu.interrupt(0x10);
+    emu.run();
+
+    let recorded = snapshot(&emu);
+    assert_eq!(recorded.1[0x100], 0x55);
+    assert!(matches!(
+        log.borrow().events[..],
+        [IoEvent::Interrupt { vector: 0x10, .. }]
+    ));
+
+    // Replayed from the log without the host raising it
+    let state = Rc::new(RefCell::new(ReplayState::new(log.borrow().clone())));
+    let mut emu = board();
+    emu.wrap_memory(|mem| Box::new(Replayer::new(mem, state.clone())));
+    emu.run();
+
+    assert!(state.borrow().diverged().is_none());
+    assert!(state.borrow().finished());
+    assert_eq!(snapshot(&emu), recorded);
+}
+
+#[test]
+fn exit_status() {
+    let dir = std::env::temp_dir().join("emu8080-replay-status");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("twice.bin"), [0xdb, 0x00, 0xdb, 0x00, 0x76]).unwrap();
+    std::fs::write(dir.join("once.bin"), [0xdb, 0x00, 0x76]).unwrap();
+    std::fs::write(dir.join("other.bin"), [0xdb, 0x01, 0xdb, 0x00, 0x76]).unwrap();
+
+    let status = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_emu8080"))
+            .current_dir(&dir)
+            .args(args)
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+    assert_eq!(status(&["--record", "twice.log", "twice.bin"]), Some(0));
+    assert_eq!(status(&["--replay", "twice.log", "twice.bin"]), Some(0));
+    assert_eq!(status(&["--replay", "twice.log", "other.bin"]), Some(1));
+    assert_eq!(status(&["--replay", "twice.log", "once.bin"]), Some(1));
+}
@@ -0,0 +1,142 @@
+use intel_8080_kit::{
+    asm::{codegen, diag::Severity, lexer::tokenize},
+    op::Opcode,
+};
+
+fn messages(src: &str) -> Vec<(Severity, usize, String)> {
+    tokenize(src)
+        .unwrap_err()
+        .into_iter()
+        .map(|d| (d.severity, d.line, d.message))
+        .collect()
+}
+
+#[test]
+fn equ() {
+    let src = "\
+        CR EQU 0DH\n\
+        LF: equ CR - 3\n\
+        BUF equ 2000H\n\
+        VEC equ 7\n\
+        mvi a, CR\n\
+        mvi b, LF\n\
+        lxi h, BUF + 1\n\
+        lda BUF\n\
+        rst VEC\n\
+        db CR, LF\n";
+
+    assert_eq!(
+        tokenize(src).unwrap(),
+        [
+            Opcode::MviA(0x0d),
+            Opcode::MviB(0x0a),
+            Opcode::LxiH(0x20, 0x01),
+            Opcode::Lda(0x2000),
+            Opcode::Rst7,
+            Opcode::Db(0x0d),
+            Opcode::Db(0x0a),
+        ]
+    );
+}
+
+#[test]
+fn set() {
+    let src = "\
+        n set 1\n\
+        db n\n\
+        n set n + 1\n\
+        db n, later\n\
+        n set n * 10\n\
+        db n\n\
+        later equ n\n";
+    assert_eq!(codegen(&tokenize(src).unwrap()), [1, 2, 20, 20]);
+}
+
+#[test]
+fn mixed_case() {
+    let src = "\
+        Loop: dcr b\n\
+        \x20     jnz loop\n\
+        Size EQU 3\n\
+        \x20     mvi a, SIZE\n\
+        n set 1\n\
+        N set N + 1\n\
+        \x20     db n\n\
+        \x20     ifdef LOOP\n\
+        \x20     db 0aah\n\
+        \x20     endif\n\
+        m macro Reg\n\
+        \x20     mov a, REG\n\
+        \x20     endm\n\
+        \x20     M b\n";
+    assert_eq!(
+        codegen(&tokenize(src).unwrap()),
+        [0x05, 0xc2, 0x00, 0x00, 0x3e, 0x03, 0x02, 0xaa, 0x78]
+    );
+
+    let src = "Start:\nstart:\n";
+    assert_eq!(
+        messages(src),
+        [
+            (Severity::Error, 2, "label `start` redefined".to_string()),
+            (Severity::Note, 1, "first defined here".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn register_names() {
+    let src = "b equ 5\nPSW: nop\nsp set 1\n\tmov a,b\n";
+    assert_eq!(
+        messages(src),
+        [
+            (
+                Severity::Error,
+                1,
+                "`b` is a register and can't be a symbol".to_string()
+            ),
+            (
+                Severity::Error,
+                2,
+                "`PSW` is a register and can't be a symbol".to_string()
+            ),
+            (
+                Severity::Error,
+                3,
+                "`sp` is a register and can't be a symbol".to_string()
+            ),
+        ]
+    );
+    assert_eq!(tokenize("bc equ 5\n\tmov a,b\n").unwrap(), [Opcode::MovAB]);
+}
+
+#[test]
+fn redefinitions() {
+    let src = "x equ 1\nx equ 2\ny set 1\ny equ 2\nstart:\nstart set 3\n";
+    assert_eq!(
+        messages(src),
+        [
+            (Severity::Error, 2, "symbol `x` redefined".into()),
+            (Severity::Note, 1, "first defined here".into()),
+            (Severity::Error, 4, "symbol `y` redefined".into()),
+            (Severity::Note, 3, "first defined here".into()),
+            (Severity::Error, 6, "symbol `start` redefined".into()),
+            (Severity::Note, 5, "first defined here".into()),
+        ]
+    );
+}
+
+#[test]
+fn forward_references() {
+    assert_eq!(
+        messages("mvi a, n\nn set 1\n"),
+        [(Severity::Error, 1, "`n` is used before its `set`".into())]
+    );
+    assert_eq!(
+        messages("equ 1\nx equ\n"),
+        [
+            (Severity::Error, 1, "`equ` needs a symbol name".into()),
+            (Severity::Error, 2, "`equ` takes 1 operand, found 0".into()),
+        ]
+    );
+}
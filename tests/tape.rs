@@ -0,0 +1,145 @@
+use intel_8080_kit::emu::{
+    bus::Bus,
+    dev::{
+        shared,
+        tape::{kcs_decode, kcs_encode, punch, unpunch, TapeMode, TapePorts, KCS_RATE},
+        Device, PortBus, Tape,
+    },
+    machine::{Description, Machine},
+    Emulator,
+};
+use std::{env, fs};
+
+#[test]
+fn byte_copy() {
+    let mut bus = Bus::new();
+    bus.map_ram(0, 0x100);
+
+    // Punches every byte read until the status reports an empty reader
+    #[rustfmt::skip]
+    bus.load(0, &[
+        0xdb, 0x06, 0xe6, 0x01, 0xc2, 0x0e, 0x00,
+        0xdb, 0x07, 0xd3, 0x07,
+        0xc3, 0x00, 0x00,
+        0x76,
+    ]);
+
+    let tape = shared(Tape::new(TapePorts::default(), TapeMode::Byte));
+    tape.borrow_mut().insert(b"LOAD".to_vec());
+
+    let mut bus = PortBus::new(Box::new(bus));
+    bus.map(6, tape.clone());
+    let mut emu = Emulator::new(Box::new(bus));
+    emu.run();
+
+    assert_eq!(tape.borrow().punched(), b"LOAD");
+}
+
+#[test]
+fn bit_serial() {
+    // 10 cycles per bit
+    let mode = TapeMode::Bit {
+        baud: 100,
+        clock: 1000,
+    };
+    let mut tape = Tape::new(TapePorts::default(), mode);
+    tape.insert(vec![0xa5]);
+
+    let mut line = Vec::new();
+    for _ in 0..40 {
+        line.push(tape.read(1) == 1);
+        tape.tick(10);
+    }
+    assert!(line[..20].iter().all(|bit| *bit));
+    assert_eq!(
+        line[20..31],
+        [false, true, false, true, false, false, true, false, true, true, true]
+    );
+
+    let mut tape = Tape::new(TapePorts::default(), mode);
+    for bit in [
+        false, true, true, false, false, false, false, true, false, true, true,
+    ] {
+        tape.write(1, bit as u8);
+        tape.tick(10);
+    }
+    tape.tick(100);
+    assert_eq!(tape.punched(), [0x43]);
+}
+
+#[test]
+fn bit_status() {
+    let mode = TapeMode::Bit {
+        baud: 100,
+        clock: 1000,
+    };
+    let ports = TapePorts {
+        data: 255,
+        ..TapePorts::default()
+    };
+    let mut tape = Tape::new(ports, mode);
+    assert_eq!(tape.ports(), 256);
+    tape.insert(vec![0xa5]);
+
+    // Active low, the reader is ready until the leader and the frame passed
+    let mut ready = Vec::new();
+    for _ in 0..40 {
+        tape.read(255);
+        ready.push(tape.read(0) & 0x01 == 0);
+        tape.tick(10);
+    }
+    assert!(ready[..31].iter().all(|ready| *ready));
+    assert!(ready[31..].iter().all(|ready| !*ready));
+}
+
+#[test]
+fn media() {
+    let data: Vec<u8> = (0..=255).collect();
+
+    let samples = kcs_encode(&data, KCS_RATE);
+    assert_eq!(samples.len(), (20 + 256 * 11 + 20) * 160);
+    assert_eq!(kcs_decode(&samples, KCS_RATE), data);
+    assert_eq!(kcs_decode(&kcs_encode(b"CSAVE", 44100), 44100), b"CSAVE");
+
+    let text = punch(&[0x00, 0xff, 0x48]);
+    assert_eq!(text, "|     .   |\n|ooooo.ooo|\n| o  o.   |\n");
+    assert_eq!(unpunch(&format!("leader\n{}", text)), [0x00, 0xff, 0x48]);
+}
+
+#[test]
+fn tape_description() {
+    let dir = env::temp_dir().join(format!("tape-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("in.tape"), punch(b"HI")).unwrap();
+
+    let desc = Description::from_toml(
+        r#"
+        [[memory]]
+        kind = "ram"
+        start = 0
+        size = 0x100
+
+        [[device]]
+        name = "acr"
+        kind = "tape"
+        port = 6
+        input = "in.tape"
+        output = "out.wav"
+        "#,
+    )
+    .unwrap();
+
+    let mut machine = Machine::build(&desc, &dir).unwrap();
+    let mem = machine.emulator().memory_mut();
+    assert_eq!(mem.in_port(6) & 0x01, 0);
+    assert_eq!(mem.in_port(7), b'H');
+    mem.out_port(7, b'O');
+    mem.out_port(7, b'K');
+
+    machine.save_tapes().unwrap();
+    let (rate, samples) =
+        intel_8080_kit::emu::audio::read_wav(fs::File::open(dir.join("out.wav")).unwrap()).unwrap();
+    assert_eq!(kcs_decode(&samples, rate), b"OK");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
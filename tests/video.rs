@@ -0,0 +1,147 @@
+use intel_8080_kit::emu::{
+    bus::Bus,
+    machine::{Description, Machine},
+    video::{
+        write_ppm, Animation, Bitmap, Frame, FrameSink, Framebuffer, ImageFormat, Sequence, Video,
+    },
+    Emulator,
+};
+use std::{env, fs, path::Path};
+
+#[derive(Default)]
+struct Frames(Vec<Frame>);
+
+impl FrameSink for Frames {
+    fn frame(&mut self, frame: &Frame) -> std::io::Result<()> {
+        self.0.push(frame.clone());
+        Ok(())
+    }
+}
+
+#[test]
+fn bitmap_layout() {
+    let mut bus = Bus::new();
+    bus.map_ram(0x0000, 0x100);
+    bus.load(0x10, &[0x01, 0x80]);
+    let mut frame = Frame::new(16, 2);
+
+    let mut fb = Bitmap::new(0x10, 16, 2);
+    fb.render(&bus, &mut frame);
+    assert_eq!(frame.pixel(0, 0), [0xff; 3]);
+    assert_eq!(frame.pixel(15, 0), [0xff; 3]);
+    assert_eq!(frame.pixel(1, 0), [0; 3]);
+
+    let mut frame = Frame::new(2, 16);
+    let mut fb = Bitmap::new(0x10, 2, 16);
+    fb.rotate = true;
+    fb.on = [0, 0xff, 0];
+    fb.render(&bus, &mut frame);
+    assert_eq!(frame.pixel(0, 15), [0, 0xff, 0]);
+    assert_eq!(frame.pixel(0, 0), [0, 0xff, 0]);
+    assert_eq!(frame.pixel(1, 0), [0; 3]);
+}
+
+#[test]
+fn vblank_interrupts() {
+    let mut bus = Bus::new();
+    bus.map_ram(0x0000, 0x1000);
+
+    #[rustfmt::skip]
+    bus.load(0, &[
+        0x31, 0x00, 0x10,
+        0xfb,
+        0xc3, 0x04, 0x00,
+    ]);
+    // Counts interrupts in the first byte of the bitmap
+    bus.load(
+        0x38,
+        &[
+            0xf5, 0x3a, 0x00, 0x08, 0x3c, 0x32, 0x00, 0x08, 0xf1, 0xfb, 0xc9,
+        ],
+    );
+
+    let mut emu = Emulator::new(Box::new(bus));
+    let mut video = Video::new(Box::new(Bitmap::new(0x0800, 8, 1)), 600_000, 60);
+    video.on_cycle(5000, 0x38);
+    video.on_vblank(0x38);
+    assert_eq!(video.period(), 10_000);
+
+    let mut frames = Frames::default();
+    video.run(&mut emu, 3, &mut frames).unwrap();
+
+    assert_eq!(frames.0.len(), 3);
+    assert_eq!(emu.memory().read_byte(0x0800), 5);
+    assert!(emu.cycles() >= 30_000 && emu.cycles() < 30_100);
+    // The vblank interrupt is served after the frame is rendered
+    assert_eq!(frames.0[0].pixel(0, 0), [0xff; 3]);
+    assert_eq!(frames.0[1].pixel(0, 0), [0xff; 3]);
+    assert_eq!(frames.0[1].pixel(1, 0), [0xff; 3]);
+}
+
+#[test]
+fn image_files() {
+    let mut frame = Frame::new(3, 2);
+    frame.set_pixel(2, 1, [1, 2, 3]);
+
+    let mut ppm = Vec::new();
+    write_ppm(&mut ppm, &frame).unwrap();
+    assert!(ppm.starts_with(b"P6\n3 2\n255\n"));
+    assert_eq!(ppm.len(), 11 + 18);
+    assert_eq!(&ppm[ppm.len() - 3..], [1, 2, 3]);
+
+    let dir = env::temp_dir().join(format!("video-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut seq = Sequence::new(dir.join("shot"), ImageFormat::Png);
+    seq.frame(&frame).unwrap();
+    seq.frame(&frame).unwrap();
+    assert_eq!(seq.count(), 2);
+    assert!(fs::read(dir.join("shot00001.png"))
+        .unwrap()
+        .starts_with(b"\x89PNG"));
+
+    let mut anim = Animation::new(dir.join("anim.apng"), 30);
+    anim.frame(&frame).unwrap();
+    anim.frame(&frame).unwrap();
+    anim.finish().unwrap();
+    let apng = fs::read(dir.join("anim.apng")).unwrap();
+    assert!(apng.windows(4).any(|chunk| chunk == b"acTL"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn video_description() {
+    let desc = Description::from_toml(
+        r#"
+        clock = 1996800
+
+        [[memory]]
+        kind = "ram"
+        start = 0x2000
+        size = 0x2000
+
+        [video]
+        base = 0x2400
+        width = 224
+        height = 256
+        rotate = true
+
+        [[video.interrupt]]
+        cycle = 16640
+        vector = 0x08
+
+        [[video.interrupt]]
+        vector = 0x10
+        "#,
+    )
+    .unwrap();
+
+    let machine = Machine::build(&desc, Path::new("tests")).unwrap();
+    let video = machine.video().unwrap();
+    assert_eq!(video.period(), 33280);
+    assert_eq!((video.frame().width(), video.frame().height()), (224, 256));
+
+    let desc = Description::from_toml("[video]\nbase = 0\nwidth = 8\nheight = 8").unwrap();
+    assert!(Machine::build(&desc, Path::new("tests")).is_err());
+}
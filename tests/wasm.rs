@@ -0,0 +1,78 @@
+#![cfg(feature = "wasm")]
+
+use intel_8080_kit::wasm::{assemble, disassemble, WasmEmulator};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_test::wasm_bindgen_test as test;
+
+const SRC: &str = "	mvi\ta, 5\n\tout\t1\n\tin\t2\n\tmov\tb, a\n\thlt\n";
+
+#[test]
+fn assemble_disassemble() {
+    let bin = assemble(SRC).unwrap();
+    assert_eq!(bin, [0x3e, 0x05, 0xd3, 0x01, 0xdb, 0x02, 0x47, 0x76]);
+
+    let out = disassemble(&bin).unwrap();
+    assert_eq!(out.lines().count(), 5);
+    assert!(out.starts_with("0000"));
+}
+
+#[test]
+fn budget() {
+    let mut emu = WasmEmulator::new("8080").unwrap();
+    emu.load(&assemble("loop:\n\tjmp\tloop\n").unwrap(), 0);
+
+    assert_eq!(emu.run(100).unwrap(), 100);
+    assert_eq!(emu.run(15).unwrap(), 20);
+    assert!(!emu.halted());
+    assert_eq!(emu.cycles(), 120);
+}
+
+#[test]
+fn registers_memory() {
+    let mut emu = WasmEmulator::new("z80").unwrap();
+    emu.load(&assemble(SRC).unwrap(), 0x100);
+    emu.set_pc(0x100);
+    emu.write(0x200, &[1, 2, 3]);
+
+    emu.run(1000).unwrap();
+    assert!(emu.halted());
+
+    let regs = emu.registers();
+    assert_eq!((regs.a, regs.b, regs.sp, regs.pc), (0xff, 0xff, 0, 0x108));
+    assert_eq!(emu.read(0x1ff, 5), [0, 1, 2, 3, 0]);
+}
+
+#[cfg(target_arch = "wasm32")]
+#[test]
+fn port_hooks() {
+    use js_sys::{Array, Function};
+
+    let out = Array::new();
+    let push = Function::new_with_args("port, byte", "this.push([port, byte])").bind(&out);
+    let input = Function::new_with_args("port", "return port + 40");
+
+    let mut emu = WasmEmulator::new("8085").unwrap();
+    emu.load(&assemble(SRC).unwrap(), 0);
+    emu.on_output(Some(push));
+    emu.on_input(Some(input));
+    emu.run(1000).unwrap();
+
+    assert_eq!(emu.registers().b, 42);
+    assert_eq!(out.length(), 1);
+    assert_eq!(format!("{:?}", out.get(0)), "JsValue([1, 5])");
+}
+
+#[cfg(target_arch = "wasm32")]
+#[test]
+fn hook_error() {
+    use js_sys::Function;
+
+    let mut emu = WasmEmulator::new("8080").unwrap();
+    emu.load(&assemble(SRC).unwrap(), 0);
+    emu.on_output(Some(Function::new_with_args("port, byte", "throw 'full'")));
+
+    let err = emu.run(1000).unwrap_err();
+    assert_eq!(err.as_string().as_deref(), Some("full"));
+    assert_eq!(emu.pc(), 4);
+    assert!(!emu.halted());
+}
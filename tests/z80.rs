@@ -0,0 +1,128 @@
+use intel_8080_kit::emu::{bus::Bus, Cpu, Emulator};
+
+fn board(cpu: Cpu, code: &[(u16, &[u8])]) -> Emulator {
+    let mut bus = Bus::new();
+    bus.map_ram(0, 0x1000);
+    for (addr, bytes) in code {
+        bus.load(*addr, bytes);
+    }
+    Emulator::with_cpu(Box::new(bus), cpu)
+}
+
+fn byte(emu: &Emulator, addr: u16) -> u8 {
+    emu.memory().read_byte(addr)
+}
+
+#[test]
+fn prefixes() {
+    #[rustfmt::skip]
+    let code: &[u8] = &[
+        // LD IX,0x200; LD (IX+2),0x55; LD A,(IX+2); INC (IX+2); SET 7,(IX+2)
+        0xdd, 0x21, 0x00, 0x02, 0xdd, 0x36, 0x02, 0x55, 0xdd, 0x7e, 0x02,
+        0xdd, 0x34, 0x02, 0xdd, 0xcb, 0x02, 0xfe,
+        // LD IY,0x210; LD (IY-1),A
+        0xfd, 0x21, 0x10, 0x02, 0xfd, 0x77, 0xff,
+        // LD HL,0x200; LD DE,0x300; LD BC,3; LDIR
+        0x21, 0x00, 0x02, 0x11, 0x00, 0x03, 0x01, 0x03, 0x00, 0xed, 0xb0,
+        // LD A,1; NEG; LD (0x304),A
+        0x3e, 0x01, 0xed, 0x44, 0x32, 0x04, 0x03,
+        // LD B,5; XOR A; INC A; DJNZ -3; LD (0x305),A
+        0x06, 0x05, 0xaf, 0x3c, 0x10, 0xfd, 0x32, 0x05, 0x03,
+        // LD BC,0x1111; EXX; LD BC,0x2222; EXX; LD (0x306),BC
+        0x01, 0x11, 0x11, 0xd9, 0x01, 0x22, 0x22, 0xd9, 0xed, 0x43, 0x06, 0x03,
+        // JR +1; HALT; HALT
+        0x18, 0x01, 0x76, 0x76,
+    ];
+
+    let mut emu = board(Cpu::Z80, &[(0, code)]);
+    emu.run();
+    assert_eq!(byte(&emu, 0x202), 0xd6);
+    assert_eq!(byte(&emu, 0x20f), 0x55);
+    assert_eq!(
+        [0x300, 0x301, 0x302].map(|addr| byte(&emu, addr)),
+        [0x00, 0x00, 0xd6]
+    );
+    assert_eq!(byte(&emu, 0x304), 0xff);
+    assert_eq!(byte(&emu, 0x305), 5);
+    assert_eq!(emu.memory().read_word(0x306), 0x1111);
+    assert_eq!(emu.pc(), code.len() as u16);
+}
+
+#[test]
+fn flags() {
+    #[rustfmt::skip]
+    let code: &[u8] = &[
+        0x31, 0x00, 0x10,
+        // ADD 0x7f + 1, SUB 0x10 - 1
+        0x3e, 0x7f, 0xc6, 0x01, 0xf5, 0xc1, 0x79, 0x32, 0x00, 0x02,
+        0x3e, 0x10, 0xd6, 0x01, 0xf5, 0xc1, 0x79, 0x32, 0x01, 0x02,
+        0x76,
+    ];
+
+    let mut emu = board(Cpu::I8080, &[(0, code)]);
+    emu.run();
+    assert_eq!([byte(&emu, 0x200), byte(&emu, 0x201)], [0x92, 0x06]);
+
+    // Overflow instead of parity, borrow as half carry, add/subtract flag
+    let mut emu = board(Cpu::Z80, &[(0, code)]);
+    emu.run();
+    assert_eq!([byte(&emu, 0x200), byte(&emu, 0x201)], [0x94, 0x12]);
+}
+
+#[test]
+fn interrupt_modes() {
+    #[rustfmt::skip]
+    let code: &[(u16, &[u8])] = &[
+        (0, &[0x31, 0x00, 0x10, 0xc3, 0x40, 0x00]),
+        (0x38, &[0x3e, 0x38, 0x32, 0x00, 0x02, 0x76]),
+        (0x50, &[0x3e, 0x50, 0x32, 0x00, 0x02, 0x76]),
+        (0x66, &[0x3e, 0x66, 0x32, 0x00, 0x02, 0x76]),
+        (0x310, &[0x50, 0x00]),
+    ];
+
+    // IM 1; EI; HALT
+    let mut emu = board(Cpu::Z80, code);
+    emu.memory_mut().write_word(0x40, 0x56ed);
+    emu.memory_mut().write_word(0x42, 0x76fb);
+    emu.run();
+    emu.interrupt(0x08);
+    emu.step();
+    emu.run();
+    assert_eq!(byte(&emu, 0x200), 0x38);
+
+    // LD A,3; LD I,A; IM 2; EI; HALT
+    let mut emu = board(Cpu::Z80, code);
+    for (i, byte) in [0x3e, 0x03, 0xed, 0x47, 0xed, 0x5e, 0xfb, 0x76]
+        .iter()
+        .enumerate()
+    {
+        emu.memory_mut().write_byte(0x40 + i as u16, *byte);
+    }
+    emu.run();
+    emu.interrupt(0x10);
+    emu.step();
+    emu.run();
+    assert_eq!(byte(&emu, 0x200), 0x50);
+
+    // DI; HALT, only the nmi gets through
+    let mut emu = board(Cpu::Z80, code);
+    emu.memory_mut().write_word(0x40, 0x76f3);
+    emu.run();
+    emu.interrupt(0x08);
+    emu.step();
+    assert!(emu.halted());
+    emu.nmi();
+    emu.step();
+    emu.run();
+    assert_eq!(byte(&emu, 0x200), 0x66);
+}
+
+#[test]
+fn cycles() {
+    // LD IX,0; LD A,(IX+0); DJNZ taken then not; HALT
+    let code: &[u8] = &[0xdd, 0x21, 0, 0, 0xdd, 0x7e, 0, 0x06, 2, 0x10, 0xfe, 0x76];
+
+    let mut emu = board(Cpu::Z80, &[(0, code)]);
+    emu.run();
+    assert_eq!(emu.cycles(), 14 + 19 + 7 + 13 + 8 + 4);
+}
@@ -52,7 +52,7 @@ def main():
             f2.write(f"{' ' * 4}while i < bin.len() {{\n")
             f2.write(f"{' ' * 4 * 2}ops.push(match bin[i] {{\n")
 
-            o.write("use std::{fmt, mem};\n\n")
+            o.write("use core::{fmt, mem};\n\n")
             o.write("#[allow(non_camel_case_types)]\n")
             o.write("#[repr(u8)]\n")
             o.write("#[derive(Debug, Clone, Copy, PartialEq, Eq)]\n")
@@ -129,7 +129,9 @@ def main():
             o.write(f"{' ' * 4 * 3}0xd9 => RawOpcode::RET,\n")
             o.write(f"{' ' * 4 * 3}0xdd | 0xed | 0xfd => RawOpcode::CALL,\n")
             o.write(f"{' ' * 4 * 3}0xcb => RawOpcode::JMP,\n")
-            o.write(f"{' ' * 4 * 3}_ => unsafe {{ mem::transmute(t) }},\n")
+            o.write(
+                f"{' ' * 4 * 3}_ => unsafe {{ mem::transmute::<u8, {raw_opcode}>(t) }},\n"
+            )
             o.write(f"{' ' * 4 * 2}}}\n{' ' * 4}}}\n}}\n\n")
 
             o.write(f"impl From<&u8> for {raw_opcode} {{\n")
@@ -137,9 +139,9 @@ def main():
             o.write(f"{' ' * 4 * 2}From::from(*t)\n")
             o.write(f"{' ' * 4}}}\n}}\n\n")
 
-            o.write(f"impl Into<u8> for {raw_opcode} {{\n")
-            o.write(f"{' ' * 4}fn into(self) -> u8 {{\n")
-            o.write(f"{' ' * 4 * 2}unsafe {{ mem::transmute(self) }}\n")
+            o.write(f"impl From<{raw_opcode}> for u8 {{\n")
+            o.write(f"{' ' * 4}fn from(t: {raw_opcode}) -> u8 {{\n")
+            o.write(f"{' ' * 4 * 2}t as u8\n")
             o.write(f"{' ' * 4}}}\n}}\n\n")
 
             o.write(f"impl fmt::Display for {raw_opcode} {{\n")
@@ -231,6 +233,8 @@ def main():
             f2.write(f"{' ' * 4 * 2}}});\n")
             f2.write(f"{' ' * 4}}}\n\n{' ' * 4}Ok(ops)\n}}\n")
 
+        o.write(f"{' ' * 4}/// Raw data byte, from the `db`, `dw` and `ds` directives.\n")
+        o.write(f"{' ' * 4}Db(u8),\n")
         o.write("}\n\n")
         o.write(f"impl {wrap_opcode} {{\n")
         o.write(f"{' ' * 4}pub fn size(&self) -> usize {{\n")
@@ -260,11 +264,14 @@ def main():
 
                 o.write(f"{' ' * 4 * 3}{wrap_opcode}::{op} => {m[2]},\n")
 
+        o.write(f"{' ' * 4 * 3}{wrap_opcode}::Db(_) => 1,\n")
         o.write(f"{' ' * 4 * 2}}}\n{' ' * 4}}}\n}}\n")
 
     with open(asm_output, "w") as f:
         f.write(header.format(asm_output, op_input))
-        f.write("use super::op::*;\npub mod lexer;\n\n")
+        f.write(
+            "use super::op::*;\npub mod assembler;\npub mod diag;\npub mod expr;\npub mod lexer;\npub mod listing;\npub mod macros;\npub mod parser;\npub mod source;\n\n"
+        )
 
         f.write(f"pub fn codegen(ops: &[{wrap_opcode}]) -> Vec<u8> {{\n")
         f.write(f"{' ' * 4}let mut bin = Vec::new();\n\n")
@@ -319,8 +326,61 @@ def main():
                 else:
                     f.write(f"{' ' * 4 * 4}bin.push({m[0]}u8);\n{' ' * 4 * 3}}}\n")
 
+        f.write(f"{' ' * 4 * 3}{wrap_opcode}::Db(b) => {{\n")
+        f.write(f"{' ' * 4 * 4}bin.push(b);\n{' ' * 4 * 3}}}\n")
         f.write(f"{' ' * 4 * 2}}}\n{' ' * 4 * 2}i += 1;\n")
-        f.write(f"{' ' * 4}}}\n\n{' ' * 4}bin\n}}\n")
+        f.write(f"{' ' * 4}}}\n\n{' ' * 4}bin\n}}\n\n")
+
+        f.write(
+            "/// Mnemonic, operands and opcode of every instruction, `d8`, `d16` and\n"
+            "/// `adr` are the data and address operands.\n"
+        )
+        f.write("pub const INSTRUCTIONS: &[(&str, &[&str], u8)] = &[\n")
+
+        for m in match:
+            if m[1] != "":
+                words = m[1].split(None, 1)
+                operands = []
+                if len(words) > 1:
+                    operands = [w.strip().lower() for w in words[1].split(",")]
+
+                operands = ", ".join(f'"{w}"' for w in operands)
+                f.write(f'{" " * 4}("{words[0].lower()}", &[{operands}], {m[0]}),\n')
+
+        f.write("];\n\n")
+
+        f.write(
+            f"/// Instruction with opcode `op` and `data` as its data or address operand.\n"
+        )
+        f.write(f"pub fn opcode(op: u8, data: u16) -> {wrap_opcode} {{\n")
+        f.write(f"{' ' * 4}match op {{\n")
+
+        for m in match:
+            if m[1] != "":
+                op = m[1].replace(" ", "_").replace(",", "_").lower()
+                op = humps.pascalize(op)
+
+                op = op.replace("__D16", "((data >> 8) as u8, data as u8)")
+                op = op.replace("_D16", "((data >> 8) as u8, data as u8)")
+                op = op.replace("D16", "((data >> 8) as u8, data as u8)")
+                op = op.replace("__D8", "(data as u8)")
+                op = op.replace("_D8", "(data as u8)")
+                op = op.replace("D8", "(data as u8)")
+                op = op.replace("Adr", "(data)")
+
+                op = op.replace("_B", "B")
+                op = op.replace("_C", "C")
+                op = op.replace("_D", "D")
+                op = op.replace("_E", "E")
+                op = op.replace("_H", "H")
+                op = op.replace("_L", "L")
+                op = op.replace("_M", "M")
+                op = op.replace("_A", "A")
+
+                f.write(f"{' ' * 4 * 2}{m[0]} => {wrap_opcode}::{op},\n")
+
+        f.write(f"{' ' * 4 * 2}_ => {wrap_opcode}::Nop,\n")
+        f.write(f"{' ' * 4}}}\n}}\n")
 
 
 if __name__ == "__main__":